@@ -1,8 +1,8 @@
 //! Generated by capsule
 //!
 //! `main.rs` is used to define rust lang items and modules.
-//! See `entry.rs` for the `main` function. 
-//! See `error.rs` for the `Error` type.
+//! The verification logic lives in `eth-light-client-typescript-lib`, see
+//! `EthLightClientTypescript`.
 
 #![no_std]
 #![no_main]
@@ -10,23 +10,4 @@
 #![feature(alloc_error_handler)]
 #![feature(panic_info_message)]
 
-// define modules
-mod entry;
-mod error;
-
-use ckb_std::{
-    default_alloc,
-};
-
-ckb_std::entry!(program_entry);
-default_alloc!();
-
-/// program entry
-fn program_entry() -> i8 {
-    // Call main function and return error code
-    match entry::main() {
-        Ok(_) => 0,
-        Err(err) => err as i8,
-    }
-}
-
+ckb_env::contract_entry!(eth_light_client_typescript_lib::EthLightClientTypescript);
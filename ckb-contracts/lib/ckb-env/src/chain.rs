@@ -1,11 +1,149 @@
-use crate::traits::CkbChainInterface;
+use crate::traits::{
+    CkbChainInterface, CkbSyscallInterface, DynamicLoadingInterface, LibraryInterface,
+};
+use alloc::string::String;
+use ckb_std::ckb_constants::{CellField, HeaderField, InputField, Source};
+use ckb_std::dynamic_loading::{CKBDLContext, Error as DlError, Library};
 use ckb_std::error::SysError;
-use ckb_std::high_level::load_tx_hash;
+use ckb_std::syscalls;
+use core::mem::MaybeUninit;
+
+/// Size of the memory shared libraries are loaded into, it must be a multiple of the page size
+const DL_CONTEXT_SIZE: usize = 128 * 1024;
+
+/// Libraries are loaded one after another into the same context. It is never freed since the
+/// code of a loaded library must stay in place, and scripts are single threaded.
+static mut DL_CONTEXT: MaybeUninit<CKBDLContext<[u8; DL_CONTEXT_SIZE]>> = MaybeUninit::uninit();
+static mut DL_CONTEXT_OFFSET: usize = 0;
 
 pub struct CKBChain {}
 
-impl CkbChainInterface for CKBChain {
-    fn load_tx_hash(&self) -> Result<[u8; 32], SysError> {
-        load_tx_hash()
+impl CkbSyscallInterface for CKBChain {
+    fn sys_debug(&self, message: String) {
+        syscalls::debug(message)
+    }
+
+    fn sys_load_tx_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        syscalls::load_tx_hash(buf, offset)
+    }
+
+    fn sys_load_script_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        syscalls::load_script_hash(buf, offset)
+    }
+
+    fn sys_load_script(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        syscalls::load_script(buf, offset)
+    }
+
+    fn sys_load_transaction(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        syscalls::load_transaction(buf, offset)
+    }
+
+    fn sys_load_cell(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        syscalls::load_cell(buf, offset, index, source)
+    }
+
+    fn sys_load_cell_data(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        syscalls::load_cell_data(buf, offset, index, source)
+    }
+
+    fn sys_load_cell_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: CellField,
+    ) -> Result<usize, SysError> {
+        syscalls::load_cell_by_field(buf, offset, index, source, field)
+    }
+
+    fn sys_load_input(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        syscalls::load_input(buf, offset, index, source)
+    }
+
+    fn sys_load_input_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: InputField,
+    ) -> Result<usize, SysError> {
+        syscalls::load_input_by_field(buf, offset, index, source, field)
+    }
+
+    fn sys_load_header(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        syscalls::load_header(buf, offset, index, source)
+    }
+
+    fn sys_load_header_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: HeaderField,
+    ) -> Result<usize, SysError> {
+        syscalls::load_header_by_field(buf, offset, index, source, field)
+    }
+
+    fn sys_load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        syscalls::load_witness(buf, offset, index, source)
+    }
+}
+
+impl CkbChainInterface for CKBChain {}
+
+impl DynamicLoadingInterface for CKBChain {
+    type Library = Library;
+
+    fn load_library(&self, dep_cell_data_hash: &[u8; 32]) -> Result<Library, DlError> {
+        unsafe {
+            let context = &mut *DL_CONTEXT.as_mut_ptr();
+            let library = context.load_with_offset(
+                dep_cell_data_hash,
+                DL_CONTEXT_OFFSET,
+                DL_CONTEXT_SIZE - DL_CONTEXT_OFFSET,
+            )?;
+            DL_CONTEXT_OFFSET += library.consumed_size();
+            Ok(library)
+        }
+    }
+}
+
+impl LibraryInterface for Library {
+    unsafe fn get<S: Copy>(&self, symbol: &[u8]) -> Option<S> {
+        Library::get::<S>(self, symbol).map(|symbol| *symbol)
     }
 }
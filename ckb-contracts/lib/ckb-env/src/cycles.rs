@@ -0,0 +1,84 @@
+//! Cycle cost model used to estimate how much a verification costs in ckb-vm when it runs
+//! natively against `mock::MockCKBChain`.
+//!
+//! Syscalls are charged like the VM does: a fixed cost for the `ecall` instruction plus the
+//! bytes copied into the script memory. Everything else a script does is plain computation,
+//! which is only visible to the model when library code marks it with
+//! `CkbChainInterface::charge_cycles`. The syscall costs are the ones of the cost model of
+//! ckb-script. The costs of `Operation` are estimates, not measurements in ckb-vm: the doc of
+//! every constant says where its figure comes from, and all of them are rounded up.
+//! `test_submit_full_branch` and the sealed-branch tests of the tests crate run full branches in
+//! ckb-vm to check the estimates against the real cost.
+
+/// Cycles of the `ecall` instruction which every syscall executes, as ckb-script charges it
+pub const SYSCALL_CYCLES: u64 = 500;
+/// Data copied into the script memory by a syscall costs 1 cycle per 4 bytes
+pub const BYTES_PER_CYCLE: u64 = 4;
+
+/// Cycles of one keccak-f[1600] permutation, estimated from its 24 rounds of about 450
+/// riscv64 instructions each
+pub const KECCAK_PERMUTATION_CYCLES: u64 = 12_000;
+/// keccak256 absorbs 136 bytes per permutation
+pub const KECCAK256_RATE: usize = 136;
+/// keccak512 absorbs 72 bytes per permutation
+pub const KECCAK512_RATE: usize = 72;
+/// Cycles of ethash hashimoto, including the seed and result hashes but not the verification
+/// of the dataset items it reads, estimated as two keccak permutations and the FNV mixing of
+/// the 64 dataset items of 32 words each
+pub const HASHIMOTO_CYCLES: u64 = 90_000;
+/// Fixed cycles of decoding a merkle patricia trie node, estimated from the RLP list header
+/// and the allocation of its items
+pub const MPT_NODE_DECODE_CYCLES: u64 = 2_000;
+/// Cycles of decoding every byte of a merkle patricia trie node, estimated as the load, store
+/// and branch of copying it
+pub const MPT_NODE_DECODE_BYTE_CYCLES: u64 = 8;
+/// Cycles of recovering the public key of a secp256k1 signature with libsecp256k1. Counting
+/// the instructions of one recovery by single-stepping it natively on x86-64 gives about 2
+/// million, rounded up to 5 million for the separate loads and the costlier multiplications of
+/// riscv64imac. `test_submit_full_clique_branch` and `test_submit_full_parlia_branch` of the
+/// tests crate check full branches of recoveries in ckb-vm.
+pub const SECP256K1_RECOVER_CYCLES: u64 = 5_000_000;
+
+/// Cycles of a syscall which copied `transferred` bytes into the script memory
+pub fn syscall_cycles(transferred: usize) -> u64 {
+    let transferred = transferred as u64;
+    SYSCALL_CYCLES + (transferred + BYTES_PER_CYCLE - 1) / BYTES_PER_CYCLE
+}
+
+/// Expensive operations performed by contract logic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// keccak256 over the given number of bytes
+    Keccak256(usize),
+    /// keccak512 over the given number of bytes
+    Keccak512(usize),
+    /// ethash hashimoto over the 64 dataset items of a header
+    Hashimoto,
+    /// Decoding a merkle patricia trie node of the given size
+    MptNodeDecode(usize),
+    /// Recovering the signer of a secp256k1 signature
+    Secp256k1Recover,
+    /// An operation measured by the caller
+    Cycles(u64),
+}
+
+impl Operation {
+    /// Estimated cycles of the operation
+    pub fn cycles(&self) -> u64 {
+        let permutations = |len: usize, rate: usize| (len / rate + 1) as u64;
+        match *self {
+            Operation::Keccak256(len) => {
+                permutations(len, KECCAK256_RATE) * KECCAK_PERMUTATION_CYCLES
+            }
+            Operation::Keccak512(len) => {
+                permutations(len, KECCAK512_RATE) * KECCAK_PERMUTATION_CYCLES
+            }
+            Operation::Hashimoto => HASHIMOTO_CYCLES,
+            Operation::MptNodeDecode(len) => {
+                MPT_NODE_DECODE_CYCLES + len as u64 * MPT_NODE_DECODE_BYTE_CYCLES
+            }
+            Operation::Secp256k1Recover => SECP256K1_RECOVER_CYCLES,
+            Operation::Cycles(cycles) => cycles,
+        }
+    }
+}
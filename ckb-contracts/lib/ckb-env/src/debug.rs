@@ -0,0 +1,22 @@
+/// debug macro
+///
+/// Output a debug message through the `debug` syscall of a chain. On-chain the message goes
+/// to `ckb_std::syscalls::debug`, `mock::MockCKBChain` prints and records it so native tests
+/// can assert on the emitted lines.
+///
+/// # Example
+///
+/// ```ignore
+/// // chain is a reference to a `CkbChainInterface` implementation
+/// debug!(chain, "hello world");
+/// debug!(chain, "there is a universal error caused by {}", 42);
+/// ```
+#[macro_export]
+macro_rules! debug {
+    ($chain:expr, $fmt:literal) => {
+        $crate::traits::CkbChainInterface::debug($chain, core::format_args!($fmt))
+    };
+    ($chain:expr, $fmt:literal, $($args:expr),+) => {
+        $crate::traits::CkbChainInterface::debug($chain, core::format_args!($fmt, $($args), +))
+    };
+}
@@ -0,0 +1,101 @@
+#[cfg(not(feature = "std"))]
+use crate::chain::CKBChain;
+#[cfg(all(not(feature = "std"), feature = "record"))]
+use crate::record::RecordingChain;
+
+/// The chain `contract_entry!` verifies contracts against, built with the `record` feature
+/// every syscall is traced so the run can be replayed natively
+#[cfg(all(not(feature = "std"), not(feature = "record")))]
+pub type EntryChain = CKBChain;
+#[cfg(all(not(feature = "std"), feature = "record"))]
+pub type EntryChain = RecordingChain<CKBChain>;
+
+#[cfg(all(not(feature = "std"), not(feature = "record")))]
+pub fn entry_chain() -> EntryChain {
+    CKBChain {}
+}
+
+#[cfg(all(not(feature = "std"), feature = "record"))]
+pub fn entry_chain() -> EntryChain {
+    RecordingChain::new(CKBChain {})
+}
+
+/// Define the entry point of a contract implementing `traits::ContractInterface`
+///
+/// The contract is verified against `EntryChain` and its error is the exit code of the
+/// script. The macro also defines the global allocator and the lang items of the contract, so
+/// the contract crate only declares the crate attributes they need. Unlike the one of
+/// `ckb_std::entry!`, the panic handler reports the panic through the debug syscall in release
+/// builds too, so a contract failing on chain says why.
+///
+/// # Example
+///
+/// ```ignore
+/// #![no_std]
+/// #![no_main]
+/// #![feature(lang_items)]
+/// #![feature(alloc_error_handler)]
+/// #![feature(panic_info_message)]
+///
+/// ckb_env::contract_entry!(eth_bridge_typescript_lib::EthBridgeTypescript);
+/// ```
+#[macro_export]
+macro_rules! contract_entry {
+    ($contract:ty) => {
+        extern crate alloc;
+
+        $crate::ckb_std::default_alloc!(4 * 1024, 516 * 1024, 64);
+
+        /// program entry
+        fn program_entry() -> i8 {
+            let chain = $crate::entry::entry_chain();
+            <$contract as $crate::traits::ContractInterface<$crate::entry::EntryChain>>::run(&chain)
+        }
+
+        #[alloc_error_handler]
+        fn oom_handler(_layout: alloc::alloc::Layout) -> ! {
+            panic!("Out of memory")
+        }
+
+        #[no_mangle]
+        pub extern "C" fn _start() -> ! {
+            let f: fn() -> i8 = program_entry;
+            $crate::ckb_std::syscalls::exit(f())
+        }
+
+        #[lang = "eh_personality"]
+        extern "C" fn eh_personality() {}
+
+        /// Fix symbol missing
+        #[no_mangle]
+        pub extern "C" fn abort() {
+            panic!("abort!");
+        }
+
+        #[panic_handler]
+        fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
+            use alloc::format;
+
+            let mut s = alloc::string::String::new();
+            if let Some(p) = panic_info.payload().downcast_ref::<&str>() {
+                s.push_str(&format!("panic occurred: {:?}", p));
+            } else {
+                s.push_str("panic occurred:");
+            }
+            if let Some(m) = panic_info.message() {
+                s.push_str(&format!(" {:?}", m));
+            }
+            if let Some(location) = panic_info.location() {
+                s.push_str(&format!(
+                    ", in file {}:{}",
+                    location.file(),
+                    location.line()
+                ));
+            } else {
+                s.push_str(", but can't get location information...");
+            }
+            $crate::ckb_std::syscalls::debug(s);
+            $crate::ckb_std::syscalls::exit(-1)
+        }
+    };
+}
@@ -7,9 +7,19 @@ cfg_if::cfg_if! {
     }
 }
 
+pub mod cycles;
+pub mod debug;
+pub mod entry;
+pub mod record;
 pub mod traits;
 #[cfg(not(feature = "std"))]
 pub mod chain;
 #[cfg(feature = "std")]
 pub mod mock;
+#[cfg(feature = "std")]
+pub mod mock_tx;
+#[cfg(feature = "std")]
+pub mod replay;
+
+pub use ckb_std;
 
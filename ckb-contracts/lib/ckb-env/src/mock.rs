@@ -1,13 +1,863 @@
+use crate::cycles::{syscall_cycles, Operation};
+use crate::traits::{
+    CkbChainInterface, CkbSyscallInterface, DynamicLoadingInterface, LibraryInterface,
+};
+use blake2b_ref::Blake2bBuilder;
+use ckb_std::ckb_constants::{CellField, HeaderField, InputField, Source};
+use ckb_std::ckb_types::{packed::*, prelude::*};
+use ckb_std::dynamic_loading::Error as DlError;
 use ckb_std::error::SysError;
-use crate::traits::CkbChainInterface;
+use ckb_std::since::EpochNumberWithFraction;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 
+const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+/// 1 byte of occupied space costs 1 CKB, which is 10^8 shannons
+const BYTE_SHANNONS: u64 = 100_000_000;
+
+/// Blake2b-256 with the ckb personalization, used for data hashes and script hashes
+pub fn blake2b_256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2bBuilder::new(32)
+        .personal(CKB_HASH_PERSONALIZATION)
+        .build();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Copy `data[offset..]` into `buf` with the same semantics as the VM: the copy is truncated
+/// to the buffer size, and the full length of the remaining data is reported either as the
+/// loaded length or as `LengthNotEnough`.
+fn load_partial(data: &[u8], buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+    let offset = core::cmp::min(offset, data.len());
+    let full_size = data.len() - offset;
+    let real_size = core::cmp::min(buf.len(), full_size);
+    buf[..real_size].copy_from_slice(&data[offset..offset + real_size]);
+    if full_size > buf.len() {
+        Err(SysError::LengthNotEnough(full_size))
+    } else {
+        Ok(full_size)
+    }
+}
+
+/// A live cell together with its data, as seen by the script.
+///
+/// `header` is the hash of the block which committed the cell, it is only visible to the
+/// script when the same hash is also listed in the header deps.
+#[derive(Debug, Default, Clone)]
+pub struct MockCell {
+    pub output: CellOutput,
+    pub data: Vec<u8>,
+    pub header: Option<[u8; 32]>,
+}
+
+impl MockCell {
+    pub fn new(output: CellOutput, data: Vec<u8>) -> Self {
+        Self {
+            output,
+            data,
+            header: None,
+        }
+    }
+
+    /// Mark the cell as committed in the block with `block_hash`
+    pub fn committed_in(mut self, block_hash: [u8; 32]) -> Self {
+        self.header = Some(block_hash);
+        self
+    }
+
+    fn data_hash(&self) -> [u8; 32] {
+        if self.data.is_empty() {
+            [0u8; 32]
+        } else {
+            blake2b_256(&self.data)
+        }
+    }
+
+    fn occupied_capacity(&self) -> u64 {
+        let script_bytes = |script: Script| 32 + 1 + script.args().raw_data().len() as u64;
+        let mut bytes = 8 + script_bytes(self.output.lock()) + self.data.len() as u64;
+        if let Some(type_script) = self.output.type_().to_opt() {
+            bytes += script_bytes(type_script);
+        }
+        bytes * BYTE_SHANNONS
+    }
+}
+
+/// The header fields time based script logic relies on, the other fields are left zero
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MockHeader {
+    pub number: u64,
+    pub epoch: EpochNumberWithFraction,
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl MockHeader {
+    pub fn new(number: u64, epoch: EpochNumberWithFraction, timestamp: u64) -> Self {
+        Self {
+            number,
+            epoch,
+            timestamp,
+        }
+    }
+
+    pub fn build(&self) -> Header {
+        let raw = RawHeader::new_builder()
+            .number(self.number.pack())
+            .epoch(self.epoch.full_value().pack())
+            .timestamp(self.timestamp.pack())
+            .build();
+        Header::new_builder().raw(raw).build()
+    }
+}
+
+/// Native stand-in of a shared library, its symbols are Rust `extern "C"` functions
+#[derive(Debug, Default, Clone)]
+pub struct MockLibrary {
+    symbols: HashMap<Vec<u8>, usize>,
+}
+
+impl MockLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Export `function`, an `extern "C" fn` cast to a pointer, as `symbol`
+    pub fn symbol(mut self, symbol: &[u8], function: *const u8) -> Self {
+        self.symbols.insert(symbol.to_vec(), function as usize);
+        self
+    }
+}
+
+impl LibraryInterface for MockLibrary {
+    unsafe fn get<S: Copy>(&self, symbol: &[u8]) -> Option<S> {
+        assert_eq!(
+            core::mem::size_of::<S>(),
+            core::mem::size_of::<usize>(),
+            "symbols must be loaded as function pointers"
+        );
+        self.symbols
+            .get(symbol)
+            .map(|function| core::mem::transmute_copy(function))
+    }
+}
+
+/// Kind of the script group the executing script runs in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptGroupType {
+    /// Input cells whose lock script is the executing script
+    #[default]
+    Lock,
+    /// Input and output cells whose type script is the executing script
+    Type,
+}
+
+/// In-memory chain used by native tests.
+///
+/// Outputs and witnesses are read from `transaction`, while the resolved input cells and
+/// cell deps, which are not part of the transaction itself, are provided by `inputs` and
+/// `cell_deps` in the same order as the transaction references them.
+///
+/// `header_deps` are the resolved headers of the transaction header deps, the headers of
+/// inputs and cell deps are looked up among them by `MockCell::header`. `push_header_dep`
+/// keeps both lists in sync.
+///
+/// `script` and `script_group_type` describe the executing script, they decide which cells
+/// `Source::GroupInput` and `Source::GroupOutput` refer to.
+///
+/// Every syscall and every operation marked with `charge_cycles` adds its estimated cost to
+/// the cycle counter, see the `cycles` module for the cost model. Debug messages are printed
+/// like ckb-testtool does and kept in `debug_messages` so tests can assert on them.
+///
+/// Shared libraries are looked up among the cell deps by data hash like on-chain, the code
+/// itself is replaced by the `MockLibrary` registered in `libraries` for that hash.
 #[derive(Debug, Default)]
 pub struct MockCKBChain {
     pub tx_hash: [u8; 32],
+    pub script: Script,
+    pub script_group_type: ScriptGroupType,
+    pub transaction: Transaction,
+    pub inputs: Vec<MockCell>,
+    pub cell_deps: Vec<MockCell>,
+    pub header_deps: Vec<Header>,
+    pub cycles: Cell<u64>,
+    pub debug_messages: RefCell<Vec<String>>,
+    pub libraries: HashMap<[u8; 32], MockLibrary>,
+}
+
+impl MockCKBChain {
+    /// Set the executing script and the kind of script group it runs in
+    pub fn set_script(&mut self, script: Script, script_group_type: ScriptGroupType) {
+        self.script = script;
+        self.script_group_type = script_group_type;
+    }
+
+    /// Run the lock or the type script of a cell, like `--cell-index`, `--cell-type` and
+    /// `--script-group-type` do in ckb-debugger
+    pub fn set_script_of_cell(
+        &mut self,
+        index: usize,
+        source: Source,
+        script_group_type: ScriptGroupType,
+    ) -> Result<(), SysError> {
+        let output = self.cell(index, source)?.output;
+        let script = match script_group_type {
+            ScriptGroupType::Lock => output.lock(),
+            ScriptGroupType::Type => output.type_().to_opt().ok_or(SysError::ItemMissing)?,
+        };
+        self.set_script(script, script_group_type);
+        Ok(())
+    }
+
+    /// Cycles consumed so far by syscalls and marked operations
+    pub fn cycles(&self) -> u64 {
+        self.cycles.get()
+    }
+
+    /// Debug messages output so far
+    pub fn debug_messages(&self) -> Vec<String> {
+        self.debug_messages.borrow().clone()
+    }
+
+    /// Add a header dep to the transaction, returns the block hash cells committed in that
+    /// block refer to with `MockCell::committed_in`
+    pub fn push_header_dep(&mut self, header: Header) -> [u8; 32] {
+        let block_hash = blake2b_256(header.as_slice());
+        let raw = self.transaction.raw();
+        let header_deps = raw
+            .header_deps()
+            .as_builder()
+            .push(Byte32::new_unchecked(block_hash.to_vec().into()))
+            .build();
+        self.transaction = self
+            .transaction
+            .clone()
+            .as_builder()
+            .raw(raw.as_builder().header_deps(header_deps).build())
+            .build();
+        self.header_deps.push(header);
+        block_hash
+    }
+
+    /// Register the native implementation of the shared library deployed with `code` as the
+    /// data of a cell dep
+    pub fn register_library(&mut self, code: &[u8], library: MockLibrary) {
+        self.libraries.insert(blake2b_256(code), library);
+    }
+
+    fn script_hash(&self) -> [u8; 32] {
+        blake2b_256(self.script.as_slice())
+    }
+
+    /// Indices of the cells in `source` which belong to the executing script group
+    fn group_indices(&self, source: Source) -> Result<Vec<usize>, SysError> {
+        let script_hash = self.script_hash();
+        let mut indices = Vec::new();
+        if self.script_group_type == ScriptGroupType::Lock && source == Source::Output {
+            // lock scripts only run on inputs, there are no outputs in a lock script group
+            return Ok(indices);
+        }
+        for index in 0.. {
+            let output = match self.cell(index, source) {
+                Ok(cell) => cell.output,
+                Err(SysError::IndexOutOfBound) => break,
+                Err(err) => return Err(err),
+            };
+            let script = match self.script_group_type {
+                ScriptGroupType::Lock => Some(output.lock()),
+                ScriptGroupType::Type => output.type_().to_opt(),
+            };
+            if script.map(|script| blake2b_256(script.as_slice())) == Some(script_hash) {
+                indices.push(index);
+            }
+        }
+        Ok(indices)
+    }
+
+    /// Translate a group source to the transaction source and index it refers to, the same
+    /// way the VM does
+    fn resolve_source(&self, index: usize, source: Source) -> Result<(usize, Source), SysError> {
+        let source = match source {
+            Source::GroupInput => Source::Input,
+            Source::GroupOutput => Source::Output,
+            _ => return Ok((index, source)),
+        };
+        self.group_indices(source)?
+            .get(index)
+            .map(|&index| (index, source))
+            .ok_or(SysError::IndexOutOfBound)
+    }
+
+    fn cell(&self, index: usize, source: Source) -> Result<MockCell, SysError> {
+        let (index, source) = self.resolve_source(index, source)?;
+        let cell = match source {
+            Source::Input => self.inputs.get(index).cloned(),
+            Source::Output => {
+                let raw = self.transaction.raw();
+                raw.outputs().get(index).map(|output| {
+                    let data = raw
+                        .outputs_data()
+                        .get(index)
+                        .map(|data| data.raw_data().to_vec())
+                        .unwrap_or_default();
+                    MockCell::new(output, data)
+                })
+            }
+            Source::CellDep => self.cell_deps.get(index).cloned(),
+            Source::HeaderDep | Source::GroupInput | Source::GroupOutput => None,
+        };
+        cell.ok_or(SysError::IndexOutOfBound)
+    }
+
+    fn header(&self, index: usize, source: Source) -> Result<Header, SysError> {
+        let (index, source) = self.resolve_source(index, source)?;
+        match source {
+            Source::HeaderDep => self
+                .header_deps
+                .get(index)
+                .cloned()
+                .ok_or(SysError::IndexOutOfBound),
+            Source::Input | Source::CellDep => {
+                let block_hash = self.cell(index, source)?.header;
+                self.header_deps
+                    .iter()
+                    .find(|header| Some(blake2b_256(header.as_slice())) == block_hash)
+                    .cloned()
+                    .ok_or(SysError::ItemMissing)
+            }
+            Source::Output | Source::GroupInput | Source::GroupOutput => {
+                Err(SysError::IndexOutOfBound)
+            }
+        }
+    }
+
+    fn input(&self, index: usize, source: Source) -> Result<CellInput, SysError> {
+        let (index, source) = self.resolve_source(index, source)?;
+        let input = match source {
+            Source::Input => self.transaction.raw().inputs().get(index),
+            _ => None,
+        };
+        input.ok_or(SysError::IndexOutOfBound)
+    }
+
+    fn witness(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        let (index, source) = self.resolve_source(index, source)?;
+        let witness = match source {
+            Source::Input | Source::Output => self.transaction.witnesses().get(index),
+            _ => None,
+        };
+        witness
+            .map(|witness| witness.raw_data().to_vec())
+            .ok_or(SysError::IndexOutOfBound)
+    }
 }
 
 impl CkbChainInterface for MockCKBChain {
-    fn load_tx_hash(&self) -> Result<[u8; 32], SysError> {
-        Ok(self.tx_hash)
+    fn charge_cycles(&self, operation: Operation) {
+        self.cycles.set(self.cycles.get() + operation.cycles());
+    }
+}
+
+impl DynamicLoadingInterface for MockCKBChain {
+    type Library = MockLibrary;
+
+    fn load_library(&self, dep_cell_data_hash: &[u8; 32]) -> Result<MockLibrary, DlError> {
+        // look up the cell dep the same way `CKBDLContext` does, so the syscalls are charged
+        let mut index = 0;
+        let code = loop {
+            match self.load_cell_data_hash(index, Source::CellDep) {
+                Ok(hash) if &hash == dep_cell_data_hash => {
+                    break self.cell(index, Source::CellDep)?.data;
+                }
+                Ok(_) => index += 1,
+                Err(SysError::IndexOutOfBound) => return Err(DlError::CellNotFound),
+                Err(err) => return Err(err.into()),
+            }
+        };
+        self.charge_cycles(Operation::Cycles(syscall_cycles(code.len())));
+        // the code of a library can only run in the VM
+        self.libraries
+            .get(dep_cell_data_hash)
+            .cloned()
+            .ok_or(DlError::InvalidElf)
+    }
+}
+
+impl MockCKBChain {
+    /// Load `data` like a syscall and charge the cycles the VM would spend on it
+    fn syscall(
+        &self,
+        data: Result<Vec<u8>, SysError>,
+        buf: &mut [u8],
+        offset: usize,
+    ) -> Result<usize, SysError> {
+        let result = data.and_then(|data| load_partial(&data, buf, offset));
+        let transferred = match result {
+            Ok(len) => len,
+            Err(SysError::LengthNotEnough(_)) => buf.len(),
+            Err(_) => 0,
+        };
+        self.charge_cycles(Operation::Cycles(syscall_cycles(transferred)));
+        result
+    }
+
+    fn cell_field(
+        &self,
+        index: usize,
+        source: Source,
+        field: CellField,
+    ) -> Result<Vec<u8>, SysError> {
+        let cell = self.cell(index, source)?;
+        let data = match field {
+            CellField::Capacity => cell.output.capacity().as_slice().to_vec(),
+            CellField::DataHash => cell.data_hash().to_vec(),
+            CellField::Lock => cell.output.lock().as_slice().to_vec(),
+            CellField::LockHash => blake2b_256(cell.output.lock().as_slice()).to_vec(),
+            CellField::Type => cell
+                .output
+                .type_()
+                .to_opt()
+                .ok_or(SysError::ItemMissing)?
+                .as_slice()
+                .to_vec(),
+            CellField::TypeHash => {
+                let type_script = cell.output.type_().to_opt().ok_or(SysError::ItemMissing)?;
+                blake2b_256(type_script.as_slice()).to_vec()
+            }
+            CellField::OccupiedCapacity => cell.occupied_capacity().to_le_bytes().to_vec(),
+        };
+        Ok(data)
+    }
+
+    fn header_field(
+        &self,
+        index: usize,
+        source: Source,
+        field: HeaderField,
+    ) -> Result<Vec<u8>, SysError> {
+        let header = self.header(index, source)?;
+        let number: u64 = header.raw().number().unpack();
+        let epoch: u64 = header.raw().epoch().unpack();
+        let epoch = EpochNumberWithFraction::from_full_value(epoch);
+        let value = match field {
+            HeaderField::EpochNumber => epoch.number(),
+            // a header before the index of its epoch has no start block
+            HeaderField::EpochStartBlockNumber => number
+                .checked_sub(epoch.index())
+                .ok_or(SysError::ItemMissing)?,
+            HeaderField::EpochLength => epoch.length(),
+        };
+        Ok(value.to_le_bytes().to_vec())
+    }
+}
+
+impl CkbSyscallInterface for MockCKBChain {
+    fn sys_debug(&self, message: String) {
+        self.charge_cycles(Operation::Cycles(syscall_cycles(0)));
+        println!("[contract debug] {}", message);
+        self.debug_messages.borrow_mut().push(message);
+    }
+
+    fn sys_load_tx_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.syscall(Ok(self.tx_hash.to_vec()), buf, offset)
+    }
+
+    fn sys_load_script_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.syscall(Ok(self.script_hash().to_vec()), buf, offset)
+    }
+
+    fn sys_load_script(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.syscall(Ok(self.script.as_slice().to_vec()), buf, offset)
+    }
+
+    fn sys_load_transaction(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.syscall(Ok(self.transaction.as_slice().to_vec()), buf, offset)
+    }
+
+    fn sys_load_cell(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let data = self
+            .cell(index, source)
+            .map(|cell| cell.output.as_slice().to_vec());
+        self.syscall(data, buf, offset)
+    }
+
+    fn sys_load_cell_data(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        self.syscall(self.cell(index, source).map(|cell| cell.data), buf, offset)
+    }
+
+    fn sys_load_cell_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: CellField,
+    ) -> Result<usize, SysError> {
+        self.syscall(self.cell_field(index, source, field), buf, offset)
+    }
+
+    fn sys_load_input(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let data = self
+            .input(index, source)
+            .map(|input| input.as_slice().to_vec());
+        self.syscall(data, buf, offset)
+    }
+
+    fn sys_load_input_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: InputField,
+    ) -> Result<usize, SysError> {
+        let data = self.input(index, source).map(|input| match field {
+            InputField::OutPoint => input.previous_output().as_slice().to_vec(),
+            InputField::Since => input.since().as_slice().to_vec(),
+        });
+        self.syscall(data, buf, offset)
+    }
+
+    fn sys_load_header(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let data = self
+            .header(index, source)
+            .map(|header| header.as_slice().to_vec());
+        self.syscall(data, buf, offset)
+    }
+
+    fn sys_load_header_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: HeaderField,
+    ) -> Result<usize, SysError> {
+        self.syscall(self.header_field(index, source, field), buf, offset)
+    }
+
+    fn sys_load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        self.syscall(self.witness(index, source), buf, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_script(args: &[u8]) -> Script {
+        Script::new_builder().args(args.to_vec().pack()).build()
+    }
+
+    #[test]
+    fn test_load_output_cell() {
+        let output = CellOutput::new_builder()
+            .capacity(1000u64.pack())
+            .lock(lock_script(&[1u8; 20]))
+            .build();
+        let transaction = Transaction::new_builder()
+            .raw(
+                RawTransaction::new_builder()
+                    .outputs(CellOutputVec::new_builder().push(output.clone()).build())
+                    .outputs_data(BytesVec::new_builder().push([42u8; 10].pack()).build())
+                    .build(),
+            )
+            .build();
+        let chain = MockCKBChain {
+            transaction,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            chain.load_cell(0, Source::Output).unwrap().as_slice(),
+            output.as_slice()
+        );
+        assert_eq!(
+            chain.load_cell_data(0, Source::Output).unwrap(),
+            vec![42u8; 10]
+        );
+        assert_eq!(chain.load_cell_capacity(0, Source::Output).unwrap(), 1000);
+        assert_eq!(
+            chain
+                .load_cell_occupied_capacity(0, Source::Output)
+                .unwrap(),
+            (8 + 33 + 20 + 10) * BYTE_SHANNONS
+        );
+        assert_eq!(chain.load_cell_type_hash(0, Source::Output).unwrap(), None);
+        assert_eq!(
+            chain.load_cell(1, Source::Output).unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+        assert_eq!(
+            chain.load_input(0, Source::Output).unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+    }
+
+    #[test]
+    fn test_group_source() {
+        let lock = lock_script(&[1u8; 20]);
+        let type_script = lock_script(&[2u8; 20]);
+        let cell = |capacity: u64, type_script: Option<Script>| {
+            CellOutput::new_builder()
+                .capacity(capacity.pack())
+                .lock(lock.clone())
+                .type_(ScriptOpt::new_builder().set(type_script).build())
+                .build()
+        };
+        let outputs = CellOutputVec::new_builder()
+            .push(cell(300, None))
+            .push(cell(400, Some(type_script.clone())))
+            .build();
+        let mut chain = MockCKBChain {
+            transaction: Transaction::new_builder()
+                .raw(RawTransaction::new_builder().outputs(outputs).build())
+                .build(),
+            inputs: vec![
+                MockCell::new(cell(100, None), Vec::new()),
+                MockCell::new(cell(200, Some(type_script.clone())), Vec::new()),
+            ],
+            ..Default::default()
+        };
+
+        chain.set_script(type_script, ScriptGroupType::Type);
+        assert_eq!(
+            chain.load_cell_capacity(0, Source::GroupInput).unwrap(),
+            200
+        );
+        assert_eq!(
+            chain.load_cell_capacity(0, Source::GroupOutput).unwrap(),
+            400
+        );
+        assert_eq!(
+            chain.load_cell_capacity(1, Source::GroupInput).unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+
+        chain
+            .set_script_of_cell(0, Source::Input, ScriptGroupType::Lock)
+            .unwrap();
+        assert_eq!(
+            chain.load_cell_capacity(1, Source::GroupInput).unwrap(),
+            200
+        );
+        assert_eq!(
+            chain
+                .load_cell_capacity(0, Source::GroupOutput)
+                .unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+    }
+
+    #[test]
+    fn test_partial_load_tx_hash() {
+        let tx_hash = [7u8; 32];
+        let chain = MockCKBChain {
+            tx_hash,
+            ..Default::default()
+        };
+
+        let mut buf = [0u8; 16];
+        let err = chain.sys_load_tx_hash(&mut buf, 0).unwrap_err();
+        assert_eq!(err, SysError::LengthNotEnough(32));
+        assert_eq!(buf[..], tx_hash[..16]);
+        let len = chain.sys_load_tx_hash(&mut buf, 16).unwrap();
+        assert_eq!(len, buf.len());
+        assert_eq!(buf[..], tx_hash[16..]);
+        // offsets beyond the data load nothing
+        assert_eq!(chain.sys_load_tx_hash(&mut buf, 64).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_load_witness_in_chunks() {
+        let witness: Vec<u8> = (0..100u8).collect();
+        let chain = MockCKBChain {
+            transaction: Transaction::new_builder()
+                .witnesses(BytesVec::new_builder().push(witness.pack()).build())
+                .build(),
+            ..Default::default()
+        };
+
+        let mut loaded = Vec::new();
+        let mut buf = [0u8; 32];
+        loop {
+            match chain.sys_load_witness(&mut buf, loaded.len(), 0, Source::Input) {
+                Ok(len) => {
+                    loaded.extend_from_slice(&buf[..len]);
+                    break;
+                }
+                Err(SysError::LengthNotEnough(_)) => loaded.extend_from_slice(&buf),
+                Err(err) => panic!("unexpected error {:?}", err),
+            }
+        }
+        assert_eq!(loaded, witness);
+        assert_eq!(
+            chain
+                .sys_load_witness(&mut buf, 0, 1, Source::Input)
+                .unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+    }
+
+    #[test]
+    fn test_cycles() {
+        let transaction = Transaction::new_builder()
+            .raw(
+                RawTransaction::new_builder()
+                    .outputs(
+                        CellOutputVec::new_builder()
+                            .push(Default::default())
+                            .build(),
+                    )
+                    .outputs_data(BytesVec::new_builder().push([0u8; 2000].pack()).build())
+                    .build(),
+            )
+            .build();
+        let chain = MockCKBChain {
+            transaction,
+            ..Default::default()
+        };
+
+        // high level loaders read up to 1024 bytes first, then the rest in a second syscall
+        chain.load_cell_data(0, Source::Output).unwrap();
+        assert_eq!(chain.cycles(), syscall_cycles(1024) + syscall_cycles(976));
+
+        chain.cycles.set(0);
+        chain.load_cell_data(1, Source::Output).unwrap_err();
+        chain.charge_cycles(Operation::Keccak256(100));
+        assert_eq!(
+            chain.cycles(),
+            syscall_cycles(0) + Operation::Keccak256(100).cycles()
+        );
+    }
+
+    #[test]
+    fn test_header_deps() {
+        let epoch = EpochNumberWithFraction::new(10, 100, 1800);
+        let header = MockHeader::new(18_100, epoch, 1_600_000_000_000).build();
+        let mut chain = MockCKBChain::default();
+        let block_hash = chain.push_header_dep(header.clone());
+        chain.inputs = vec![MockCell::default().committed_in(block_hash)];
+        chain.cell_deps = vec![MockCell::default()];
+
+        assert_eq!(
+            chain.load_transaction().unwrap().raw().header_deps().len(),
+            1
+        );
+        assert_eq!(
+            chain.load_header(0, Source::HeaderDep).unwrap().as_slice(),
+            header.as_slice()
+        );
+        let timestamp: u64 = chain
+            .load_header(0, Source::Input)
+            .unwrap()
+            .raw()
+            .timestamp()
+            .unpack();
+        assert_eq!(timestamp, 1_600_000_000_000);
+        assert_eq!(
+            chain.load_header_epoch_number(0, Source::Input).unwrap(),
+            10
+        );
+        assert_eq!(
+            chain
+                .load_header_epoch_start_block_number(0, Source::HeaderDep)
+                .unwrap(),
+            18_000
+        );
+        assert_eq!(
+            chain
+                .load_header_epoch_length(0, Source::HeaderDep)
+                .unwrap(),
+            1800
+        );
+        assert_eq!(
+            chain.load_header(0, Source::CellDep).unwrap_err(),
+            SysError::ItemMissing
+        );
+        assert_eq!(
+            chain.load_header(1, Source::HeaderDep).unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+
+        let header = MockHeader::new(99, epoch, 1_600_000_000_000).build();
+        chain.push_header_dep(header);
+        assert_eq!(
+            chain
+                .load_header_epoch_start_block_number(1, Source::HeaderDep)
+                .unwrap_err(),
+            SysError::ItemMissing
+        );
+    }
+
+    extern "C" fn add_42(n: u64) -> u64 {
+        n + 42
+    }
+
+    #[test]
+    fn test_dynamic_loading() {
+        let code = b"shared library".to_vec();
+        let mut chain = MockCKBChain {
+            cell_deps: vec![
+                MockCell::new(Default::default(), vec![1u8; 10]),
+                MockCell::new(Default::default(), code.clone()),
+            ],
+            ..Default::default()
+        };
+        let code_hash = blake2b_256(&code);
+
+        assert_eq!(
+            chain.load_library(&code_hash).unwrap_err(),
+            DlError::InvalidElf
+        );
+        assert_eq!(
+            chain.load_library(&[0u8; 32]).unwrap_err(),
+            DlError::CellNotFound
+        );
+
+        chain.register_library(
+            &code,
+            MockLibrary::new().symbol(b"add_42", add_42 as *const u8),
+        );
+        let library = chain.load_library(&code_hash).unwrap();
+        unsafe {
+            let add: extern "C" fn(u64) -> u64 = library.get(b"add_42").unwrap();
+            assert_eq!(add(1), 43);
+            assert!(library
+                .get::<extern "C" fn(u64) -> u64>(b"sub_42")
+                .is_none());
+        }
     }
 }
@@ -0,0 +1,550 @@
+//! Loads a `MockCKBChain` from the mock transaction format used by ckb-debugger.
+//!
+//! A mock transaction is a JSON file holding the transaction (`tx`) and everything the VM
+//! would resolve from the chain while running it (`mock_info`): the input cells, the cell
+//! deps and the header deps. Dumping the mock transaction of a failed mainnet/testnet
+//! transaction and loading it here reproduces the failure in a native `cargo test`.
+//!
+//! ```ignore
+//! let chain = MockCKBChain::from_mock_tx_file("fixtures/failed_tx.json").unwrap();
+//! ```
+
+use crate::mock::{blake2b_256, MockCKBChain, MockCell};
+use ckb_std::ckb_types::{packed::*, prelude::*};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum MockTxError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// A hex string or numeric field can not be decoded
+    InvalidField(String),
+    /// An out point referenced by the transaction is not resolved in `mock_info`
+    MissingCell(String),
+    /// A header dep referenced by the transaction is not resolved in `mock_info`
+    MissingHeader(String),
+}
+
+impl fmt::Display for MockTxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MockTxError::Io(err) => write!(f, "io error: {}", err),
+            MockTxError::Json(err) => write!(f, "json error: {}", err),
+            MockTxError::InvalidField(field) => write!(f, "invalid field: {}", field),
+            MockTxError::MissingCell(out_point) => write!(f, "missing cell: {}", out_point),
+            MockTxError::MissingHeader(hash) => write!(f, "missing header: {}", hash),
+        }
+    }
+}
+
+impl std::error::Error for MockTxError {}
+
+impl From<std::io::Error> for MockTxError {
+    fn from(err: std::io::Error) -> Self {
+        MockTxError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for MockTxError {
+    fn from(err: serde_json::Error) -> Self {
+        MockTxError::Json(err)
+    }
+}
+
+type Result<T> = std::result::Result<T, MockTxError>;
+
+#[derive(Deserialize)]
+struct JsonMockTransaction {
+    mock_info: JsonMockInfo,
+    tx: JsonTransaction,
+}
+
+#[derive(Deserialize)]
+struct JsonMockInfo {
+    #[serde(default)]
+    inputs: Vec<JsonMockInput>,
+    #[serde(default)]
+    cell_deps: Vec<JsonMockCellDep>,
+    #[serde(default)]
+    header_deps: Vec<JsonHeader>,
+}
+
+#[derive(Deserialize)]
+struct JsonMockInput {
+    input: JsonCellInput,
+    output: JsonCellOutput,
+    data: String,
+    #[serde(default)]
+    header: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonMockCellDep {
+    cell_dep: JsonCellDep,
+    output: JsonCellOutput,
+    data: String,
+    #[serde(default)]
+    header: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonTransaction {
+    version: String,
+    cell_deps: Vec<JsonCellDep>,
+    header_deps: Vec<String>,
+    inputs: Vec<JsonCellInput>,
+    outputs: Vec<JsonCellOutput>,
+    outputs_data: Vec<String>,
+    witnesses: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonOutPoint {
+    tx_hash: String,
+    index: String,
+}
+
+#[derive(Deserialize)]
+struct JsonCellInput {
+    since: String,
+    previous_output: JsonOutPoint,
+}
+
+#[derive(Deserialize)]
+struct JsonCellDep {
+    out_point: JsonOutPoint,
+    dep_type: String,
+}
+
+#[derive(Deserialize)]
+struct JsonScript {
+    code_hash: String,
+    hash_type: String,
+    args: String,
+}
+
+#[derive(Deserialize)]
+struct JsonCellOutput {
+    capacity: String,
+    lock: JsonScript,
+    #[serde(rename = "type", default)]
+    type_: Option<JsonScript>,
+}
+
+#[derive(Deserialize)]
+struct JsonHeader {
+    version: String,
+    compact_target: String,
+    timestamp: String,
+    number: String,
+    epoch: String,
+    parent_hash: String,
+    transactions_root: String,
+    proposals_hash: String,
+    #[serde(alias = "extra_hash")]
+    uncles_hash: String,
+    dao: String,
+    nonce: String,
+}
+
+fn decode_hex(field: &str) -> Result<Vec<u8>> {
+    let invalid = || MockTxError::InvalidField(field.to_owned());
+    let hex = field.strip_prefix("0x").ok_or_else(invalid)?;
+    if hex.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+fn decode_u128(field: &str) -> Result<u128> {
+    field
+        .strip_prefix("0x")
+        .and_then(|hex| u128::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| MockTxError::InvalidField(field.to_owned()))
+}
+
+fn decode_u64(field: &str) -> Result<u64> {
+    let value = decode_u128(field)?;
+    if value > u128::from(u64::MAX) {
+        return Err(MockTxError::InvalidField(field.to_owned()));
+    }
+    Ok(value as u64)
+}
+
+fn decode_u32(field: &str) -> Result<u32> {
+    let value = decode_u64(field)?;
+    if value > u64::from(u32::MAX) {
+        return Err(MockTxError::InvalidField(field.to_owned()));
+    }
+    Ok(value as u32)
+}
+
+fn decode_hash(field: &str) -> Result<[u8; 32]> {
+    let bytes = decode_hex(field)?;
+    if bytes.len() != 32 {
+        return Err(MockTxError::InvalidField(field.to_owned()));
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&bytes);
+    Ok(hash)
+}
+
+fn decode_byte32(field: &str) -> Result<Byte32> {
+    Ok(Byte32::new_unchecked(decode_hash(field)?.to_vec().into()))
+}
+
+impl JsonOutPoint {
+    fn to_packed(&self) -> Result<OutPoint> {
+        Ok(OutPoint::new_builder()
+            .tx_hash(decode_byte32(&self.tx_hash)?)
+            .index(decode_u32(&self.index)?.pack())
+            .build())
+    }
+}
+
+impl JsonCellInput {
+    fn to_packed(&self) -> Result<CellInput> {
+        Ok(CellInput::new_builder()
+            .since(decode_u64(&self.since)?.pack())
+            .previous_output(self.previous_output.to_packed()?)
+            .build())
+    }
+}
+
+impl JsonCellDep {
+    fn is_dep_group(&self) -> Result<bool> {
+        match self.dep_type.as_str() {
+            "code" => Ok(false),
+            "dep_group" => Ok(true),
+            _ => Err(MockTxError::InvalidField(self.dep_type.clone())),
+        }
+    }
+
+    fn to_packed(&self) -> Result<CellDep> {
+        Ok(CellDep::new_builder()
+            .out_point(self.out_point.to_packed()?)
+            .dep_type(Byte::new(self.is_dep_group()? as u8))
+            .build())
+    }
+}
+
+impl JsonScript {
+    fn to_packed(&self) -> Result<Script> {
+        let hash_type = match self.hash_type.as_str() {
+            "data" => 0,
+            "type" => 1,
+            "data1" => 2,
+            _ => return Err(MockTxError::InvalidField(self.hash_type.clone())),
+        };
+        Ok(Script::new_builder()
+            .code_hash(decode_byte32(&self.code_hash)?)
+            .hash_type(Byte::new(hash_type))
+            .args(decode_hex(&self.args)?.pack())
+            .build())
+    }
+}
+
+impl JsonCellOutput {
+    fn to_packed(&self) -> Result<CellOutput> {
+        let type_script = match &self.type_ {
+            Some(script) => Some(script.to_packed()?),
+            None => None,
+        };
+        Ok(CellOutput::new_builder()
+            .capacity(decode_u64(&self.capacity)?.pack())
+            .lock(self.lock.to_packed()?)
+            .type_(ScriptOpt::new_builder().set(type_script).build())
+            .build())
+    }
+}
+
+impl JsonHeader {
+    fn to_packed(&self) -> Result<Header> {
+        let raw = RawHeader::new_builder()
+            .version(decode_u32(&self.version)?.pack())
+            .compact_target(decode_u32(&self.compact_target)?.pack())
+            .timestamp(decode_u64(&self.timestamp)?.pack())
+            .number(decode_u64(&self.number)?.pack())
+            .epoch(decode_u64(&self.epoch)?.pack())
+            .parent_hash(decode_byte32(&self.parent_hash)?)
+            .transactions_root(decode_byte32(&self.transactions_root)?)
+            .proposals_hash(decode_byte32(&self.proposals_hash)?)
+            .uncles_hash(decode_byte32(&self.uncles_hash)?)
+            .dao(decode_byte32(&self.dao)?)
+            .build();
+        let nonce = decode_u128(&self.nonce)?.to_le_bytes().to_vec();
+        Ok(Header::new_builder()
+            .raw(raw)
+            .nonce(Uint128::new_unchecked(nonce.into()))
+            .build())
+    }
+}
+
+fn resolve_cell(cells: &HashMap<Vec<u8>, MockCell>, out_point: &OutPoint) -> Result<MockCell> {
+    cells
+        .get(out_point.as_slice())
+        .cloned()
+        .ok_or_else(|| MockTxError::MissingCell(format!("{}", out_point)))
+}
+
+/// Bytes of a serialized `OutPoint`, the transaction hash and the index as a little endian u32
+const OUT_POINT_SIZE: usize = 32 + 4;
+
+/// The out points listed by the data of a dep group cell, a molecule fixvec: the number of out
+/// points as a little endian u32 followed by the out points
+fn dep_group_members(data: &[u8]) -> Option<Vec<OutPoint>> {
+    if data.len() < 4 {
+        return None;
+    }
+    let mut count = [0u8; 4];
+    count.copy_from_slice(&data[..4]);
+    let count = u32::from_le_bytes(count) as usize;
+    if data.len() - 4 != count.checked_mul(OUT_POINT_SIZE)? {
+        return None;
+    }
+    Some(
+        data[4..]
+            .chunks(OUT_POINT_SIZE)
+            .map(|out_point| OutPoint::new_unchecked(out_point.to_vec().into()))
+            .collect(),
+    )
+}
+
+fn mock_cell(output: &JsonCellOutput, data: &str, header: &Option<String>) -> Result<MockCell> {
+    let mut cell = MockCell::new(output.to_packed()?, decode_hex(data)?);
+    cell.header = match header {
+        Some(hash) => Some(decode_hash(hash)?),
+        None => None,
+    };
+    Ok(cell)
+}
+
+impl MockCKBChain {
+    /// Build a chain from a ckb-debugger mock transaction in JSON.
+    ///
+    /// Inputs, cell deps and header deps are resolved by out point and block hash in the
+    /// same order as the transaction references them, dep groups are expanded into their
+    /// member cells exactly as the VM does.
+    pub fn from_mock_tx_json(json: &str) -> Result<Self> {
+        let mock_tx: JsonMockTransaction = serde_json::from_str(json)?;
+        let JsonMockTransaction { mock_info, tx } = mock_tx;
+
+        let mut input_cells = HashMap::new();
+        for input in &mock_info.inputs {
+            let out_point = input.input.previous_output.to_packed()?;
+            let cell = mock_cell(&input.output, &input.data, &input.header)?;
+            input_cells.insert(out_point.as_slice().to_vec(), cell);
+        }
+        let mut dep_cells = HashMap::new();
+        for dep in &mock_info.cell_deps {
+            let out_point = dep.cell_dep.out_point.to_packed()?;
+            let cell = mock_cell(&dep.output, &dep.data, &dep.header)?;
+            dep_cells.insert(out_point.as_slice().to_vec(), cell);
+        }
+        let mut headers = HashMap::new();
+        for header in &mock_info.header_deps {
+            let header = header.to_packed()?;
+            headers.insert(blake2b_256(header.as_slice()), header);
+        }
+
+        let inputs = tx
+            .inputs
+            .iter()
+            .map(JsonCellInput::to_packed)
+            .collect::<Result<Vec<_>>>()?;
+        let cell_deps = tx
+            .cell_deps
+            .iter()
+            .map(JsonCellDep::to_packed)
+            .collect::<Result<Vec<_>>>()?;
+        let header_deps = tx
+            .header_deps
+            .iter()
+            .map(|hash| decode_hash(hash))
+            .collect::<Result<Vec<_>>>()?;
+        let outputs = tx
+            .outputs
+            .iter()
+            .map(JsonCellOutput::to_packed)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut resolved_inputs = Vec::with_capacity(inputs.len());
+        for input in &inputs {
+            resolved_inputs.push(resolve_cell(&input_cells, &input.previous_output())?);
+        }
+        let mut resolved_cell_deps = Vec::with_capacity(cell_deps.len());
+        for (cell_dep, json_cell_dep) in cell_deps.iter().zip(tx.cell_deps.iter()) {
+            let cell = resolve_cell(&dep_cells, &cell_dep.out_point())?;
+            if json_cell_dep.is_dep_group()? {
+                let members = dep_group_members(&cell.data)
+                    .ok_or_else(|| MockTxError::InvalidField(format!("{}", cell_dep)))?;
+                for member in &members {
+                    resolved_cell_deps.push(resolve_cell(&dep_cells, &member)?);
+                }
+            } else {
+                resolved_cell_deps.push(cell);
+            }
+        }
+        let mut resolved_header_deps = Vec::with_capacity(header_deps.len());
+        for hash in &header_deps {
+            let header = headers
+                .get(hash)
+                .cloned()
+                .ok_or_else(|| MockTxError::MissingHeader(format!("{:?}", hash)))?;
+            resolved_header_deps.push(header);
+        }
+
+        let mut outputs_data = BytesVec::new_builder();
+        for data in &tx.outputs_data {
+            outputs_data = outputs_data.push(decode_hex(data)?.pack());
+        }
+        let mut witnesses = BytesVec::new_builder();
+        for witness in &tx.witnesses {
+            witnesses = witnesses.push(decode_hex(witness)?.pack());
+        }
+        let raw = RawTransaction::new_builder()
+            .version(decode_u32(&tx.version)?.pack())
+            .cell_deps(CellDepVec::new_builder().set(cell_deps).build())
+            .header_deps(
+                Byte32Vec::new_builder()
+                    .set(
+                        header_deps
+                            .iter()
+                            .map(|hash| Byte32::new_unchecked(hash.to_vec().into()))
+                            .collect(),
+                    )
+                    .build(),
+            )
+            .inputs(CellInputVec::new_builder().set(inputs).build())
+            .outputs(CellOutputVec::new_builder().set(outputs).build())
+            .outputs_data(outputs_data.build())
+            .build();
+        let transaction = Transaction::new_builder()
+            .raw(raw.clone())
+            .witnesses(witnesses.build())
+            .build();
+
+        Ok(MockCKBChain {
+            tx_hash: blake2b_256(raw.as_slice()),
+            transaction,
+            inputs: resolved_inputs,
+            cell_deps: resolved_cell_deps,
+            header_deps: resolved_header_deps,
+            ..Default::default()
+        })
+    }
+
+    /// Build a chain from a ckb-debugger mock transaction file
+    pub fn from_mock_tx_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mock_tx_json(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::CkbChainInterface;
+    use ckb_std::ckb_constants::Source;
+
+    const LOCK_CODE_HASH: &str =
+        "0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8";
+    const INPUT_TX_HASH: &str =
+        "0xa563884b3686078ec7e7677a5f86449b15cf2693f3c1241766c6996f206cc541";
+    const DEP_TX_HASH: &str = "0x71a7ba8fc96349fea0ed3a5c47992e3b4084b031a42264a018e0072e8172e46c";
+
+    fn mock_tx_json() -> String {
+        // the dep group cell at index 0 lists the code cell at index 1
+        let mut dep_group_data = 1u32.to_le_bytes().to_vec();
+        dep_group_data.extend_from_slice(&decode_hash(DEP_TX_HASH).unwrap());
+        dep_group_data.extend_from_slice(&1u32.to_le_bytes());
+        let dep_group_hex: String = dep_group_data
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let lock = format!(
+            r#"{{"code_hash": "{}", "hash_type": "type", "args": "0x01"}}"#,
+            LOCK_CODE_HASH
+        );
+        format!(
+            r#"{{
+                "mock_info": {{
+                    "inputs": [{{
+                        "input": {{"since": "0x0", "previous_output": {{"tx_hash": "{input_tx}", "index": "0x0"}}}},
+                        "output": {{"capacity": "0x2540be400", "lock": {lock}, "type": null}},
+                        "data": "0x2a",
+                        "header": null
+                    }}],
+                    "cell_deps": [{{
+                        "cell_dep": {{"out_point": {{"tx_hash": "{dep_tx}", "index": "0x0"}}, "dep_type": "dep_group"}},
+                        "output": {{"capacity": "0x0", "lock": {lock}, "type": null}},
+                        "data": "0x{dep_group}"
+                    }}, {{
+                        "cell_dep": {{"out_point": {{"tx_hash": "{dep_tx}", "index": "0x1"}}, "dep_type": "code"}},
+                        "output": {{"capacity": "0x0", "lock": {lock}, "type": null}},
+                        "data": "0xdeadbeef"
+                    }}],
+                    "header_deps": []
+                }},
+                "tx": {{
+                    "version": "0x0",
+                    "cell_deps": [{{"out_point": {{"tx_hash": "{dep_tx}", "index": "0x0"}}, "dep_type": "dep_group"}}],
+                    "header_deps": [],
+                    "inputs": [{{"since": "0x0", "previous_output": {{"tx_hash": "{input_tx}", "index": "0x0"}}}}],
+                    "outputs": [{{"capacity": "0x2540be400", "lock": {lock}, "type": null}}],
+                    "outputs_data": ["0x2b"],
+                    "witnesses": ["0x"]
+                }}
+            }}"#,
+            input_tx = INPUT_TX_HASH,
+            dep_tx = DEP_TX_HASH,
+            dep_group = dep_group_hex,
+            lock = lock,
+        )
+    }
+
+    #[test]
+    fn test_from_mock_tx_json() {
+        let chain = MockCKBChain::from_mock_tx_json(&mock_tx_json()).unwrap();
+        let raw = chain.load_transaction().unwrap().raw();
+
+        assert_eq!(chain.load_tx_hash().unwrap(), blake2b_256(raw.as_slice()));
+        assert_eq!(chain.load_cell_data(0, Source::Input).unwrap(), vec![0x2a]);
+        assert_eq!(chain.load_cell_data(0, Source::Output).unwrap(), vec![0x2b]);
+        assert_eq!(
+            chain.load_cell_capacity(0, Source::Input).unwrap(),
+            10_000_000_000
+        );
+        // the dep group is expanded into its member cells
+        assert_eq!(
+            chain.load_cell_data(0, Source::CellDep).unwrap(),
+            vec![0xde, 0xad, 0xbe, 0xef]
+        );
+        assert!(chain.load_cell(1, Source::CellDep).is_err());
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let json = mock_tx_json().replacen(INPUT_TX_HASH, DEP_TX_HASH, 1);
+        match MockCKBChain::from_mock_tx_json(&json) {
+            Err(MockTxError::MissingCell(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_invalid_dep_group() {
+        // the dep group lists two out points but holds one
+        let json = mock_tx_json().replacen("\"0x01000000", "\"0x02000000", 1);
+        match MockCKBChain::from_mock_tx_json(&json) {
+            Err(MockTxError::InvalidField(_)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+}
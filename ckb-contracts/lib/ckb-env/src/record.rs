@@ -0,0 +1,336 @@
+//! Recording of syscalls, so a run in the VM can be replayed natively by
+//! `replay::ReplayCKBChain`.
+//!
+//! `RecordingChain` forwards every syscall to the chain it wraps and outputs the request and
+//! its response as a trace line through the debug syscall. Contracts built with the `record`
+//! feature run against it through `contract_entry!`, and the trace lines can be collected from
+//! the debug messages captured by ckb-testtool.
+//!
+//! A trace line is made of space separated fields:
+//!
+//! ```text
+//! ckb-env-trace <call> <index> <source> <field> <offset> <result> [<data in hex>]
+//! ```
+//!
+//! `source` and `field` are the numeric values of the syscall constants, `result` is `ok:<len>`,
+//! `length-not-enough:<len>`, `index-out-of-bound`, `item-missing`, `encoding` or
+//! `unknown:<code>`, and data are the bytes copied into the buffer of the script.
+
+use crate::cycles::Operation;
+use crate::traits::{CkbChainInterface, CkbSyscallInterface, DynamicLoadingInterface};
+use ckb_std::ckb_constants::{CellField, HeaderField, InputField, Source};
+use ckb_std::dynamic_loading::Error as DlError;
+use ckb_std::error::SysError;
+use core::fmt::Write;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+        use std::string::{String, ToString};
+        use std::vec::Vec;
+    } else {
+        use alloc::string::{String, ToString};
+        use alloc::vec::Vec;
+    }
+}
+
+/// Prefix of the debug messages which are trace lines
+pub const TRACE_PREFIX: &str = "ckb-env-trace";
+
+/// A recorded syscall
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub request: TraceRequest,
+    pub result: Result<usize, SysError>,
+    /// The bytes copied into the buffer of the script
+    pub data: Vec<u8>,
+}
+
+/// The arguments which identify a syscall, calls without an index, a source or a field record
+/// them as 0
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraceRequest {
+    pub call: String,
+    pub index: usize,
+    pub source: u64,
+    pub field: u64,
+    pub offset: usize,
+}
+
+impl TraceRequest {
+    pub fn new(call: &str, index: usize, source: u64, field: u64, offset: usize) -> Self {
+        Self {
+            call: call.to_string(),
+            index,
+            source,
+            field,
+            offset,
+        }
+    }
+}
+
+impl TraceEntry {
+    pub fn to_line(&self) -> String {
+        let request = &self.request;
+        let mut line = String::new();
+        write!(
+            line,
+            "{} {} {} {} {} {} ",
+            TRACE_PREFIX,
+            request.call,
+            request.index,
+            request.source,
+            request.field,
+            request.offset
+        )
+        .unwrap();
+        match self.result {
+            Ok(len) => write!(line, "ok:{}", len),
+            Err(SysError::LengthNotEnough(len)) => write!(line, "length-not-enough:{}", len),
+            Err(SysError::IndexOutOfBound) => write!(line, "index-out-of-bound"),
+            Err(SysError::ItemMissing) => write!(line, "item-missing"),
+            Err(SysError::Encoding) => write!(line, "encoding"),
+            Err(SysError::Unknown(code)) => write!(line, "unknown:{}", code),
+        }
+        .unwrap();
+        if !self.data.is_empty() {
+            line.push(' ');
+            for byte in &self.data {
+                write!(line, "{:02x}", byte).unwrap();
+            }
+        }
+        line
+    }
+
+    /// Parse a trace line, `None` if the line is not a well formed trace line
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        if fields.next()? != TRACE_PREFIX {
+            return None;
+        }
+        let call = fields.next()?;
+        let index = fields.next()?.parse().ok()?;
+        let source = fields.next()?.parse().ok()?;
+        let field = fields.next()?.parse().ok()?;
+        let offset = fields.next()?.parse().ok()?;
+        let result = fields.next()?;
+        let (status, value) = match result.find(':') {
+            Some(pos) => (&result[..pos], result[pos + 1..].parse().ok()),
+            None => (result, None),
+        };
+        let result = match (status, value) {
+            ("ok", Some(len)) => Ok(len as usize),
+            ("length-not-enough", Some(len)) => Err(SysError::LengthNotEnough(len as usize)),
+            ("unknown", Some(code)) => Err(SysError::Unknown(code)),
+            ("index-out-of-bound", None) => Err(SysError::IndexOutOfBound),
+            ("item-missing", None) => Err(SysError::ItemMissing),
+            ("encoding", None) => Err(SysError::Encoding),
+            _ => return None,
+        };
+        let data = match fields.next() {
+            Some(hex) if hex.len() % 2 == 0 => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?,
+            Some(_) => return None,
+            None => Vec::new(),
+        };
+        Some(Self {
+            request: TraceRequest::new(call, index, source, field, offset),
+            result,
+            data,
+        })
+    }
+}
+
+/// Chain wrapper which traces every syscall made through it, see the module documentation
+pub struct RecordingChain<T> {
+    pub inner: T,
+}
+
+impl<T: CkbSyscallInterface> RecordingChain<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    fn record(
+        &self,
+        request: TraceRequest,
+        buf: &[u8],
+        result: Result<usize, SysError>,
+    ) -> Result<usize, SysError> {
+        let copied = match result {
+            Ok(len) => core::cmp::min(len, buf.len()),
+            Err(SysError::LengthNotEnough(_)) => buf.len(),
+            Err(_) => 0,
+        };
+        let entry = TraceEntry {
+            request,
+            result,
+            data: buf[..copied].to_vec(),
+        };
+        self.inner.sys_debug(entry.to_line());
+        result
+    }
+}
+
+impl<T: CkbChainInterface> CkbChainInterface for RecordingChain<T> {
+    fn charge_cycles(&self, operation: Operation) {
+        self.inner.charge_cycles(operation)
+    }
+}
+
+/// Libraries are loaded by the wrapped chain, the lookup of their cell dep is not traced
+impl<T: DynamicLoadingInterface> DynamicLoadingInterface for RecordingChain<T> {
+    type Library = T::Library;
+
+    fn load_library(&self, dep_cell_data_hash: &[u8; 32]) -> Result<Self::Library, DlError> {
+        self.inner.load_library(dep_cell_data_hash)
+    }
+}
+
+impl<T: CkbSyscallInterface> CkbSyscallInterface for RecordingChain<T> {
+    fn sys_debug(&self, message: String) {
+        self.inner.sys_debug(message)
+    }
+
+    fn sys_load_tx_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_tx_hash(buf, offset);
+        self.record(TraceRequest::new("tx_hash", 0, 0, 0, offset), buf, result)
+    }
+
+    fn sys_load_script_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_script_hash(buf, offset);
+        self.record(
+            TraceRequest::new("script_hash", 0, 0, 0, offset),
+            buf,
+            result,
+        )
+    }
+
+    fn sys_load_script(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_script(buf, offset);
+        self.record(TraceRequest::new("script", 0, 0, 0, offset), buf, result)
+    }
+
+    fn sys_load_transaction(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_transaction(buf, offset);
+        self.record(
+            TraceRequest::new("transaction", 0, 0, 0, offset),
+            buf,
+            result,
+        )
+    }
+
+    fn sys_load_cell(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_cell(buf, offset, index, source);
+        let request = TraceRequest::new("cell", index, source as u64, 0, offset);
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_cell_data(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_cell_data(buf, offset, index, source);
+        let request = TraceRequest::new("cell_data", index, source as u64, 0, offset);
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_cell_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: CellField,
+    ) -> Result<usize, SysError> {
+        let result = self
+            .inner
+            .sys_load_cell_by_field(buf, offset, index, source, field);
+        let request =
+            TraceRequest::new("cell_by_field", index, source as u64, field as u64, offset);
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_input(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_input(buf, offset, index, source);
+        let request = TraceRequest::new("input", index, source as u64, 0, offset);
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_input_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: InputField,
+    ) -> Result<usize, SysError> {
+        let result = self
+            .inner
+            .sys_load_input_by_field(buf, offset, index, source, field);
+        let request =
+            TraceRequest::new("input_by_field", index, source as u64, field as u64, offset);
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_header(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_header(buf, offset, index, source);
+        let request = TraceRequest::new("header", index, source as u64, 0, offset);
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_header_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: HeaderField,
+    ) -> Result<usize, SysError> {
+        let result = self
+            .inner
+            .sys_load_header_by_field(buf, offset, index, source, field);
+        let request = TraceRequest::new(
+            "header_by_field",
+            index,
+            source as u64,
+            field as u64,
+            offset,
+        );
+        self.record(request, buf, result)
+    }
+
+    fn sys_load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let result = self.inner.sys_load_witness(buf, offset, index, source);
+        let request = TraceRequest::new("witness", index, source as u64, 0, offset);
+        self.record(request, buf, result)
+    }
+}
@@ -0,0 +1,333 @@
+use crate::cycles::{syscall_cycles, Operation};
+use crate::record::{TraceEntry, TraceRequest};
+use crate::traits::{CkbChainInterface, CkbSyscallInterface};
+use ckb_std::ckb_constants::{CellField, HeaderField, InputField, Source};
+use ckb_std::error::SysError;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Chain answering syscalls from a trace recorded by `record::RecordingChain`.
+///
+/// Every syscall must have been made by the recorded run with the same arguments, otherwise
+/// the native run diverged from the VM one and the replay panics. The responses are looked up
+/// by request, not by position, so the native run may repeat or reorder loads. A request
+/// recorded several times keeps the entry which copied the most data, so a load is served
+/// whatever the buffer the recorded run used for it first. Cycles and debug messages are kept
+/// the same way `mock::MockCKBChain` does.
+#[derive(Debug, Default)]
+pub struct ReplayCKBChain {
+    pub trace: HashMap<TraceRequest, TraceEntry>,
+    pub cycles: Cell<u64>,
+    pub debug_messages: RefCell<Vec<String>>,
+}
+
+impl ReplayCKBChain {
+    /// Build a chain from the debug messages of a recorded run, the messages which are not
+    /// trace lines are ignored
+    pub fn from_trace<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> Self {
+        let mut trace: HashMap<TraceRequest, TraceEntry> = HashMap::new();
+        for entry in lines.into_iter().filter_map(TraceEntry::from_line) {
+            let replace = match trace.get(&entry.request) {
+                Some(kept) => kept.data.len() < entry.data.len(),
+                None => true,
+            };
+            if replace {
+                trace.insert(entry.request.clone(), entry);
+            }
+        }
+        Self {
+            trace,
+            ..Default::default()
+        }
+    }
+
+    /// Build a chain from a file with one debug message per line
+    pub fn from_trace_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::from_trace(fs::read_to_string(path)?.lines()))
+    }
+
+    /// Cycles consumed so far by syscalls and marked operations
+    pub fn cycles(&self) -> u64 {
+        self.cycles.get()
+    }
+
+    /// Debug messages output so far
+    pub fn debug_messages(&self) -> Vec<String> {
+        self.debug_messages.borrow().clone()
+    }
+
+    fn replay(&self, request: TraceRequest, buf: &mut [u8]) -> Result<usize, SysError> {
+        let entry = self.trace.get(&request).unwrap_or_else(|| {
+            panic!(
+                "{:?} is not in the trace, the run diverged from the recorded one",
+                request
+            )
+        });
+        let full_size = match entry.result {
+            Ok(len) | Err(SysError::LengthNotEnough(len)) => len,
+            Err(err) => {
+                self.charge_cycles(Operation::Cycles(syscall_cycles(0)));
+                return Err(err);
+            }
+        };
+        let real_size = core::cmp::min(buf.len(), full_size);
+        assert!(
+            real_size <= entry.data.len(),
+            "the trace holds {} bytes of {:?}, {} are loaded",
+            entry.data.len(),
+            request,
+            real_size
+        );
+        buf[..real_size].copy_from_slice(&entry.data[..real_size]);
+        self.charge_cycles(Operation::Cycles(syscall_cycles(real_size)));
+        if full_size > buf.len() {
+            Err(SysError::LengthNotEnough(full_size))
+        } else {
+            Ok(full_size)
+        }
+    }
+}
+
+impl CkbChainInterface for ReplayCKBChain {
+    fn charge_cycles(&self, operation: Operation) {
+        self.cycles.set(self.cycles.get() + operation.cycles());
+    }
+}
+
+impl CkbSyscallInterface for ReplayCKBChain {
+    fn sys_debug(&self, message: String) {
+        self.charge_cycles(Operation::Cycles(syscall_cycles(0)));
+        println!("[contract debug] {}", message);
+        self.debug_messages.borrow_mut().push(message);
+    }
+
+    fn sys_load_tx_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.replay(TraceRequest::new("tx_hash", 0, 0, 0, offset), buf)
+    }
+
+    fn sys_load_script_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.replay(TraceRequest::new("script_hash", 0, 0, 0, offset), buf)
+    }
+
+    fn sys_load_script(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.replay(TraceRequest::new("script", 0, 0, 0, offset), buf)
+    }
+
+    fn sys_load_transaction(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError> {
+        self.replay(TraceRequest::new("transaction", 0, 0, 0, offset), buf)
+    }
+
+    fn sys_load_cell(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let request = TraceRequest::new("cell", index, source as u64, 0, offset);
+        self.replay(request, buf)
+    }
+
+    fn sys_load_cell_data(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let request = TraceRequest::new("cell_data", index, source as u64, 0, offset);
+        self.replay(request, buf)
+    }
+
+    fn sys_load_cell_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: CellField,
+    ) -> Result<usize, SysError> {
+        let request =
+            TraceRequest::new("cell_by_field", index, source as u64, field as u64, offset);
+        self.replay(request, buf)
+    }
+
+    fn sys_load_input(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let request = TraceRequest::new("input", index, source as u64, 0, offset);
+        self.replay(request, buf)
+    }
+
+    fn sys_load_input_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: InputField,
+    ) -> Result<usize, SysError> {
+        let request =
+            TraceRequest::new("input_by_field", index, source as u64, field as u64, offset);
+        self.replay(request, buf)
+    }
+
+    fn sys_load_header(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let request = TraceRequest::new("header", index, source as u64, 0, offset);
+        self.replay(request, buf)
+    }
+
+    fn sys_load_header_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: HeaderField,
+    ) -> Result<usize, SysError> {
+        let request = TraceRequest::new(
+            "header_by_field",
+            index,
+            source as u64,
+            field as u64,
+            offset,
+        );
+        self.replay(request, buf)
+    }
+
+    fn sys_load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError> {
+        let request = TraceRequest::new("witness", index, source as u64, 0, offset);
+        self.replay(request, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{blake2b_256, MockCKBChain, MockCell, MockLibrary};
+    use crate::record::RecordingChain;
+    use crate::traits::{DynamicLoadingInterface, LibraryInterface};
+    use ckb_std::ckb_types::{packed::*, prelude::*};
+
+    #[test]
+    fn test_record_and_replay() {
+        let lock = BytesOpt::new_builder()
+            .set(Some(vec![7u8; 1500].pack()))
+            .build();
+        let witness = WitnessArgs::new_builder().lock(lock).build();
+        let transaction = Transaction::new_builder()
+            .witnesses(
+                BytesVec::new_builder()
+                    .push(witness.as_slice().to_vec().pack())
+                    .build(),
+            )
+            .build();
+        let recording = RecordingChain::new(MockCKBChain {
+            tx_hash: [1u8; 32],
+            transaction,
+            ..Default::default()
+        });
+        recording.debug(format_args!("not a trace line"));
+        recording.load_tx_hash().unwrap();
+        recording.load_witness_args(0, Source::Input).unwrap();
+        recording.load_cell(0, Source::Input).unwrap_err();
+
+        let messages = recording.inner.debug_messages();
+        assert_eq!(messages[0], "not a trace line");
+        let chain = ReplayCKBChain::from_trace(messages.iter().map(String::as_str));
+        assert_eq!(chain.load_tx_hash().unwrap(), [1u8; 32]);
+        assert_eq!(
+            chain
+                .load_witness_args(0, Source::Input)
+                .unwrap()
+                .as_slice(),
+            witness.as_slice()
+        );
+        // a smaller buffer than the recorded one is served from the recorded data
+        let mut buf = [0u8; 100];
+        assert_eq!(
+            chain.sys_load_witness(&mut buf, 0, 0, Source::Input),
+            Err(SysError::LengthNotEnough(witness.as_slice().len()))
+        );
+        assert_eq!(&buf[..], &witness.as_slice()[..100]);
+        assert_eq!(
+            chain.load_cell(0, Source::Input).unwrap_err(),
+            SysError::IndexOutOfBound
+        );
+    }
+
+    #[test]
+    fn test_replay_repeated_request() {
+        let witness = vec![5u8; 300];
+        let transaction = Transaction::new_builder()
+            .witnesses(BytesVec::new_builder().push(witness.pack()).build())
+            .build();
+        let recording = RecordingChain::new(MockCKBChain {
+            transaction,
+            ..Default::default()
+        });
+        // the whole witness, then its first bytes only
+        let mut buf = [0u8; 400];
+        recording
+            .sys_load_witness(&mut buf, 0, 0, Source::Input)
+            .unwrap();
+        let mut buf = [0u8; 10];
+        recording
+            .sys_load_witness(&mut buf, 0, 0, Source::Input)
+            .unwrap_err();
+
+        let messages = recording.inner.debug_messages();
+        let chain = ReplayCKBChain::from_trace(messages.iter().map(String::as_str));
+        let mut buf = [0u8; 400];
+        assert_eq!(
+            chain.sys_load_witness(&mut buf, 0, 0, Source::Input),
+            Ok(300)
+        );
+        assert_eq!(&buf[..300], &witness[..]);
+    }
+
+    #[test]
+    fn test_record_dynamic_loading() {
+        extern "C" fn one() -> u64 {
+            1
+        }
+        let code = b"shared library".to_vec();
+        let mut chain = MockCKBChain {
+            cell_deps: vec![MockCell::new(Default::default(), code.clone())],
+            ..Default::default()
+        };
+        chain.register_library(&code, MockLibrary::new().symbol(b"one", one as *const u8));
+        let recording = RecordingChain::new(chain);
+        let library = recording.load_library(&blake2b_256(&code)).unwrap();
+        unsafe {
+            let one: extern "C" fn() -> u64 = library.get(b"one").unwrap();
+            assert_eq!(one(), 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not in the trace")]
+    fn test_replay_diverged() {
+        let chain = ReplayCKBChain::from_trace(vec!["ckb-env-trace tx_hash 0 0 0 0 ok:0"]);
+        chain.load_script_hash().unwrap();
+    }
+}
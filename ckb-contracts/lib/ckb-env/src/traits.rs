@@ -1,17 +1,355 @@
+use crate::cycles::Operation;
+use ckb_std::ckb_constants::{CellField, HeaderField, InputField, Source};
+use ckb_std::ckb_types::{packed::*, prelude::*};
+use ckb_std::dynamic_loading::Error as DlError;
 use ckb_std::error::SysError;
+use ckb_std::high_level::BUF_SIZE;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
+        use std::fmt::format;
     } else {
+        use alloc::fmt::format;
+        use alloc::string::String;
+        use alloc::vec;
+        use alloc::vec::Vec;
     }
 }
 
-pub trait CkbChainInterface {
-    fn load_tx_hash(&self) -> Result<[u8; 32], SysError>;
-    // fn load_script_hash(&self) -> Result<[u8; 32], String>;
-    // fn load_cell(index: usize, source: Source) -> Result<CellOutput, SysError>;
+/// Low level syscalls which load data into a caller provided buffer.
+///
+/// Every method mirrors the function in `ckb_std::syscalls` named without the `sys_` prefix.
+/// The data is loaded starting from `offset`, as many bytes as `buf` can hold are copied and
+/// `SysError::LengthNotEnough(actual)` is returned when the remaining data is longer than
+/// `buf`, so large items such as witnesses can be read in chunks.
+pub trait CkbSyscallInterface {
+    fn sys_debug(&self, message: String);
+    fn sys_load_tx_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError>;
+    fn sys_load_script_hash(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError>;
+    fn sys_load_script(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError>;
+    fn sys_load_transaction(&self, buf: &mut [u8], offset: usize) -> Result<usize, SysError>;
+    fn sys_load_cell(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError>;
+    fn sys_load_cell_data(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError>;
+    fn sys_load_cell_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: CellField,
+    ) -> Result<usize, SysError>;
+    fn sys_load_input(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError>;
+    fn sys_load_input_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: InputField,
+    ) -> Result<usize, SysError>;
+    fn sys_load_header(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError>;
+    fn sys_load_header_by_field(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+        field: HeaderField,
+    ) -> Result<usize, SysError>;
+    fn sys_load_witness(
+        &self,
+        buf: &mut [u8],
+        offset: usize,
+        index: usize,
+        source: Source,
+    ) -> Result<usize, SysError>;
 }
 
+/// Common method to fully load data from syscall, the same as `ckb_std::high_level` does
+fn load_data<F: Fn(&mut [u8], usize) -> Result<usize, SysError>>(
+    syscall: F,
+) -> Result<Vec<u8>, SysError> {
+    let mut buf = [0u8; BUF_SIZE];
+    match syscall(&mut buf, 0) {
+        Ok(len) => Ok(buf[..len].to_vec()),
+        Err(SysError::LengthNotEnough(actual_size)) => {
+            let mut data = vec![0u8; actual_size];
+            let loaded_len = buf.len();
+            data[..loaded_len].copy_from_slice(&buf);
+            let len = syscall(&mut data[loaded_len..], loaded_len)?;
+            debug_assert_eq!(len + loaded_len, actual_size);
+            Ok(data)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn load_u64<F: Fn(&mut [u8], usize) -> Result<usize, SysError>>(
+    syscall: F,
+) -> Result<u64, SysError> {
+    let mut buf = [0u8; 8];
+    let len = syscall(&mut buf, 0)?;
+    debug_assert_eq!(len, buf.len());
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn load_hash<F: Fn(&mut [u8], usize) -> Result<usize, SysError>>(
+    syscall: F,
+) -> Result<[u8; 32], SysError> {
+    let mut hash = [0u8; 32];
+    let len = syscall(&mut hash, 0)?;
+    debug_assert_eq!(hash.len(), len);
+    Ok(hash)
+}
+
+/// High level view of the transaction being verified.
+///
+/// Every method mirrors the function with the same name in `ckb_std::high_level` and is
+/// implemented the same way on top of `CkbSyscallInterface`, so contract logic written against
+/// this trait behaves the same on-chain (`chain::CKBChain`) and in native tests
+/// (`mock::MockCKBChain`).
+pub trait CkbChainInterface: CkbSyscallInterface {
+    fn load_tx_hash(&self) -> Result<[u8; 32], SysError> {
+        load_hash(|buf, offset| self.sys_load_tx_hash(buf, offset))
+    }
+
+    fn load_script_hash(&self) -> Result<[u8; 32], SysError> {
+        load_hash(|buf, offset| self.sys_load_script_hash(buf, offset))
+    }
+
+    fn load_script(&self) -> Result<Script, SysError> {
+        let data = load_data(|buf, offset| self.sys_load_script(buf, offset))?;
+        match ScriptReader::verify(&data, false) {
+            Ok(()) => Ok(Script::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_transaction(&self) -> Result<Transaction, SysError> {
+        let data = load_data(|buf, offset| self.sys_load_transaction(buf, offset))?;
+        match TransactionReader::verify(&data, false) {
+            Ok(()) => Ok(Transaction::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_cell(&self, index: usize, source: Source) -> Result<CellOutput, SysError> {
+        let data = load_data(|buf, offset| self.sys_load_cell(buf, offset, index, source))?;
+        match CellOutputReader::verify(&data, false) {
+            Ok(()) => Ok(CellOutput::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_cell_data(&self, index: usize, source: Source) -> Result<Vec<u8>, SysError> {
+        load_data(|buf, offset| self.sys_load_cell_data(buf, offset, index, source))
+    }
+
+    fn load_cell_capacity(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_u64(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::Capacity)
+        })
+    }
+
+    fn load_cell_occupied_capacity(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_u64(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::OccupiedCapacity)
+        })
+    }
+
+    fn load_cell_data_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        load_hash(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::DataHash)
+        })
+    }
+
+    fn load_cell_lock_hash(&self, index: usize, source: Source) -> Result<[u8; 32], SysError> {
+        load_hash(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::LockHash)
+        })
+    }
+
+    /// return None if the cell has no type
+    fn load_cell_type_hash(
+        &self,
+        index: usize,
+        source: Source,
+    ) -> Result<Option<[u8; 32]>, SysError> {
+        match load_hash(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::TypeHash)
+        }) {
+            Ok(hash) => Ok(Some(hash)),
+            Err(SysError::ItemMissing) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn load_cell_lock(&self, index: usize, source: Source) -> Result<Script, SysError> {
+        let data = load_data(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::Lock)
+        })?;
+        match ScriptReader::verify(&data, false) {
+            Ok(()) => Ok(Script::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    /// return None if the cell has no type
+    fn load_cell_type(&self, index: usize, source: Source) -> Result<Option<Script>, SysError> {
+        let data = match load_data(|buf, offset| {
+            self.sys_load_cell_by_field(buf, offset, index, source, CellField::Type)
+        }) {
+            Ok(data) => data,
+            Err(SysError::ItemMissing) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        match ScriptReader::verify(&data, false) {
+            Ok(()) => Ok(Some(Script::new_unchecked(data.into()))),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_input(&self, index: usize, source: Source) -> Result<CellInput, SysError> {
+        let data = load_data(|buf, offset| self.sys_load_input(buf, offset, index, source))?;
+        match CellInputReader::verify(&data, false) {
+            Ok(()) => Ok(CellInput::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_input_since(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_u64(|buf, offset| {
+            self.sys_load_input_by_field(buf, offset, index, source, InputField::Since)
+        })
+    }
+
+    fn load_input_out_point(&self, index: usize, source: Source) -> Result<OutPoint, SysError> {
+        let mut buf = [0u8; 36];
+        let len = self.sys_load_input_by_field(&mut buf, 0, index, source, InputField::OutPoint)?;
+        debug_assert_eq!(len, buf.len());
+        match OutPointReader::verify(&buf, false) {
+            Ok(()) => Ok(OutPoint::new_unchecked(buf.to_vec().into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_header(&self, index: usize, source: Source) -> Result<Header, SysError> {
+        let data = load_data(|buf, offset| self.sys_load_header(buf, offset, index, source))?;
+        match HeaderReader::verify(&data, false) {
+            Ok(()) => Ok(Header::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    fn load_header_epoch_number(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_u64(|buf, offset| {
+            self.sys_load_header_by_field(buf, offset, index, source, HeaderField::EpochNumber)
+        })
+    }
+
+    fn load_header_epoch_start_block_number(
+        &self,
+        index: usize,
+        source: Source,
+    ) -> Result<u64, SysError> {
+        load_u64(|buf, offset| {
+            self.sys_load_header_by_field(
+                buf,
+                offset,
+                index,
+                source,
+                HeaderField::EpochStartBlockNumber,
+            )
+        })
+    }
+
+    fn load_header_epoch_length(&self, index: usize, source: Source) -> Result<u64, SysError> {
+        load_u64(|buf, offset| {
+            self.sys_load_header_by_field(buf, offset, index, source, HeaderField::EpochLength)
+        })
+    }
+
+    fn load_witness_args(&self, index: usize, source: Source) -> Result<WitnessArgs, SysError> {
+        let data = load_data(|buf, offset| self.sys_load_witness(buf, offset, index, source))?;
+        match WitnessArgsReader::verify(&data, false) {
+            Ok(()) => Ok(WitnessArgs::new_unchecked(data.into())),
+            Err(_err) => Err(SysError::Encoding),
+        }
+    }
+
+    /// Mark an expensive operation performed by the script.
+    ///
+    /// The VM meters cycles by itself so this does nothing on-chain, the mock chain adds the
+    /// estimated cycles of the operation to its counter.
+    fn charge_cycles(&self, _operation: Operation) {}
+
+    /// Output a formatted debug message, use the `debug!` macro instead of calling it directly
+    fn debug(&self, args: core::fmt::Arguments) {
+        self.sys_debug(format(args))
+    }
+}
+
+/// A shared library loaded by `DynamicLoadingInterface`
+pub trait LibraryInterface {
+    /// Look up an exported symbol, `None` if the library does not export it.
+    ///
+    /// # Safety
+    ///
+    /// `S` must be the `extern "C"` function pointer type of the symbol, like
+    /// `ckb_std::dynamic_loading::Library::get` requires.
+    unsafe fn get<S: Copy>(&self, symbol: &[u8]) -> Option<S>;
+}
+
+/// Loading shared libraries deployed in cell deps, see `ckb_std::dynamic_loading`.
+///
+/// On-chain the library code is loaded into the script memory by `CKBDLContext`, the mock chain
+/// dispatches to native Rust functions registered for the data hash of the cell dep instead.
+pub trait DynamicLoadingInterface {
+    type Library: LibraryInterface;
+
+    /// Load the library whose code is the data of the cell dep with `dep_cell_data_hash`
+    fn load_library(&self, dep_cell_data_hash: &[u8; 32]) -> Result<Self::Library, DlError>;
+}
+
+/// A contract whose verification logic is written against `CkbChainInterface`.
+///
+/// The same type runs on-chain through `contract_entry!` and in native tests against
+/// `mock::MockCKBChain`. `Error` is the contract specific error enum, its conversion to `i8`
+/// gives the exit code of the script.
 pub trait ContractInterface<T: CkbChainInterface> {
-    fn verify(chain: T) -> i8;
+    type Error: Into<i8>;
+
+    fn verify(chain: &T) -> Result<(), Self::Error>;
+
+    /// Verify and map the result to the exit code of the script
+    fn run(chain: &T) -> i8 {
+        match Self::verify(chain) {
+            Ok(()) => 0,
+            Err(err) => err.into(),
+        }
+    }
 }
@@ -0,0 +1,41 @@
+use ckb_std::error::SysError;
+
+/// Error
+#[repr(i8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    IndexOutOfBound = 1,
+    ItemMissing,
+    LengthNotEnough,
+    Encoding,
+    /// No cell dep is the light-client cell of the script args
+    MissingLightClient,
+    /// The data of the light-client cell is not a light-client state
+    InvalidLightClient,
+    /// The proven header is not an RLP encoded header
+    InvalidHeader,
+    /// The proven header is not in the chain the light client follows
+    UnknownHeader,
+    /// The proven header has fewer headers on top of it than the confirmations of the config
+    /// of the light-client cell
+    Unconfirmed,
+}
+
+impl From<SysError> for Error {
+    fn from(err: SysError) -> Self {
+        use SysError::*;
+        match err {
+            IndexOutOfBound => Self::IndexOutOfBound,
+            ItemMissing => Self::ItemMissing,
+            LengthNotEnough(_) => Self::LengthNotEnough,
+            Encoding => Self::Encoding,
+            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+        }
+    }
+}
+
+impl From<Error> for i8 {
+    fn from(err: Error) -> i8 {
+        err as i8
+    }
+}
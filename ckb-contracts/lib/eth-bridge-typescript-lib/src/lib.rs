@@ -1,32 +1,449 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-pub mod debug;
+pub mod error;
 
-use ckb_env::traits::CkbChainInterface;
+pub use ckb_env::debug;
+
+use ckb_env::cycles::Operation;
+use ckb_env::traits::{CkbChainInterface, ContractInterface};
+use ckb_std::ckb_constants::Source;
+use ckb_std::error::SysError;
+use error::Error;
+use eth_light_client_typescript_lib::config::{Config, Consensus};
+use eth_light_client_typescript_lib::header::Header;
+use eth_light_client_typescript_lib::rlp;
+use eth_light_client_typescript_lib::state::{
+    CliqueClientState, HeaderChain, LightClientState, ParliaClientState,
+};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
     } else {
         extern crate alloc;
+        use alloc::vec::Vec;
     }
 }
 
-pub fn verify<T: CkbChainInterface>(chain: T) -> i8 {
-    let tx = chain.load_tx_hash();
-    debug!("tx: {:?}", &tx);
-    return 0;
+/// Type script of the bridge cells.
+///
+/// A header proof in the `output_type` of the witness of the first output cell proves that an
+/// Ethereum header is in the chain followed by the light-client cell whose type script hash is
+/// the script args, which the transaction references as a cell dep. The proof is the RLP list
+/// `[header, [sibling, ...]]` where `header` is the RLP encoded header as a byte string. The
+/// siblings are empty for a header the light-client cell keeps, and are the path of an older
+/// header to its peak in the history of the cell, see
+/// `eth_light_client_typescript_lib::state::HeaderChain::verify_inclusion`. The light-client
+/// cell may follow ethash, a clique chain or a parlia chain, the data of every mode starts
+/// with its headers, whose receipts roots the proven headers commit to. The proven header
+/// needs the `confirmations` of the config of the light-client cell on top of it, so a bridge
+/// follows the network of the chain id of that config at its depth.
+pub struct EthBridgeTypescript;
+
+impl<T: CkbChainInterface> ContractInterface<T> for EthBridgeTypescript {
+    type Error = Error;
+
+    fn verify(chain: &T) -> Result<(), Error> {
+        let tx = chain.load_tx_hash()?;
+        debug!(chain, "tx: {:?}", &tx);
+        if let Some(header) = load_header(chain)? {
+            debug!(chain, "header {}: {:?}", header.number, &header.hash);
+        }
+        Ok(())
+    }
+}
+
+/// Load the header proven with the output cell, if any
+fn load_header<T: CkbChainInterface>(chain: &T) -> Result<Option<Header>, Error> {
+    let proof = match chain.load_witness_args(0, Source::GroupOutput) {
+        Ok(witness) => match witness.output_type().to_opt() {
+            Some(proof) => proof.raw_data(),
+            None => return Ok(None),
+        },
+        Err(SysError::IndexOutOfBound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let items = rlp::decode(&proof)
+        .and_then(|item| item.list())
+        .map_err(|_| Error::Encoding)?;
+    if items.len() != 2 {
+        return Err(Error::Encoding);
+    }
+    let data = items[0].bytes().map_err(|_| Error::Encoding)?;
+    chain.charge_cycles(Operation::Keccak256(data.len()));
+    let header = Header::decode(data).map_err(|_| Error::InvalidHeader)?;
+    let siblings = items[1]
+        .list()
+        .map_err(|_| Error::Encoding)?
+        .iter()
+        .map(|sibling| {
+            let mut hash = [0u8; 32];
+            sibling.fixed(&mut hash).map_err(|_| Error::Encoding)?;
+            chain.charge_cycles(Operation::Keccak256(64));
+            Ok(hash)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let (config, headers) = load_light_client(chain)?;
+    headers
+        .verify_inclusion(header.number, &header.hash, &siblings)
+        .map_err(|_| Error::UnknownHeader)?;
+    let tip = headers.tip().ok_or(Error::InvalidLightClient)?;
+    if tip.number - header.number < config.confirmations {
+        return Err(Error::Unconfirmed);
+    }
+    Ok(Some(header))
+}
+
+/// Load the config and the headers of the light-client cell of the script args from the cell
+/// deps
+fn load_light_client<T: CkbChainInterface>(chain: &T) -> Result<(Config, HeaderChain), Error> {
+    let args = chain.load_script()?.args().raw_data();
+    if args.len() != 32 {
+        return Err(Error::Encoding);
+    }
+    for index in 0.. {
+        match chain.load_cell_type_hash(index, Source::CellDep) {
+            Ok(Some(type_hash)) if type_hash[..] == args[..] => {
+                let script = chain
+                    .load_cell_type(index, Source::CellDep)?
+                    .ok_or(Error::InvalidLightClient)?;
+                let config = Config::decode(&script.args().raw_data())
+                    .map_err(|_| Error::InvalidLightClient)?;
+                let data = chain.load_cell_data(index, Source::CellDep)?;
+                // the data of every mode starts with the headers
+                let size = match config.consensus {
+                    Consensus::Ethash(_) => LightClientState::SIZE,
+                    Consensus::Clique(_) => CliqueClientState::SIZE,
+                    Consensus::Parlia(_) => ParliaClientState::SIZE,
+                };
+                if data.len() != size {
+                    return Err(Error::InvalidLightClient);
+                }
+                let headers = HeaderChain::decode(&data[..HeaderChain::SIZE])
+                    .map_err(|_| Error::InvalidLightClient)?;
+                return Ok((config, headers));
+            }
+            Ok(_) => {}
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(Error::MissingLightClient)
 }
 
 #[cfg(test)]
 mod tests {
-    use ckb_env::mock::MockCKBChain;
-    use super::verify;
+    use super::EthBridgeTypescript;
+    use crate::error::Error;
+    use ckb_env::mock::{blake2b_256, MockCKBChain, MockCell, ScriptGroupType};
+    use ckb_env::record::{RecordingChain, TRACE_PREFIX};
+    use ckb_env::replay::ReplayCKBChain;
+    use ckb_env::traits::ContractInterface;
+    use ckb_std::ckb_types::{packed::*, prelude::*};
+    use eth_light_client_typescript_lib::hash::keccak256;
+    use eth_light_client_typescript_lib::header::Header;
+    use eth_light_client_typescript_lib::rlp;
+    use eth_light_client_typescript_lib::roots::DatasetRoots;
+    use eth_light_client_typescript_lib::clique::{Seal, Snapshot};
+    use eth_light_client_typescript_lib::config::{
+        CliqueConfig, Config, Consensus, ForkSchedule, ParliaConfig,
+    };
+    use eth_light_client_typescript_lib::parlia::{self, ValidatorSet};
+    use eth_light_client_typescript_lib::state::{
+        CliqueClientState, HeaderChain, HeaderRecord, LightClientState, ParliaClientState,
+        HEADER_SLOTS,
+    };
+
+    const MAX_CYCLES: u64 = 10_000_000;
 
     #[test]
     // #[should_panic(expected = "hello")]
     fn it_works() {
         let mock_chain = MockCKBChain::default();
-        let return_code = verify(mock_chain);
+        let return_code = EthBridgeTypescript::run(&mock_chain);
         assert_eq!(return_code, 0);
+        assert!(mock_chain.cycles() < MAX_CYCLES);
+        assert_eq!(
+            mock_chain.debug_messages(),
+            vec![format!("tx: {:?}", [0u8; 32])]
+        );
+    }
+
+    /// A header of `number` on top of `parent_hash`
+    fn header(number: u64, parent_hash: [u8; 32]) -> Header {
+        let mut header = Header {
+            hash: [0u8; 32],
+            parent_hash,
+            uncles_hash: [0u8; 32],
+            coinbase: [0u8; 20],
+            state_root: [0u8; 32],
+            transactions_root: [0u8; 32],
+            receipts_root: [0u8; 32],
+            logs_bloom: vec![0u8; 256],
+            difficulty: 1,
+            number,
+            gas_limit: 5000,
+            gas_used: 0,
+            timestamp: number,
+            extra_data: Vec::new(),
+            mix_hash: [0u8; 32],
+            nonce: [0u8; 8],
+            base_fee_per_gas: None,
+            withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
+            requests_hash: None,
+        };
+        header.hash = keccak256(&header.encode());
+        header
+    }
+
+    /// A light-client state following `len` headers from block 0, and the headers
+    fn light_client(len: usize) -> (LightClientState, Vec<Header>) {
+        let mut headers = vec![header(0, [0u8; 32])];
+        let mut chain = HeaderChain::new(HeaderRecord::new(&headers[0]), 1);
+        for number in 1..len as u64 {
+            let next = header(number, headers.last().unwrap().hash);
+            chain.push(&next).unwrap();
+            headers.push(next);
+        }
+        let state = LightClientState {
+            headers: chain,
+            roots: DatasetRoots::decode(&[0u8; DatasetRoots::SIZE]).unwrap(),
+        };
+        (state, headers)
+    }
+
+    fn encode_proof(header: &Header, siblings: &[[u8; 32]]) -> Vec<u8> {
+        let mut encoded_siblings = Vec::new();
+        for sibling in siblings {
+            rlp::encode_bytes(&mut encoded_siblings, sibling);
+        }
+        let mut items = Vec::new();
+        rlp::encode_bytes(&mut items, &header.encode());
+        rlp::encode_list(&mut items, &encoded_siblings);
+        let mut proof = Vec::new();
+        rlp::encode_list(&mut proof, &items);
+        proof
+    }
+
+    /// The config of a light-client cell following the `consensus` without confirmations
+    fn config(consensus: Consensus) -> Config {
+        Config {
+            owner_lock_hash: [1u8; 32],
+            type_id: [2u8; 32],
+            chain_id: 1,
+            consensus,
+            confirmations: 0,
+            ring_size: HEADER_SLOTS,
+        }
+    }
+
+    /// A transaction creating a bridge cell with `proof`, referencing the `light_client` cell
+    fn chain_with_proof(light_client: &LightClientState, proof: Vec<u8>) -> MockCKBChain {
+        let config = config(Consensus::Ethash(ForkSchedule::MAINNET));
+        chain_with_light_client(&config, light_client.encode(), proof)
+    }
+
+    /// A transaction creating a bridge cell with `proof`, referencing a light-client cell of
+    /// `config` with `data`
+    fn chain_with_light_client(config: &Config, data: Vec<u8>, proof: Vec<u8>) -> MockCKBChain {
+        let light_client_type = Script::new_builder().args(config.encode().pack()).build();
+        let light_client_cell = CellOutput::new_builder()
+            .type_(
+                ScriptOpt::new_builder()
+                    .set(Some(light_client_type.clone()))
+                    .build(),
+            )
+            .build();
+        let type_script = Script::new_builder()
+            .args(blake2b_256(light_client_type.as_slice()).to_vec().pack())
+            .build();
+        let output = CellOutput::new_builder()
+            .type_(
+                ScriptOpt::new_builder()
+                    .set(Some(type_script.clone()))
+                    .build(),
+            )
+            .build();
+        let witness = WitnessArgs::new_builder()
+            .output_type(BytesOpt::new_builder().set(Some(proof.pack())).build())
+            .build();
+        let transaction = Transaction::new_builder()
+            .raw(
+                RawTransaction::new_builder()
+                    .outputs(CellOutputVec::new_builder().push(output).build())
+                    .outputs_data(BytesVec::new_builder().push(Bytes::default()).build())
+                    .build(),
+            )
+            .witnesses(
+                BytesVec::new_builder()
+                    .push(witness.as_slice().to_vec().pack())
+                    .build(),
+            )
+            .build();
+        let mut chain = MockCKBChain {
+            transaction,
+            cell_deps: vec![
+                MockCell::default(),
+                MockCell::new(light_client_cell, data),
+            ],
+            ..Default::default()
+        };
+        chain.set_script(type_script, ScriptGroupType::Type);
+        chain
+    }
+
+    #[test]
+    fn test_prove_kept_header() {
+        let (state, headers) = light_client(3);
+        let chain = chain_with_proof(&state, encode_proof(&headers[1], &[]));
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
+        assert!(chain.cycles() < MAX_CYCLES);
+        assert_eq!(
+            chain.debug_messages()[1],
+            format!("header 1: {:?}", headers[1].hash)
+        );
+
+        // a header the light client does not follow
+        let forged = header(1, [1u8; 32]);
+        let chain = chain_with_proof(&state, encode_proof(&forged, &[]));
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::UnknownHeader as i8
+        );
+        let chain = chain_with_proof(&state, encode_proof(&headers[2], &[headers[1].hash]));
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::UnknownHeader as i8
+        );
+    }
+
+    #[test]
+    fn test_prove_old_header() {
+        // headers 0 and 1 left the ring, and are a tree of the history
+        let (state, headers) = light_client(HEADER_SLOTS + 2);
+        assert_eq!(state.headers.history().leaves(), 2);
+        let chain = chain_with_proof(&state, encode_proof(&headers[0], &[headers[1].hash]));
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
+        let chain = chain_with_proof(&state, encode_proof(&headers[1], &[headers[0].hash]));
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
+
+        let chain = chain_with_proof(&state, encode_proof(&headers[0], &[]));
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::UnknownHeader as i8
+        );
+        let chain = chain_with_proof(&state, encode_proof(&headers[0], &[headers[0].hash]));
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::UnknownHeader as i8
+        );
+    }
+
+    #[test]
+    fn test_replay_failed_run() {
+        let (state, _) = light_client(3);
+        let chain = RecordingChain::new(chain_with_proof(&state, vec![0x80]));
+        assert_eq!(EthBridgeTypescript::run(&chain), Error::Encoding as i8);
+        let messages = chain.inner.debug_messages();
+        let replay = ReplayCKBChain::from_trace(messages.iter().map(String::as_str));
+        assert_eq!(EthBridgeTypescript::run(&replay), Error::Encoding as i8);
+        let debug = messages
+            .into_iter()
+            .filter(|message| !message.starts_with(TRACE_PREFIX))
+            .collect::<Vec<_>>();
+        assert_eq!(replay.debug_messages(), debug);
+    }
+
+    #[test]
+    fn test_missing_light_client() {
+        let (state, headers) = light_client(3);
+        let mut chain = chain_with_proof(&state, encode_proof(&headers[1], &[]));
+        chain.cell_deps.pop();
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::MissingLightClient as i8
+        );
+        let mut chain = chain_with_proof(&state, encode_proof(&headers[1], &[]));
+        chain.cell_deps[1].data.pop();
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::InvalidLightClient as i8
+        );
+        // the data is the one of the mode of the config
+        let config = config(Consensus::Clique(CliqueConfig::GOERLI));
+        let proof = encode_proof(&headers[1], &[]);
+        let chain = chain_with_light_client(&config, state.encode(), proof);
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::InvalidLightClient as i8
+        );
+    }
+
+    #[test]
+    fn test_confirmations() {
+        let (state, headers) = light_client(HEADER_SLOTS + 2);
+        let config = Config {
+            confirmations: 12,
+            ..config(Consensus::Ethash(ForkSchedule::MAINNET))
+        };
+        let tip = HEADER_SLOTS + 1;
+        let proof = encode_proof(&headers[tip - 12], &[]);
+        let chain = chain_with_light_client(&config, state.encode(), proof);
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
+        let proof = encode_proof(&headers[tip - 11], &[]);
+        let chain = chain_with_light_client(&config, state.encode(), proof);
+        assert_eq!(
+            EthBridgeTypescript::run(&chain),
+            Error::Unconfirmed as i8
+        );
+        // headers of the history are deep enough
+        let proof = encode_proof(&headers[0], &[headers[1].hash]);
+        let chain = chain_with_light_client(&config, state.encode(), proof);
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
+    }
+
+    #[test]
+    fn test_prove_clique_header() {
+        let (state, headers) = light_client(3);
+        let clique = CliqueClientState {
+            headers: state.headers,
+            snapshot: Snapshot::new(vec![[1u8; 20]]).unwrap(),
+            seals: vec![Seal::default(); 3],
+        };
+        let config = Config {
+            chain_id: 5,
+            ..config(Consensus::Clique(CliqueConfig::GOERLI))
+        };
+        let proof = encode_proof(&headers[1], &[]);
+        let chain = chain_with_light_client(&config, clique.encode(), proof);
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
+    }
+
+    #[test]
+    fn test_prove_parlia_header() {
+        let (state, headers) = light_client(3);
+        let validators = ValidatorSet::new(vec![[1u8; 20]], 1).unwrap();
+        let parlia = ParliaClientState {
+            headers: state.headers,
+            snapshot: parlia::Snapshot::new(validators),
+            signers: vec![[1u8; 20]; 3],
+            listed: None,
+        };
+        let config = Config {
+            chain_id: 56,
+            ..config(Consensus::Parlia(ParliaConfig {
+                chain_id: 56,
+                epoch: 200,
+                luban: 0,
+                bohr: 0,
+                london: u64::MAX,
+            }))
+        };
+        let proof = encode_proof(&headers[1], &[]);
+        let chain = chain_with_light_client(&config, parlia.encode(), proof);
+        assert_eq!(EthBridgeTypescript::run(&chain), 0);
     }
 }
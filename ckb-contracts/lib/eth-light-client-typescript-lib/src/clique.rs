@@ -0,0 +1,657 @@
+//! Clique proof of authority, as the clique engine of go-ethereum, which dev and test networks
+//! such as Goerli run.
+//!
+//! A header is sealed by one of the signers of the snapshot of its parent: the last
+//! `EXTRA_SEAL` bytes of its extra data are the recoverable secp256k1 signature of its sealing
+//! hash. The signer in turn seals with difficulty 2, the others with difficulty 1, and a signer
+//! seals at most one of `signers / 2 + 1` consecutive headers. Every header carries a vote of
+//! its signer in its coinbase and nonce, to authorize an address or drop a signer, and a
+//! majority of the signers changes the set. Checkpoints, every `epoch` blocks, reset the votes
+//! and list the signers in their extra data.
+//!
+//! The signature recovery is charged as `ckb_env::cycles::Operation::Secp256k1Recover` when
+//! the branch is loaded.
+
+use crate::config::CliqueConfig;
+use crate::consensus;
+use crate::difficulty::EMPTY_UNCLES_HASH;
+use crate::error::Error;
+use crate::hash::keccak256;
+use crate::header::Header;
+use crate::state::CliqueClientState;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Bytes of vanity at the start of the extra data
+pub const EXTRA_VANITY: usize = 32;
+/// Bytes of the seal at the end of the extra data
+pub const EXTRA_SEAL: usize = 65;
+/// Signers a snapshot holds at most
+pub const MAX_SIGNERS: usize = 16;
+/// Recent signers a snapshot holds at most, one per header of the window of the largest set
+pub const MAX_RECENTS: usize = MAX_SIGNERS / 2 + 1;
+/// Pending votes a snapshot holds at most
+pub const MAX_VOTES: usize = 32;
+
+const DIFF_IN_TURN: u128 = 2;
+const DIFF_NO_TURN: u128 = 1;
+/// Nonce of a vote to authorize the coinbase
+const NONCE_AUTH: [u8; 8] = [0xff; 8];
+/// Nonce of a vote to drop the coinbase, and of the headers without a vote
+const NONCE_DROP: [u8; 8] = [0u8; 8];
+
+/// The signer of a header and the vote the header carries
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Seal {
+    pub signer: [u8; 20],
+    pub candidate: [u8; 20],
+    pub authorize: bool,
+}
+
+impl Seal {
+    pub const SIZE: usize = 20 + 20 + 1;
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut seal = Seal::default();
+        seal.signer.copy_from_slice(&data[..20]);
+        seal.candidate.copy_from_slice(&data[20..40]);
+        seal.authorize = match data[40] {
+            0 => false,
+            1 => true,
+            _ => return Err(Error::Encoding),
+        };
+        Ok(seal)
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.signer);
+        out.extend_from_slice(&self.candidate);
+        out.push(self.authorize as u8);
+    }
+}
+
+/// The signers of the chain at a header, with the signers of the latest headers and the votes
+/// cast since the last checkpoint
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Sorted by address
+    pub signers: Vec<[u8; 20]>,
+    /// Block numbers of the latest headers with their signers
+    pub recents: Vec<(u64, [u8; 20])>,
+    /// The pending votes, as the seals which cast them
+    pub votes: Vec<Seal>,
+}
+
+impl Snapshot {
+    /// Bytes of the encoded snapshot: the signers, the recents and the votes, each as the
+    /// number of entries in a byte followed by the slots of the entries. A recent is the block
+    /// number as a little endian u64 followed by the signer.
+    pub const SIZE: usize =
+        1 + 20 * MAX_SIGNERS + 1 + (8 + 20) * MAX_RECENTS + 1 + Seal::SIZE * MAX_VOTES;
+
+    /// A snapshot of the `signers`, sorted by address, without recents nor votes
+    pub fn new(signers: Vec<[u8; 20]>) -> Result<Self, Error> {
+        if signers.is_empty()
+            || signers.len() > MAX_SIGNERS
+            || signers.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(Error::InvalidSigners);
+        }
+        Ok(Snapshot {
+            signers,
+            ..Default::default()
+        })
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let (signers, data) = entries(data, 20, MAX_SIGNERS)?;
+        let (recents, data) = entries(data, 8 + 20, MAX_RECENTS)?;
+        let (votes, _) = entries(data, Seal::SIZE, MAX_VOTES)?;
+        let mut snapshot = Snapshot::default();
+        for data in signers.chunks(20) {
+            let mut signer = [0u8; 20];
+            signer.copy_from_slice(data);
+            snapshot.signers.push(signer);
+        }
+        for data in recents.chunks(8 + 20) {
+            let mut number = [0u8; 8];
+            number.copy_from_slice(&data[..8]);
+            let mut signer = [0u8; 20];
+            signer.copy_from_slice(&data[8..]);
+            snapshot.recents.push((u64::from_le_bytes(number), signer));
+        }
+        for data in votes.chunks(Seal::SIZE) {
+            snapshot.votes.push(Seal::decode(data)?);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.signers.len() as u8);
+        for signer in &self.signers {
+            out.extend_from_slice(signer);
+        }
+        out.resize(out.len() + 20 * (MAX_SIGNERS - self.signers.len()), 0);
+        out.push(self.recents.len() as u8);
+        for (number, signer) in &self.recents {
+            out.extend_from_slice(&number.to_le_bytes());
+            out.extend_from_slice(signer);
+        }
+        out.resize(out.len() + (8 + 20) * (MAX_RECENTS - self.recents.len()), 0);
+        out.push(self.votes.len() as u8);
+        for vote in &self.votes {
+            vote.encode(out);
+        }
+        out.resize(out.len() + Seal::SIZE * (MAX_VOTES - self.votes.len()), 0);
+    }
+
+    /// Whether `signer` is the one in turn to seal the header of `number`
+    pub fn in_turn(&self, number: u64, signer: &[u8; 20]) -> bool {
+        let index = (number % self.signers.len() as u64) as usize;
+        &self.signers[index] == signer
+    }
+
+    /// Check that `signer` may seal the header of `number`, as a signer which did not seal
+    /// one of the latest `signers / 2` headers. As in geth, a recent signer may not seal again
+    /// before the window has passed the genesis.
+    pub fn verify_signer(&self, number: u64, signer: &[u8; 20]) -> Result<(), Error> {
+        if self.signers.binary_search(signer).is_err() {
+            return Err(Error::UnauthorizedSigner);
+        }
+        let limit = self.limit();
+        let recent = self
+            .recents
+            .iter()
+            .any(|(seen, recent)| recent == signer && (number < limit || *seen > number - limit));
+        if recent {
+            return Err(Error::UnauthorizedSigner);
+        }
+        Ok(())
+    }
+
+    /// Apply the verified `seal` of the header of `number`, on a chain with checkpoints every
+    /// `epoch` blocks
+    pub fn apply(&mut self, epoch: u64, number: u64, seal: &Seal) -> Result<(), Error> {
+        if number % epoch == 0 {
+            self.votes.clear();
+        }
+        // the oldest signer of the window may seal again
+        self.forget_recent(number);
+        self.recents.push((number, seal.signer));
+
+        // a signer votes once for a candidate, its latest vote counts
+        self.votes
+            .retain(|vote| vote.signer != seal.signer || vote.candidate != seal.candidate);
+        let is_signer = self.signers.binary_search(&seal.candidate);
+        if is_signer.is_ok() != seal.authorize {
+            self.votes.push(*seal);
+        }
+        let tally = self
+            .votes
+            .iter()
+            .filter(|vote| vote.candidate == seal.candidate)
+            .count();
+        if tally > self.signers.len() / 2 {
+            match is_signer {
+                Err(index) => self.signers.insert(index, seal.candidate),
+                Ok(index) => {
+                    self.signers.remove(index);
+                    // the window shrinks with the set
+                    self.forget_recent(number);
+                    let candidate = seal.candidate;
+                    self.votes.retain(|vote| vote.signer != candidate);
+                }
+            }
+            let candidate = seal.candidate;
+            self.votes.retain(|vote| vote.candidate != candidate);
+        }
+        if self.signers.is_empty()
+            || self.signers.len() > MAX_SIGNERS
+            || self.recents.len() > MAX_RECENTS
+            || self.votes.len() > MAX_VOTES
+        {
+            return Err(Error::InvalidSigners);
+        }
+        Ok(())
+    }
+
+    /// Headers of the window a signer seals at most one of
+    fn limit(&self) -> u64 {
+        self.signers.len() as u64 / 2 + 1
+    }
+
+    /// Drop the recent signer which leaves the window at the header of `number`
+    fn forget_recent(&mut self, number: u64) {
+        let limit = self.limit();
+        if number >= limit {
+            self.recents.retain(|(seen, _)| *seen != number - limit);
+        }
+    }
+}
+
+/// Split the entries of an encoded list of `slots` entries of `size` bytes, and the data after
+/// the list. The unused slots are zeros.
+fn entries(data: &[u8], size: usize, slots: usize) -> Result<(&[u8], &[u8]), Error> {
+    let len = data[0] as usize;
+    if len > slots {
+        return Err(Error::Encoding);
+    }
+    let (list, rest) = data[1..].split_at(size * slots);
+    let (entries, unused) = list.split_at(size * len);
+    if unused.iter().any(|&byte| byte != 0) {
+        return Err(Error::Encoding);
+    }
+    Ok((entries, rest))
+}
+
+/// keccak256 of the RLP encoded header without the seal of its extra data, the message its
+/// signer signs
+pub fn seal_hash(header: &Header) -> [u8; 32] {
+    let mut unsealed = header.clone();
+    let len = unsealed.extra_data.len().saturating_sub(EXTRA_SEAL);
+    unsealed.extra_data.truncate(len);
+    keccak256(&unsealed.encode())
+}
+
+/// Recover the address of the signer of `header` from its seal
+pub fn recover_signer(header: &Header) -> Result<[u8; 20], Error> {
+    let len = header.extra_data.len();
+    if len < EXTRA_VANITY + EXTRA_SEAL {
+        return Err(Error::InvalidExtraData);
+    }
+    ecrecover(&seal_hash(header), &header.extra_data[len - EXTRA_SEAL..])
+}
+
+/// Recover the address which signed `hash` with the `EXTRA_SEAL` bytes `seal`, the signature
+/// followed by its recovery id
+pub fn ecrecover(hash: &[u8; 32], seal: &[u8]) -> Result<[u8; 20], Error> {
+    let mut signature = [0u8; 64];
+    signature.copy_from_slice(&seal[..64]);
+    let signature =
+        libsecp256k1::Signature::parse_standard(&signature).map_err(|_| Error::InvalidSeal)?;
+    let recovery_id = libsecp256k1::RecoveryId::parse(seal[64]).map_err(|_| Error::InvalidSeal)?;
+    let message = libsecp256k1::Message::parse(hash);
+    let pubkey = libsecp256k1::recover(&message, &signature, &recovery_id)
+        .map_err(|_| Error::InvalidSeal)?;
+    let mut signer = [0u8; 20];
+    signer.copy_from_slice(&keccak256(&pubkey.serialize()[1..])[12..]);
+    Ok(signer)
+}
+
+/// The signers a checkpoint header lists in its extra data, between the vanity and the seal
+pub fn checkpoint_signers(header: &Header) -> Result<Vec<[u8; 20]>, Error> {
+    let len = header.extra_data.len();
+    if len < EXTRA_VANITY + EXTRA_SEAL || (len - EXTRA_VANITY - EXTRA_SEAL) % 20 != 0 {
+        return Err(Error::InvalidExtraData);
+    }
+    Ok(header.extra_data[EXTRA_VANITY..len - EXTRA_SEAL]
+        .chunks(20)
+        .map(|data| {
+            let mut signer = [0u8; 20];
+            signer.copy_from_slice(data);
+            signer
+        })
+        .collect())
+}
+
+/// Verify `header` against its `parent`, whose snapshot is `snapshot`, and return its seal
+pub fn verify_header(
+    config: &CliqueConfig,
+    snapshot: &Snapshot,
+    parent: &Header,
+    header: &Header,
+) -> Result<Seal, Error> {
+    let checkpoint = header.number % config.epoch == 0;
+    if checkpoint && (header.coinbase != [0u8; 20] || header.nonce != NONCE_DROP) {
+        return Err(Error::InvalidSeal);
+    }
+    if header.nonce != NONCE_AUTH && header.nonce != NONCE_DROP {
+        return Err(Error::InvalidSeal);
+    }
+    if header.mix_hash != [0u8; 32] || header.uncles_hash != EMPTY_UNCLES_HASH {
+        return Err(Error::InvalidSeal);
+    }
+    let signers = checkpoint_signers(header)?;
+    if !checkpoint && !signers.is_empty() {
+        return Err(Error::InvalidExtraData);
+    }
+    if checkpoint && signers != snapshot.signers {
+        return Err(Error::InvalidSigners);
+    }
+    let earliest = parent
+        .timestamp
+        .checked_add(config.period)
+        .ok_or(Error::InvalidTimestamp)?;
+    if header.timestamp < earliest {
+        return Err(Error::InvalidTimestamp);
+    }
+    consensus::verify_gas(config.london, parent, header)?;
+
+    let signer = recover_signer(header)?;
+    snapshot.verify_signer(header.number, &signer)?;
+    let difficulty = if snapshot.in_turn(header.number, &signer) {
+        DIFF_IN_TURN
+    } else {
+        DIFF_NO_TURN
+    };
+    if header.difficulty != difficulty {
+        return Err(Error::InvalidDifficulty);
+    }
+    Ok(Seal {
+        signer,
+        candidate: header.coinbase,
+        authorize: header.nonce == NONCE_AUTH,
+    })
+}
+
+/// Apply a branch of sealed headers whose first header is the child of the kept `parent`.
+///
+/// The headers follow `state::HeaderChain::apply_branch`, the heavier chain of in-turn seals
+/// wins. The snapshot of the parent is the one of the oldest kept header with the seals of the
+/// kept headers up to the parent replayed, and the snapshot of the result moves to its oldest
+/// kept header as headers leave the ring.
+pub fn apply_branch(
+    config: &CliqueConfig,
+    state: &CliqueClientState,
+    parent: &Header,
+    branch: &[Header],
+) -> Result<CliqueClientState, Error> {
+    let mut headers = state.headers.clone();
+    headers.apply_branch(branch)?;
+    let oldest = state.headers.get(0).ok_or(Error::UnknownParent)?.number;
+    let fork = parent.number.saturating_sub(oldest) as usize;
+    if state.headers.get(fork).map(|record| record.hash) != Some(parent.hash) {
+        return Err(Error::InvalidHeaderChain);
+    }
+
+    let mut snapshot = state.snapshot.clone();
+    for (index, seal) in state.seals.iter().enumerate().take(fork + 1).skip(1) {
+        snapshot.apply(config.epoch, oldest + index as u64, seal)?;
+    }
+    let mut seals = state.seals[..=fork].to_vec();
+    let mut parent = parent;
+    for header in branch {
+        if header.parent_hash != parent.hash {
+            return Err(Error::InvalidHeaderChain);
+        }
+        let seal = verify_header(config, &snapshot, parent, header)?;
+        snapshot.apply(config.epoch, header.number, &seal)?;
+        seals.push(seal);
+        parent = header;
+    }
+
+    // the seals of the headers which left the ring move to the snapshot
+    let dropped = seals.len() - headers.len();
+    let mut snapshot = state.snapshot.clone();
+    for (index, seal) in seals.iter().enumerate().take(dropped + 1).skip(1) {
+        snapshot.apply(config.epoch, oldest + index as u64, seal)?;
+    }
+    Ok(CliqueClientState {
+        headers,
+        snapshot,
+        seals: seals.split_off(dropped),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{HeaderChain, HeaderRecord, HEADER_SLOTS};
+    use crate::test_data::*;
+    use crate::MAX_SEALED_HEADERS;
+
+    fn vote(signer: &Signer, candidate: &Signer, authorize: bool) -> Seal {
+        Seal {
+            signer: signer.address,
+            candidate: candidate.address,
+            authorize,
+        }
+    }
+
+    /// The seal of a header of `clique_headers`
+    fn in_turn_seal(signers: &[Signer], number: u64) -> Seal {
+        Seal {
+            signer: signers[(number % signers.len() as u64) as usize].address,
+            ..Default::default()
+        }
+    }
+
+    /// State of a light client started from the checkpoint `header`
+    fn checkpoint_state(header: &Header, signers: &[Signer]) -> CliqueClientState {
+        CliqueClientState {
+            headers: HeaderChain::new(HeaderRecord::new(header), header.difficulty),
+            snapshot: Snapshot::new(addresses(signers)).unwrap(),
+            seals: vec![Seal::default()],
+        }
+    }
+
+    #[test]
+    fn test_recover_signer() {
+        let signers = new_signers(3);
+        let headers = clique_headers(&signers, 2);
+        assert_eq!(recover_signer(&headers[1]), Ok(signers[1].address));
+        // the seal signs the other fields
+        let mut header = headers[1].clone();
+        header.timestamp += 1;
+        assert_ne!(recover_signer(&header), Ok(signers[1].address));
+        let len = header.extra_data.len();
+        header.extra_data[len - 1] = 4;
+        assert_eq!(recover_signer(&header), Err(Error::InvalidSeal));
+        header.extra_data.truncate(EXTRA_VANITY + EXTRA_SEAL - 1);
+        assert_eq!(recover_signer(&header), Err(Error::InvalidExtraData));
+    }
+
+    #[test]
+    fn test_verify_header() {
+        let all = new_signers(4);
+        let signers = &all[..3];
+        let headers = clique_headers(signers, 17);
+        let snapshot = Snapshot::new(addresses(signers)).unwrap();
+        let verify =
+            |parent: &Header, header: &Header| verify_header(&CLIQUE, &snapshot, parent, header);
+        assert_eq!(
+            verify(&headers[0], &headers[1]),
+            Ok(in_turn_seal(signers, 1))
+        );
+        verify(&headers[15], &headers[16]).unwrap();
+        // out of turn with the difficulty of the signer in turn, or by an outsider
+        let header = clique_child(&headers[0], &signers[2], true, signers);
+        assert_eq!(verify(&headers[0], &header), Err(Error::InvalidDifficulty));
+        let header = clique_child(&headers[0], &signers[2], false, signers);
+        verify(&headers[0], &header).unwrap();
+        let header = clique_child(&headers[0], &all[3], false, signers);
+        assert_eq!(verify(&headers[0], &header), Err(Error::UnauthorizedSigner));
+        // the signer of header 1 waits for another header
+        let mut recent = snapshot.clone();
+        recent.recents.push((1, signers[1].address));
+        let header = clique_child(&headers[1], &signers[1], false, signers);
+        assert_eq!(
+            verify_header(&CLIQUE, &recent, &headers[1], &header),
+            Err(Error::UnauthorizedSigner)
+        );
+
+        let forge = |parent: &Header, forge: &dyn Fn(&mut Header)| {
+            let signer = &signers[((parent.number + 1) % 3) as usize];
+            let mut header = clique_child(parent, signer, true, signers);
+            forge(&mut header);
+            seal_header(&mut header, signer);
+            verify(parent, &header)
+        };
+        let parent = &headers[0];
+        assert_eq!(
+            forge(parent, &|header| header.mix_hash = [1u8; 32]),
+            Err(Error::InvalidSeal)
+        );
+        assert_eq!(
+            forge(parent, &|header| header.nonce = [1u8; 8]),
+            Err(Error::InvalidSeal)
+        );
+        assert_eq!(
+            forge(parent, &|header| header.uncles_hash = [0u8; 32]),
+            Err(Error::InvalidSeal)
+        );
+        assert_eq!(
+            forge(parent, &|header| header.timestamp -= 1),
+            Err(Error::InvalidTimestamp)
+        );
+        assert_eq!(
+            forge(parent, &|header| header.gas_limit *= 2),
+            Err(Error::InvalidGasLimit)
+        );
+        // the seal is rewritten over the end of the extra data
+        let list_signer = |header: &mut Header| header.extra_data.extend_from_slice(&[0u8; 20]);
+        let drop_signer =
+            |header: &mut Header| header.extra_data.truncate(EXTRA_VANITY + 40 + EXTRA_SEAL);
+        assert_eq!(forge(parent, &list_signer), Err(Error::InvalidExtraData));
+        // checkpoints list the signers and carry no vote
+        assert_eq!(
+            forge(&headers[15], &|header| header.coinbase = [1u8; 20]),
+            Err(Error::InvalidSeal)
+        );
+        assert_eq!(
+            forge(&headers[15], &drop_signer),
+            Err(Error::InvalidSigners)
+        );
+    }
+
+    #[test]
+    fn test_votes() {
+        let all = new_signers(4);
+        let (a, b, c, d) = (&all[0], &all[1], &all[2], &all[3]);
+        let mut snapshot = Snapshot::new(addresses(&all[..3])).unwrap();
+        let epoch = CLIQUE.epoch;
+        // a signer votes once per candidate, a majority authorizes it
+        snapshot.apply(epoch, 1, &vote(a, d, true)).unwrap();
+        snapshot.apply(epoch, 2, &vote(a, d, true)).unwrap();
+        assert_eq!(snapshot.votes, vec![vote(a, d, true)]);
+        snapshot.apply(epoch, 3, &vote(b, d, true)).unwrap();
+        assert_eq!(snapshot.signers, addresses(&all));
+        assert!(snapshot.votes.is_empty());
+
+        // votes to authorize a signer are void, dropping one takes 3 of 4 votes
+        snapshot.apply(epoch, 4, &vote(a, c, false)).unwrap();
+        snapshot.apply(epoch, 5, &vote(b, a, true)).unwrap();
+        snapshot.apply(epoch, 6, &vote(c, a, false)).unwrap();
+        snapshot.apply(epoch, 7, &vote(b, c, false)).unwrap();
+        assert_eq!(snapshot.votes.len(), 3);
+        snapshot.apply(epoch, 8, &vote(d, c, false)).unwrap();
+        // the votes of the dropped signer go with it, and the window shrinks
+        assert_eq!(snapshot.signers, vec![a.address, b.address, d.address]);
+        assert!(snapshot.votes.is_empty());
+        assert_eq!(snapshot.recents, vec![(7, b.address), (8, d.address)]);
+
+        // checkpoints reset the votes
+        snapshot.apply(epoch, 9, &vote(a, c, true)).unwrap();
+        assert_eq!(snapshot.votes.len(), 1);
+        snapshot.apply(epoch, 16, &Seal::default()).unwrap();
+        assert!(snapshot.votes.is_empty());
+
+        assert_eq!(Snapshot::new(Vec::new()), Err(Error::InvalidSigners));
+        assert_eq!(
+            Snapshot::new(vec![d.address, a.address]),
+            Err(Error::InvalidSigners)
+        );
+    }
+
+    #[test]
+    fn test_verify_signer() {
+        let all = new_signers(4);
+        let (a, b) = (&all[0], &all[1]);
+        let seal = |signer: &Signer| Seal {
+            signer: signer.address,
+            ..Default::default()
+        };
+        let mut snapshot = Snapshot::new(addresses(&all)).unwrap();
+        snapshot.apply(CLIQUE.epoch, 1, &seal(a)).unwrap();
+        // a signer seals one of 3 consecutive headers, even near the genesis
+        assert_eq!(
+            snapshot.verify_signer(2, &a.address),
+            Err(Error::UnauthorizedSigner)
+        );
+        assert_eq!(snapshot.verify_signer(2, &b.address), Ok(()));
+        snapshot.apply(CLIQUE.epoch, 2, &seal(b)).unwrap();
+        assert_eq!(
+            snapshot.verify_signer(3, &a.address),
+            Err(Error::UnauthorizedSigner)
+        );
+        assert_eq!(snapshot.verify_signer(4, &a.address), Ok(()));
+        assert_eq!(
+            snapshot.verify_signer(4, &b.address),
+            Err(Error::UnauthorizedSigner)
+        );
+    }
+
+    #[test]
+    fn test_encode_snapshot() {
+        let all = new_signers(4);
+        let mut snapshot = Snapshot::new(addresses(&all[..3])).unwrap();
+        snapshot
+            .apply(CLIQUE.epoch, 1, &vote(&all[0], &all[3], true))
+            .unwrap();
+        let mut data = Vec::new();
+        snapshot.encode(&mut data);
+        assert_eq!(data.len(), Snapshot::SIZE);
+        assert_eq!(Snapshot::decode(&data), Ok(snapshot));
+        // unused slots are zeros
+        data[1 + 20 * 3] = 1;
+        assert_eq!(Snapshot::decode(&data), Err(Error::Encoding));
+        data[1 + 20 * 3] = 0;
+        data[0] = MAX_SIGNERS as u8 + 1;
+        assert_eq!(Snapshot::decode(&data), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_apply_branch() {
+        let signers = new_signers(3);
+        let headers = clique_headers(&signers, 40);
+        let mut state = checkpoint_state(&headers[0], &signers);
+        for start in (1..39).step_by(MAX_SEALED_HEADERS) {
+            let end = core::cmp::min(start + MAX_SEALED_HEADERS, 39);
+            state =
+                apply_branch(&CLIQUE, &state, &headers[start - 1], &headers[start..end]).unwrap();
+        }
+        let tip = apply_branch(&CLIQUE, &state, &headers[38], &headers[39..]).unwrap();
+        assert_eq!(tip.headers.tip().unwrap().hash, headers[39].hash);
+        assert_eq!(tip.seals.len(), HEADER_SLOTS);
+        assert_eq!(tip.seals[HEADER_SLOTS - 1], in_turn_seal(&signers, 39));
+        // the snapshot moves to the oldest kept header
+        let mut snapshot = Snapshot::new(addresses(&signers)).unwrap();
+        for number in 1..=8 {
+            snapshot
+                .apply(CLIQUE.epoch, number, &in_turn_seal(&signers, number))
+                .unwrap();
+        }
+        assert_eq!(tip.snapshot, snapshot);
+
+        // the in-turn header replaces an out-of-turn one
+        let out_of_turn = [clique_child(&headers[38], &signers[1], false, &signers)];
+        let fork = apply_branch(&CLIQUE, &state, &headers[38], &out_of_turn).unwrap();
+        assert_eq!(
+            apply_branch(&CLIQUE, &fork, &headers[38], &headers[39..]),
+            Ok(tip.clone())
+        );
+        assert_eq!(
+            apply_branch(&CLIQUE, &tip, &headers[38], &out_of_turn),
+            Err(Error::StaleBranch)
+        );
+        // the signer of the tip waits for another header
+        let header = clique_child(&headers[39], &signers[0], false, &signers);
+        assert_eq!(
+            apply_branch(&CLIQUE, &tip, &headers[39], &[header]),
+            Err(Error::UnauthorizedSigner)
+        );
+        // the parent is the one of the branch
+        assert_eq!(
+            apply_branch(&CLIQUE, &state, &headers[37], &headers[39..]),
+            Err(Error::InvalidHeaderChain)
+        );
+    }
+}
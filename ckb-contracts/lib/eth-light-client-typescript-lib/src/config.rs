@@ -0,0 +1,413 @@
+//! Configuration of a light client, from its script args.
+
+use crate::error::Error;
+use crate::generated::blockchain::{Byte32, Bytes, Uint32, Uint64};
+use crate::generated::light_client::{LightClientConfig, LightClientConfigReader};
+use crate::state::HEADER_SLOTS;
+use molecule::prelude::*;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Blocks the hard forks which change header validation activate at, `u64::MAX` for a fork
+/// the chain never activates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForkSchedule {
+    pub homestead: u64,
+    pub byzantium: u64,
+    pub constantinople: u64,
+    pub muir_glacier: u64,
+    pub london: u64,
+    pub arrow_glacier: u64,
+    pub gray_glacier: u64,
+}
+
+impl ForkSchedule {
+    pub const MAINNET: ForkSchedule = ForkSchedule {
+        homestead: 1_150_000,
+        byzantium: 4_370_000,
+        constantinople: 7_280_000,
+        muir_glacier: 9_200_000,
+        london: 12_965_000,
+        arrow_glacier: 13_773_000,
+        gray_glacier: 15_050_000,
+    };
+
+    const FORKS: usize = 7;
+
+    /// Blocks the difficulty bomb is delayed by at block `number`
+    pub fn bomb_delay(&self, number: u64) -> u64 {
+        if number >= self.gray_glacier {
+            11_400_000
+        } else if number >= self.arrow_glacier {
+            10_700_000
+        } else if number >= self.london {
+            9_700_000
+        } else if number >= self.muir_glacier {
+            9_000_000
+        } else if number >= self.constantinople {
+            5_000_000
+        } else if number >= self.byzantium {
+            3_000_000
+        } else {
+            0
+        }
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != 8 * Self::FORKS {
+            return Err(Error::Encoding);
+        }
+        let mut blocks = [0u64; Self::FORKS];
+        for (block, data) in blocks.iter_mut().zip(data.chunks(8)) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            *block = u64::from_le_bytes(bytes);
+        }
+        Ok(ForkSchedule {
+            homestead: blocks[0],
+            byzantium: blocks[1],
+            constantinople: blocks[2],
+            muir_glacier: blocks[3],
+            london: blocks[4],
+            arrow_glacier: blocks[5],
+            gray_glacier: blocks[6],
+        })
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        for block in self.blocks().iter() {
+            out.extend_from_slice(&block.to_le_bytes());
+        }
+    }
+
+    fn blocks(&self) -> [u64; Self::FORKS] {
+        [
+            self.homestead,
+            self.byzantium,
+            self.constantinople,
+            self.muir_glacier,
+            self.london,
+            self.arrow_glacier,
+            self.gray_glacier,
+        ]
+    }
+}
+
+/// The proof-of-authority chain a light client in clique mode follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CliqueConfig {
+    /// Seconds between two blocks at least
+    pub period: u64,
+    /// Blocks between two checkpoints, which reset the votes and list the signers
+    pub epoch: u64,
+    /// Block London activates at
+    pub london: u64,
+}
+
+impl CliqueConfig {
+    pub const GOERLI: CliqueConfig = CliqueConfig {
+        period: 15,
+        epoch: 30_000,
+        london: 5_062_605,
+    };
+
+    /// Bytes of the encoded config, the fields as little endian u64
+    pub const SIZE: usize = 8 * 3;
+
+    fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let mut fields = [0u64; 3];
+        for (field, data) in fields.iter_mut().zip(data.chunks(8)) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            *field = u64::from_le_bytes(bytes);
+        }
+        if fields[1] == 0 {
+            return Err(Error::Encoding);
+        }
+        Ok(CliqueConfig {
+            period: fields[0],
+            epoch: fields[1],
+            london: fields[2],
+        })
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.period.to_le_bytes());
+        out.extend_from_slice(&self.epoch.to_le_bytes());
+        out.extend_from_slice(&self.london.to_le_bytes());
+    }
+}
+
+/// The proof-of-staked-authority chain a light client in parlia mode follows, such as BNB
+/// Smart Chain. A fork the chain never activates has the block `u64::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParliaConfig {
+    /// Chain id the validators sign headers for, the one of the `Config`
+    pub chain_id: u64,
+    /// Blocks between two epoch headers, which list the next validators. It is more than the
+    /// headers the cell keeps, so the cell keeps at most one epoch header after its oldest one.
+    pub epoch: u64,
+    /// Block Luban activates at, from which epoch headers list the BLS keys of the validators
+    pub luban: u64,
+    /// Block Bohr activates at, from which validators seal several consecutive headers
+    pub bohr: u64,
+    /// Block London activates at
+    pub london: u64,
+}
+
+impl ParliaConfig {
+    /// Bytes of the encoded config, the fields after the chain id as little endian u64
+    pub const SIZE: usize = 8 * 4;
+
+    fn decode(data: &[u8], chain_id: u64) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let mut fields = [0u64; 4];
+        for (field, data) in fields.iter_mut().zip(data.chunks(8)) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            *field = u64::from_le_bytes(bytes);
+        }
+        if fields[0] <= HEADER_SLOTS as u64 {
+            return Err(Error::Encoding);
+        }
+        Ok(ParliaConfig {
+            chain_id,
+            epoch: fields[0],
+            luban: fields[1],
+            bohr: fields[2],
+            london: fields[3],
+        })
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.epoch.to_le_bytes());
+        out.extend_from_slice(&self.luban.to_le_bytes());
+        out.extend_from_slice(&self.bohr.to_le_bytes());
+        out.extend_from_slice(&self.london.to_le_bytes());
+    }
+}
+
+/// How the light client verifies the headers it follows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Consensus {
+    /// Proof of work, the cell data is a `state::LightClientState`
+    Ethash(ForkSchedule),
+    /// Proof of authority, headers sealed by the signers voted in, the cell data is a
+    /// `state::CliqueClientState`
+    Clique(CliqueConfig),
+    /// Proof of staked authority, headers sealed by the validators listed in the latest epoch
+    /// header, the cell data is a `state::ParliaClientState`
+    Parlia(ParliaConfig),
+}
+
+impl Consensus {
+    const ETHASH: u8 = 0;
+    const CLIQUE: u8 = 1;
+    const PARLIA: u8 = 2;
+}
+
+/// The script args of the light client, the molecule table `LightClientConfig` of
+/// `schemas/light_client.mol`.
+///
+/// The params are the ones of the consensus mode, with its fork schedule: the fork blocks as
+/// little endian u64 in the order of the `ForkSchedule` fields for ethash, a `CliqueConfig` for
+/// clique and a `ParliaConfig` for parlia. The same binary serves the cells of every network,
+/// told apart by their config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Lock hash of the owner, who creates the cell and appends dataset roots
+    pub owner_lock_hash: [u8; 32],
+    /// Type id of the light-client cell, the hash of the first input of the transaction
+    /// creating it and of the index of the cell in the outputs
+    pub type_id: [u8; 32],
+    /// EIP-155 chain id of the network the cell follows
+    pub chain_id: u64,
+    pub consensus: Consensus,
+    /// Headers on top of a header before the bridge accepts a proof of it
+    pub confirmations: u64,
+    /// Headers the cell keeps in its `state::HeaderChain`, at most `state::HEADER_SLOTS`
+    pub ring_size: usize,
+}
+
+impl Config {
+    pub fn decode(args: &[u8]) -> Result<Self, Error> {
+        let reader = LightClientConfigReader::from_slice(args).map_err(|_| Error::Encoding)?;
+        let mut owner_lock_hash = [0u8; 32];
+        owner_lock_hash.copy_from_slice(reader.owner_lock_hash().raw_data());
+        let mut type_id = [0u8; 32];
+        type_id.copy_from_slice(reader.type_id().raw_data());
+        let mut chain_id = [0u8; 8];
+        chain_id.copy_from_slice(reader.chain_id().raw_data());
+        let chain_id = u64::from_le_bytes(chain_id);
+        let params = reader.params().raw_data();
+        let consensus = match reader.consensus().as_slice()[0] {
+            Consensus::ETHASH => Consensus::Ethash(ForkSchedule::decode(params)?),
+            Consensus::CLIQUE => Consensus::Clique(CliqueConfig::decode(params)?),
+            Consensus::PARLIA => Consensus::Parlia(ParliaConfig::decode(params, chain_id)?),
+            _ => return Err(Error::Encoding),
+        };
+        let mut confirmations = [0u8; 8];
+        confirmations.copy_from_slice(reader.confirmations().raw_data());
+        let mut ring_size = [0u8; 4];
+        ring_size.copy_from_slice(reader.ring_size().raw_data());
+        let ring_size = u32::from_le_bytes(ring_size) as usize;
+        if ring_size == 0 || ring_size > HEADER_SLOTS {
+            return Err(Error::Encoding);
+        }
+        Ok(Config {
+            owner_lock_hash,
+            type_id,
+            chain_id,
+            consensus,
+            confirmations: u64::from_le_bytes(confirmations),
+            ring_size,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut params = Vec::new();
+        let mode = match &self.consensus {
+            Consensus::Ethash(forks) => {
+                forks.encode(&mut params);
+                Consensus::ETHASH
+            }
+            Consensus::Clique(clique) => {
+                clique.encode(&mut params);
+                Consensus::CLIQUE
+            }
+            Consensus::Parlia(parlia) => {
+                parlia.encode(&mut params);
+                Consensus::PARLIA
+            }
+        };
+        let params = Bytes::new_builder()
+            .set(params.into_iter().map(Byte::new).collect())
+            .build();
+        LightClientConfig::new_builder()
+            .owner_lock_hash(Byte32::new_unchecked(self.owner_lock_hash.to_vec().into()))
+            .type_id(Byte32::new_unchecked(self.type_id.to_vec().into()))
+            .chain_id(Uint64::new_unchecked(
+                self.chain_id.to_le_bytes().to_vec().into(),
+            ))
+            .consensus(Byte::new(mode))
+            .params(params)
+            .confirmations(Uint64::new_unchecked(
+                self.confirmations.to_le_bytes().to_vec().into(),
+            ))
+            .ring_size(Uint32::new_unchecked(
+                (self.ring_size as u32).to_le_bytes().to_vec().into(),
+            ))
+            .build()
+            .as_slice()
+            .to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_config() {
+        let config = Config {
+            owner_lock_hash: [1u8; 32],
+            type_id: [2u8; 32],
+            chain_id: 1,
+            consensus: Consensus::Ethash(ForkSchedule::MAINNET),
+            confirmations: 15,
+            ring_size: HEADER_SLOTS,
+        };
+        let args = config.encode();
+        assert_eq!(
+            args.len(),
+            4 * 8 + 32 + 32 + 8 + 1 + 4 + 8 * ForkSchedule::FORKS + 8 + 4
+        );
+        assert_eq!(Config::decode(&args).unwrap(), config);
+        assert_eq!(Config::decode(&args[..64]), Err(Error::Encoding));
+        assert_eq!(
+            Config::decode(&args[..args.len() - 1]),
+            Err(Error::Encoding)
+        );
+
+        // the ring size is at least one header and at most the slots of the cell
+        for &ring_size in &[1, 8] {
+            let config = Config {
+                ring_size,
+                ..config.clone()
+            };
+            assert_eq!(Config::decode(&config.encode()).unwrap(), config);
+        }
+        let mut args = config.encode();
+        let len = args.len();
+        args[len - 4] = 0;
+        assert_eq!(Config::decode(&args), Err(Error::Encoding));
+        args[len - 4] = HEADER_SLOTS as u8 + 1;
+        assert_eq!(Config::decode(&args), Err(Error::Encoding));
+
+        let config = Config {
+            chain_id: 5,
+            consensus: Consensus::Clique(CliqueConfig::GOERLI),
+            ..config
+        };
+        let mut args = config.encode();
+        assert_eq!(Config::decode(&args).unwrap(), config);
+        // the parameters are the ones of the mode
+        let mode = 4 * 8 + 32 + 32 + 8;
+        args[mode] = Consensus::ETHASH;
+        assert_eq!(Config::decode(&args), Err(Error::Encoding));
+        args[mode] = 3;
+        assert_eq!(Config::decode(&args), Err(Error::Encoding));
+        args[mode] = Consensus::CLIQUE;
+        // checkpoints need an epoch
+        let params = mode + 1 + 4;
+        args[params + 8..params + 16].copy_from_slice(&[0u8; 8]);
+        assert_eq!(Config::decode(&args), Err(Error::Encoding));
+
+        let parlia = ParliaConfig {
+            chain_id: 56,
+            epoch: 200,
+            luban: 0,
+            bohr: 0,
+            london: u64::MAX,
+        };
+        let config = Config {
+            chain_id: 56,
+            consensus: Consensus::Parlia(parlia),
+            ..config
+        };
+        let mut args = config.encode();
+        assert_eq!(Config::decode(&args).unwrap(), config);
+        // the validators sign for the chain id of the config
+        let other = Config {
+            chain_id: 97,
+            ..config.clone()
+        };
+        match Config::decode(&other.encode()).unwrap().consensus {
+            Consensus::Parlia(parlia) => assert_eq!(parlia.chain_id, 97),
+            _ => unreachable!(),
+        }
+        // the cell keeps at most one epoch header after its oldest one
+        args[params] = HEADER_SLOTS as u8;
+        args[params + 1] = 0;
+        assert_eq!(Config::decode(&args), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_bomb_delay() {
+        let forks = ForkSchedule::MAINNET;
+        assert_eq!(forks.bomb_delay(4_369_999), 0);
+        assert_eq!(forks.bomb_delay(4_370_000), 3_000_000);
+        assert_eq!(forks.bomb_delay(9_200_000), 9_000_000);
+        assert_eq!(forks.bomb_delay(15_050_000), 11_400_000);
+    }
+}
@@ -0,0 +1,263 @@
+//! Consensus rules of a header relative to its parent, as `verifyHeader` in go-ethereum's
+//! ethash engine, apart from the proof of work which `ethash::verify_pow` checks.
+//!
+//! The format of the fields, such as the 32 bytes uncles hash or the 256 bytes logs bloom, is
+//! checked by `Header::decode`. The uncles themselves are not part of the header, so the
+//! uncles hash is committed to without being verified.
+
+use crate::config::ForkSchedule;
+use crate::difficulty;
+use crate::error::Error;
+use crate::header::Header;
+
+/// Bytes of extra data a header may carry
+pub const MAXIMUM_EXTRA_DATA_SIZE: usize = 32;
+pub const MIN_GAS_LIMIT: u64 = 5000;
+pub const MAX_GAS_LIMIT: u64 = 0x7fff_ffff_ffff_ffff;
+/// The gas limit moves by less than `1 / GAS_LIMIT_BOUND_DIVISOR` of the parent one per block
+pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+/// Base fee of the London block
+pub const INITIAL_BASE_FEE: u128 = 1_000_000_000;
+/// The gas limit of a block is twice its gas target from London
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+/// The base fee moves by at most `1 / BASE_FEE_CHANGE_DENOMINATOR` per block
+pub const BASE_FEE_CHANGE_DENOMINATOR: u128 = 8;
+
+/// Verify `header` against its `parent`
+pub fn verify_header(forks: &ForkSchedule, parent: &Header, header: &Header) -> Result<(), Error> {
+    if header.extra_data.len() > MAXIMUM_EXTRA_DATA_SIZE {
+        return Err(Error::InvalidExtraData);
+    }
+    if header.timestamp <= parent.timestamp {
+        return Err(Error::InvalidTimestamp);
+    }
+    verify_gas(forks.london, parent, header)?;
+    difficulty::verify_difficulty(forks, parent, header)
+}
+
+/// Verify the gas limit, the gas used and the base fee of `header` against its `parent`, on a
+/// chain which activates London at the block `london`
+pub fn verify_gas(london: u64, parent: &Header, header: &Header) -> Result<(), Error> {
+    if header.gas_limit > MAX_GAS_LIMIT {
+        return Err(Error::InvalidGasLimit);
+    }
+    if header.gas_used > header.gas_limit {
+        return Err(Error::InvalidGasUsed);
+    }
+    if header.number >= london {
+        verify_eip1559(london, parent, header)
+    } else if header.base_fee_per_gas.is_some() {
+        Err(Error::InvalidBaseFee)
+    } else {
+        verify_gas_limit(parent.gas_limit, header.gas_limit)
+    }
+}
+
+/// Verify the gas limit and the base fee of a header from London, see EIP-1559
+fn verify_eip1559(london: u64, parent: &Header, header: &Header) -> Result<(), Error> {
+    // the gas limit of the London block is twice the one of its parent, as the gas target
+    // stays the same
+    let parent_gas_limit = if parent.number >= london {
+        parent.gas_limit
+    } else {
+        parent
+            .gas_limit
+            .checked_mul(ELASTICITY_MULTIPLIER)
+            .ok_or(Error::InvalidGasLimit)?
+    };
+    verify_gas_limit(parent_gas_limit, header.gas_limit)?;
+    if header.base_fee_per_gas != Some(base_fee(london, parent)?) {
+        return Err(Error::InvalidBaseFee);
+    }
+    Ok(())
+}
+
+/// Base fee of the child of `parent`, which follows the gas `parent` uses above or below its
+/// target
+pub fn base_fee(london: u64, parent: &Header) -> Result<u128, Error> {
+    if parent.number < london {
+        return Ok(INITIAL_BASE_FEE);
+    }
+    let parent_base_fee = parent.base_fee_per_gas.ok_or(Error::InvalidBaseFee)?;
+    let gas_target = (parent.gas_limit / ELASTICITY_MULTIPLIER) as u128;
+    let gas_used = parent.gas_used as u128;
+    if gas_target == 0 {
+        return Err(Error::InvalidGasLimit);
+    }
+    if gas_used > gas_target {
+        let delta = parent_base_fee
+            .checked_mul(gas_used - gas_target)
+            .ok_or(Error::InvalidBaseFee)?
+            / gas_target
+            / BASE_FEE_CHANGE_DENOMINATOR;
+        parent_base_fee
+            .checked_add(core::cmp::max(delta, 1))
+            .ok_or(Error::InvalidBaseFee)
+    } else {
+        let delta = parent_base_fee
+            .checked_mul(gas_target - gas_used)
+            .ok_or(Error::InvalidBaseFee)?
+            / gas_target
+            / BASE_FEE_CHANGE_DENOMINATOR;
+        Ok(parent_base_fee.saturating_sub(delta))
+    }
+}
+
+fn verify_gas_limit(parent_gas_limit: u64, gas_limit: u64) -> Result<(), Error> {
+    let diff = if gas_limit > parent_gas_limit {
+        gas_limit - parent_gas_limit
+    } else {
+        parent_gas_limit - gas_limit
+    };
+    if diff >= parent_gas_limit / GAS_LIMIT_BOUND_DIVISOR || gas_limit < MIN_GAS_LIMIT {
+        return Err(Error::InvalidGasLimit);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::*;
+
+    fn headers() -> (Header, Header) {
+        (
+            Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap(),
+            Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_verify_header() {
+        let forks = ForkSchedule::MAINNET;
+        let (genesis, block_1) = headers();
+        verify_header(&forks, &genesis, &block_1).unwrap();
+
+        let mut header = block_1.clone();
+        header.extra_data = vec![0u8; MAXIMUM_EXTRA_DATA_SIZE + 1];
+        assert_eq!(
+            verify_header(&forks, &genesis, &header),
+            Err(Error::InvalidExtraData)
+        );
+        let mut header = block_1.clone();
+        header.timestamp = genesis.timestamp;
+        assert_eq!(
+            verify_header(&forks, &genesis, &header),
+            Err(Error::InvalidTimestamp)
+        );
+        let mut header = block_1.clone();
+        header.gas_used = header.gas_limit + 1;
+        assert_eq!(
+            verify_header(&forks, &genesis, &header),
+            Err(Error::InvalidGasUsed)
+        );
+        let mut header = block_1;
+        header.gas_limit = MAX_GAS_LIMIT + 1;
+        assert_eq!(
+            verify_header(&forks, &genesis, &header),
+            Err(Error::InvalidGasLimit)
+        );
+    }
+
+    #[test]
+    fn test_verify_gas_limit() {
+        verify_gas_limit(5000, 5000).unwrap();
+        verify_gas_limit(5000, 5003).unwrap();
+        assert_eq!(verify_gas_limit(5000, 5004), Err(Error::InvalidGasLimit));
+        assert_eq!(verify_gas_limit(5000, 4996), Err(Error::InvalidGasLimit));
+        // below the minimum
+        assert_eq!(verify_gas_limit(5000, 4997), Err(Error::InvalidGasLimit));
+        let parent = 15_000_000;
+        verify_gas_limit(parent, parent + parent / 1024 - 1).unwrap();
+        assert_eq!(
+            verify_gas_limit(parent, parent + parent / 1024),
+            Err(Error::InvalidGasLimit)
+        );
+        assert_eq!(
+            verify_gas_limit(parent, parent - parent / 1024),
+            Err(Error::InvalidGasLimit)
+        );
+    }
+
+    /// A London header of `number` using `gas_used` out of 30 million gas
+    fn london_header(number: u64, base_fee: u128, gas_used: u64) -> Header {
+        let (_, mut header) = headers();
+        header.number = number;
+        header.gas_limit = 30_000_000;
+        header.gas_used = gas_used;
+        header.base_fee_per_gas = Some(base_fee);
+        header
+    }
+
+    #[test]
+    fn test_base_fee() {
+        let london = ForkSchedule::MAINNET.london;
+        let gwei = 1_000_000_000;
+        // at the target, above it and below it
+        let parent = london_header(london + 1, 100 * gwei, 15_000_000);
+        assert_eq!(base_fee(london, &parent), Ok(100 * gwei));
+        let parent = london_header(london + 1, 100 * gwei, 30_000_000);
+        assert_eq!(base_fee(london, &parent), Ok(112_500_000_000));
+        let parent = london_header(london + 1, 100 * gwei, 0);
+        assert_eq!(base_fee(london, &parent), Ok(87_500_000_000));
+        // the base fee rises by at least 1 wei when the parent is above its target
+        let parent = london_header(london + 1, 7, 15_000_001);
+        assert_eq!(base_fee(london, &parent), Ok(8));
+        let mut parent = london_header(london + 1, 7, 15_000_000);
+        parent.base_fee_per_gas = None;
+        assert_eq!(base_fee(london, &parent), Err(Error::InvalidBaseFee));
+    }
+
+    #[test]
+    fn test_verify_eip1559() {
+        let forks = ForkSchedule::MAINNET;
+        let london = forks.london;
+        // the London block doubles the gas limit and starts from the initial base fee
+        let mut parent = london_header(london - 1, 0, 10_000_000);
+        parent.gas_limit = 15_000_000;
+        parent.base_fee_per_gas = None;
+        let header = london_header(london, INITIAL_BASE_FEE, 0);
+        verify_eip1559(london, &parent, &header).unwrap();
+        let mut forged = header.clone();
+        forged.base_fee_per_gas = Some(INITIAL_BASE_FEE - 1);
+        assert_eq!(
+            verify_eip1559(london, &parent, &forged),
+            Err(Error::InvalidBaseFee)
+        );
+        forged.base_fee_per_gas = None;
+        assert_eq!(
+            verify_eip1559(london, &parent, &forged),
+            Err(Error::InvalidBaseFee)
+        );
+
+        // after London the gas limit moves by less than 1/1024 again
+        let parent = header;
+        let mut header = london_header(london + 1, 875_000_000, 0);
+        verify_eip1559(london, &parent, &header).unwrap();
+        header.gas_limit = 30_000_000 + 30_000_000 / 1024;
+        assert_eq!(
+            verify_eip1559(london, &parent, &header),
+            Err(Error::InvalidGasLimit)
+        );
+
+        // headers before London have no base fee
+        let (genesis, mut block_1) = headers();
+        block_1.base_fee_per_gas = Some(INITIAL_BASE_FEE);
+        assert_eq!(
+            verify_header(&forks, &genesis, &block_1),
+            Err(Error::InvalidBaseFee)
+        );
+    }
+
+    #[test]
+    fn test_uncles_hash_format() {
+        let mut data = decode_hex(MAINNET_GENESIS);
+        // shorten the uncles hash, which follows the 3 bytes list prefix and the parent hash
+        assert_eq!(&data[..3], &[0xf9, 0x02, 0x14]);
+        assert_eq!(data[36], 0xa0);
+        data[36] = 0x9f;
+        data.remove(37);
+        data[2] -= 1;
+        assert_eq!(Header::decode(&data), Err(Error::InvalidRlp));
+    }
+}
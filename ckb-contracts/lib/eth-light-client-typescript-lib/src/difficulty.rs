@@ -0,0 +1,190 @@
+//! Difficulty adjustment of the ethash chain, as `CalcDifficulty` in go-ethereum.
+//!
+//! The difficulty follows the time between a block and its parent, plus the exponential
+//! difficulty bomb which hard forks delay. A header with valid work but a lower difficulty
+//! than the formula of its fork would be cheaper to forge, so it is rejected.
+
+use crate::config::ForkSchedule;
+use crate::error::Error;
+use crate::header::Header;
+
+pub const MINIMUM_DIFFICULTY: u128 = 131_072;
+/// Blocks between doublings of the difficulty bomb
+const EXP_DIFF_PERIOD: u64 = 100_000;
+/// Uncles hash of a block without uncles, the keccak256 of an empty RLP list
+pub const EMPTY_UNCLES_HASH: [u8; 32] = [
+    0x1d, 0xcc, 0x4d, 0xe8, 0xde, 0xc7, 0x5d, 0x7a, 0xab, 0x85, 0xb5, 0x67, 0xb6, 0xcc, 0xd4, 0x1a,
+    0xd3, 0x12, 0x45, 0x1b, 0x94, 0x8a, 0x74, 0x13, 0xf0, 0xa1, 0x42, 0xfd, 0x40, 0xd4, 0x93, 0x47,
+];
+
+/// Difficulty of the child of `parent` mined at `timestamp`
+pub fn difficulty(forks: &ForkSchedule, parent: &Header, timestamp: u64) -> Result<u128, Error> {
+    let number = parent.number + 1;
+    let elapsed = timestamp as i128 - parent.timestamp as i128;
+    let adjustment = if number >= forks.byzantium {
+        // EIP-100, blocks with uncles count as longer
+        let uncles = if parent.uncles_hash == EMPTY_UNCLES_HASH {
+            1
+        } else {
+            2
+        };
+        core::cmp::max(uncles - elapsed.div_euclid(9), -99)
+    } else if number >= forks.homestead {
+        // EIP-2
+        core::cmp::max(1 - elapsed.div_euclid(10), -99)
+    } else if elapsed < 13 {
+        1
+    } else {
+        -1
+    };
+
+    let step = parent.difficulty / 2048;
+    let difficulty = if adjustment >= 0 {
+        parent
+            .difficulty
+            .checked_add(step * adjustment as u128)
+            .ok_or(Error::InvalidDifficulty)?
+    } else {
+        parent.difficulty - step * (-adjustment) as u128
+    };
+    let difficulty = core::cmp::max(difficulty, MINIMUM_DIFFICULTY);
+
+    let periods = number.saturating_sub(forks.bomb_delay(number)) / EXP_DIFF_PERIOD;
+    if periods < 2 {
+        return Ok(difficulty);
+    }
+    if periods - 2 >= 128 {
+        return Err(Error::InvalidDifficulty);
+    }
+    difficulty
+        .checked_add(1 << (periods - 2))
+        .ok_or(Error::InvalidDifficulty)
+}
+
+/// Check the difficulty of `header` against the one of its parent
+pub fn verify_difficulty(
+    forks: &ForkSchedule,
+    parent: &Header,
+    header: &Header,
+) -> Result<(), Error> {
+    if header.difficulty != difficulty(forks, parent, header.timestamp)? {
+        return Err(Error::InvalidDifficulty);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::keccak256;
+    use crate::test_data::*;
+
+    fn parent_at(number: u64, difficulty: u128) -> Header {
+        let mut header = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        header.number = number;
+        header.difficulty = difficulty;
+        header.timestamp = 1_000_000;
+        header.uncles_hash = EMPTY_UNCLES_HASH;
+        header
+    }
+
+    #[test]
+    fn test_empty_uncles_hash() {
+        assert_eq!(keccak256(&[0xc0]), EMPTY_UNCLES_HASH);
+    }
+
+    #[test]
+    fn test_frontier_difficulty() {
+        let forks = ForkSchedule::MAINNET;
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        verify_difficulty(&forks, &genesis, &block_1).unwrap();
+        let mut forged = block_1.clone();
+        forged.difficulty -= 1;
+        assert_eq!(
+            verify_difficulty(&forks, &genesis, &forged),
+            Err(Error::InvalidDifficulty)
+        );
+
+        let d = 1u128 << 40;
+        let parent = parent_at(999_999, d);
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_012),
+            Ok(d + d / 2048 + (1 << 8))
+        );
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_013),
+            Ok(d - d / 2048 + (1 << 8))
+        );
+    }
+
+    #[test]
+    fn test_homestead_difficulty() {
+        let forks = ForkSchedule::MAINNET;
+        let d = 1u128 << 44;
+        let parent = parent_at(1_199_999, d);
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_009),
+            Ok(d + d / 2048 + (1 << 10))
+        );
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_035),
+            Ok(d - 2 * (d / 2048) + (1 << 10))
+        );
+        // the adjustment is capped at -99
+        assert_eq!(
+            difficulty(&forks, &parent, 1_010_000),
+            Ok(d - 99 * (d / 2048) + (1 << 10))
+        );
+        // and the difficulty never drops below the minimum
+        let parent = parent_at(1_199_999, MINIMUM_DIFFICULTY);
+        assert_eq!(
+            difficulty(&forks, &parent, 1_010_000),
+            Ok(MINIMUM_DIFFICULTY + (1 << 10))
+        );
+    }
+
+    #[test]
+    fn test_byzantium_difficulty() {
+        let forks = ForkSchedule::MAINNET;
+        let d = 1u128 << 50;
+        // the bomb is delayed by 3 million blocks, 13 periods
+        let mut parent = parent_at(4_369_999, d);
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_008),
+            Ok(d + d / 2048 + (1 << 11))
+        );
+        assert_eq!(difficulty(&forks, &parent, 1_000_009), Ok(d + (1 << 11)));
+        parent.uncles_hash = [0u8; 32];
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_009),
+            Ok(d + d / 2048 + (1 << 11))
+        );
+    }
+
+    #[test]
+    fn test_bomb_delays() {
+        let forks = ForkSchedule::MAINNET;
+        let d = 1u128 << 50;
+        // the bomb is 2 periods in at Muir Glacier, and 36 at Gray Glacier
+        let parent = parent_at(9_199_999, d);
+        assert_eq!(difficulty(&forks, &parent, 1_000_009), Ok(d + 1));
+        let parent = parent_at(15_049_999, d);
+        assert_eq!(difficulty(&forks, &parent, 1_000_009), Ok(d + (1 << 34)));
+        // without a fork the bomb overflows the difficulty
+        let forks = ForkSchedule {
+            homestead: u64::MAX,
+            byzantium: u64::MAX,
+            constantinople: u64::MAX,
+            muir_glacier: u64::MAX,
+            london: u64::MAX,
+            arrow_glacier: u64::MAX,
+            gray_glacier: u64::MAX,
+        };
+        let parent = parent_at(15_049_999, d);
+        assert_eq!(
+            difficulty(&forks, &parent, 1_000_009),
+            Err(Error::InvalidDifficulty)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use ckb_std::error::SysError;
+
+/// Error
+#[repr(i8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    IndexOutOfBound = 1,
+    ItemMissing,
+    LengthNotEnough,
+    Encoding,
+    /// Data is not canonical RLP
+    InvalidRlp,
+    /// RLP is not a header or a header field is out of range
+    InvalidHeader,
+    /// A dataset item does not match the dataset root of its epoch
+    InvalidDagProof,
+    /// The mix hash or the difficulty of the proof of work does not match the header
+    InvalidPow,
+    /// The dataset root of the epoch of the header is not known
+    MissingDatasetRoot,
+    /// More than one light-client cell in the inputs or in the outputs
+    InvalidCellCount,
+    /// The dataset root table changes known roots, moves its window too far or has a number of
+    /// items which does not fit the size of the dataset
+    InvalidDatasetRoots,
+    /// The update requires an input locked by the owner
+    Unauthorized,
+    /// A header does not extend the tip, or the output headers are not the input headers
+    /// with the submitted one appended
+    InvalidHeaderChain,
+    /// The light-client cell changes its capacity
+    InvalidCapacity,
+    /// The parent of a submitted branch is not a kept header, the branch is invalid or forks
+    /// deeper than the kept headers
+    UnknownParent,
+    /// A competing branch does not have more total difficulty than the tip
+    StaleBranch,
+    /// The difficulty of a header does not follow from its parent
+    InvalidDifficulty,
+    /// A header is not later than its parent
+    InvalidTimestamp,
+    /// The gas limit of a header is out of bounds or moves too much from its parent
+    InvalidGasLimit,
+    /// A header uses more gas than its gas limit
+    InvalidGasUsed,
+    /// The extra data of a header is too long
+    InvalidExtraData,
+    /// The base fee of a header is missing, set before London, or does not follow from its
+    /// parent
+    InvalidBaseFee,
+    /// A header is not kept, nor a leaf of the merkle mountain range of the former headers
+    InvalidMmrProof,
+    /// A branch submits more than `MAX_BRANCH_HEADERS` headers, or more than
+    /// `MAX_SEALED_HEADERS` sealed headers in clique and parlia modes
+    TooManyHeaders,
+    /// The type id of the config is not the one of the created light-client cell
+    InvalidTypeId,
+    /// The created light-client cell does not start from the submitted checkpoint
+    InvalidCheckpoint,
+    /// The seal of a clique or parlia header does not recover its signer, or its vote, mix hash
+    /// or uncles hash is malformed
+    InvalidSeal,
+    /// The signer of a clique or parlia header is not a signer of the snapshot, or sealed too
+    /// many of the recent headers
+    UnauthorizedSigner,
+    /// A clique checkpoint does not list the signers of the snapshot, or the snapshot can not
+    /// hold its signers or votes
+    InvalidSigners,
+    /// A parlia epoch header lists no validator, the same validator twice or more validators
+    /// than a snapshot holds, or an invalid turn length
+    InvalidValidators,
+}
+
+impl From<SysError> for Error {
+    fn from(err: SysError) -> Self {
+        use SysError::*;
+        match err {
+            IndexOutOfBound => Self::IndexOutOfBound,
+            ItemMissing => Self::ItemMissing,
+            LengthNotEnough(_) => Self::LengthNotEnough,
+            Encoding => Self::Encoding,
+            Unknown(err_code) => panic!("unexpected sys error {}", err_code),
+        }
+    }
+}
+
+impl From<Error> for i8 {
+    fn from(err: Error) -> i8 {
+        err as i8
+    }
+}
@@ -0,0 +1,316 @@
+//! Ethash proof of work verification.
+//!
+//! The ethash dataset is more than a gigabyte, so instead of computing the 64 items hashimoto
+//! reads, the relayer supplies them with a merkle branch each. The items of the dataset of an
+//! epoch are the leaves of a binary merkle tree of depth `merkle_depth`: a leaf is the
+//! keccak256 of a 128 bytes item, a node is the keccak256 of its two children, and the leaves
+//! past the end of the dataset are zero. Each branch is checked against the dataset root of
+//! the epoch, so the work of the header is verified without trusting the relayer.
+
+use crate::error::Error;
+use crate::hash::{keccak256, keccak512};
+use crate::header::Header;
+use crate::rlp;
+use ckb_env::cycles::Operation;
+use ckb_env::traits::CkbChainInterface;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+pub const EPOCH_LENGTH: u64 = 30_000;
+/// Bytes of a dataset item, hashimoto mixes one item per access
+pub const ITEM_BYTES: usize = 128;
+/// Number of dataset items hashimoto reads
+pub const ACCESSES: usize = 64;
+const DATASET_BYTES_INIT: u64 = 1 << 30;
+const DATASET_BYTES_GROWTH: u64 = 1 << 23;
+/// Largest gap between two primes below 2^32, a bound of the distance of the number of items
+/// to the size limit
+const MAX_PRIME_GAP: u64 = 336;
+const MIX_WORDS: usize = ITEM_BYTES / 4;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+pub fn epoch(number: u64) -> u64 {
+    number / EPOCH_LENGTH
+}
+
+/// Largest number of items in the dataset of `epoch`, the limit grows linearly
+fn dataset_items_limit(epoch: u64) -> u64 {
+    (DATASET_BYTES_INIT + DATASET_BYTES_GROWTH * epoch) / ITEM_BYTES as u64 - 1
+}
+
+/// Number of items in the dataset of `epoch`, the largest prime below the size limit. The
+/// search is too expensive for the contract, the owner supplies the count with the dataset root
+pub fn dataset_items(epoch: u64) -> u64 {
+    (0..)
+        .map(|i| dataset_items_limit(epoch) - 2 * i)
+        .find(|&items| is_prime(items))
+        .expect("prime below the limit")
+}
+
+/// Cheap check of a number of items supplied for the dataset of `epoch`: an odd number at most
+/// `MAX_PRIME_GAP` below the size limit, so it sets the depth of the tree of the dataset
+pub fn check_dataset_items(epoch: u64, items: u64) -> bool {
+    let limit = dataset_items_limit(epoch);
+    items % 2 == 1 && items <= limit && limit - items <= MAX_PRIME_GAP
+}
+
+/// Depth of the merkle tree of a dataset with `items` leaves
+pub fn merkle_depth(items: u64) -> usize {
+    (64 - (items - 1).leading_zeros()) as usize
+}
+
+fn is_prime(n: u64) -> bool {
+    n >= 2 && (2..).take_while(|i| i * i <= n).all(|i| n % i != 0)
+}
+
+fn fnv(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(FNV_PRIME) ^ b
+}
+
+fn words(bytes: &[u8], words: &mut [u32]) {
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+}
+
+/// A dataset item read by hashimoto together with its merkle branch, siblings are ordered
+/// from the leaf up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DagProof {
+    pub item: Vec<u8>,
+    pub branch: Vec<[u8; 32]>,
+}
+
+impl DagProof {
+    /// Decode the RLP list `[item, [sibling, ...]]`
+    pub fn decode(item: rlp::Item<'_>) -> Result<Self, Error> {
+        let fields = item.list()?;
+        if fields.len() != 2 {
+            return Err(Error::InvalidDagProof);
+        }
+        let item = fields[0].bytes()?.to_vec();
+        if item.len() != ITEM_BYTES {
+            return Err(Error::InvalidDagProof);
+        }
+        let branch = fields[1]
+            .list()?
+            .iter()
+            .map(|sibling| {
+                let mut hash = [0u8; 32];
+                sibling.fixed(&mut hash)?;
+                Ok(hash)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(DagProof { item, branch })
+    }
+
+    /// Root of the tree the branch leads to when the item is the leaf at `index`
+    pub fn root(&self, index: u64) -> [u8; 32] {
+        let mut hash = keccak256(&self.item);
+        let mut node = [0u8; 64];
+        for (level, sibling) in self.branch.iter().enumerate() {
+            if (index >> level) & 1 == 0 {
+                node[..32].copy_from_slice(&hash);
+                node[32..].copy_from_slice(sibling);
+            } else {
+                node[..32].copy_from_slice(sibling);
+                node[32..].copy_from_slice(&hash);
+            }
+            hash = keccak256(&node);
+        }
+        hash
+    }
+}
+
+/// Run hashimoto over a dataset of `items` items, `lookup` returns the item at an index.
+///
+/// Returns the mix hash and the result which is compared with the difficulty.
+pub fn hashimoto<F>(
+    seal_hash: &[u8; 32],
+    nonce: &[u8; 8],
+    items: u64,
+    mut lookup: F,
+) -> Result<([u8; 32], [u8; 32]), Error>
+where
+    F: FnMut(usize, u64) -> Result<[u8; ITEM_BYTES], Error>,
+{
+    // the nonce is hashed in little endian
+    let mut seed_input = [0u8; 40];
+    seed_input[..32].copy_from_slice(seal_hash);
+    for (i, byte) in nonce.iter().rev().enumerate() {
+        seed_input[32 + i] = *byte;
+    }
+    let seed = keccak512(&seed_input);
+    let mut seed_words = [0u32; 16];
+    words(&seed, &mut seed_words);
+
+    let mut mix = [0u32; MIX_WORDS];
+    for (i, word) in mix.iter_mut().enumerate() {
+        *word = seed_words[i % 16];
+    }
+    let mut item_words = [0u32; MIX_WORDS];
+    for i in 0..ACCESSES {
+        let index = fnv(i as u32 ^ seed_words[0], mix[i % MIX_WORDS]) as u64 % items;
+        words(&lookup(i, index)?, &mut item_words);
+        for (word, item_word) in mix.iter_mut().zip(item_words.iter()) {
+            *word = fnv(*word, *item_word);
+        }
+    }
+
+    let mut mix_hash = [0u8; 32];
+    for (i, chunk) in mix.chunks(4).enumerate() {
+        let word = fnv(fnv(fnv(chunk[0], chunk[1]), chunk[2]), chunk[3]);
+        mix_hash[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    let mut result_input = [0u8; 96];
+    result_input[..64].copy_from_slice(&seed);
+    result_input[64..].copy_from_slice(&mix_hash);
+    Ok((mix_hash, keccak256(&result_input)))
+}
+
+/// Whether `result` meets `difficulty`, that is `result <= 2^256 / difficulty`
+pub fn meets_difficulty(result: &[u8; 32], difficulty: u128) -> bool {
+    if difficulty == 0 {
+        return false;
+    }
+    // multiply the little endian 64 bits limbs of the result by the difficulty
+    let mut limbs = [0u64; 4];
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&result[24 - i * 8..32 - i * 8]);
+        *limb = u64::from_be_bytes(bytes);
+    }
+    let mut product = [0u64; 6];
+    for (j, &factor) in [difficulty as u64, (difficulty >> 64) as u64]
+        .iter()
+        .enumerate()
+    {
+        let mut carry = 0u128;
+        for (i, &limb) in limbs.iter().enumerate() {
+            let value = limb as u128 * factor as u128 + product[i + j] as u128 + carry;
+            product[i + j] = value as u64;
+            carry = value >> 64;
+        }
+        product[j + 4] = carry as u64;
+    }
+    // the product is at most 2^256
+    match (product[4], product[5]) {
+        (0, 0) => true,
+        (1, 0) => product[..4].iter().all(|&limb| limb == 0),
+        _ => false,
+    }
+}
+
+/// Verify the proof of work of `header`, `proofs` are the items hashimoto reads in order of
+/// access, `dataset_root` is the merkle root of the dataset of the epoch of the header and
+/// `items` its number of items
+pub fn verify_pow<T: CkbChainInterface>(
+    chain: &T,
+    header: &Header,
+    proofs: &[DagProof],
+    dataset_root: &[u8; 32],
+    items: u64,
+) -> Result<(), Error> {
+    if proofs.len() != ACCESSES {
+        return Err(Error::InvalidDagProof);
+    }
+    let depth = merkle_depth(items);
+
+    let seal = header.encode_without_seal();
+    chain.charge_cycles(Operation::Keccak256(seal.len()));
+    chain.charge_cycles(Operation::Hashimoto);
+    let (mix_hash, result) = hashimoto(&keccak256(&seal), &header.nonce, items, |i, index| {
+        let proof = &proofs[i];
+        if proof.branch.len() != depth {
+            return Err(Error::InvalidDagProof);
+        }
+        chain.charge_cycles(Operation::Keccak256(ITEM_BYTES));
+        chain.charge_cycles(Operation::Cycles(
+            depth as u64 * Operation::Keccak256(64).cycles(),
+        ));
+        if &proof.root(index) != dataset_root {
+            return Err(Error::InvalidDagProof);
+        }
+        let mut item = [0u8; ITEM_BYTES];
+        item.copy_from_slice(&proof.item);
+        Ok(item)
+    })?;
+    if mix_hash != header.mix_hash || !meets_difficulty(&result, header.difficulty) {
+        return Err(Error::InvalidPow);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::*;
+    use ckb_env::mock::MockCKBChain;
+
+    #[test]
+    fn test_dataset_items() {
+        assert_eq!(dataset_items(0) * ITEM_BYTES as u64, 1_073_739_904);
+        assert_eq!(dataset_items(1) * ITEM_BYTES as u64, 1_082_130_304);
+        assert_eq!(dataset_items(100) * ITEM_BYTES as u64, 1_912_601_216);
+        assert_eq!(merkle_depth(dataset_items(0)), 23);
+        assert_eq!(merkle_depth(8), 3);
+        assert_eq!(merkle_depth(9), 4);
+
+        for &epoch in &[0, 1, 100] {
+            let items = dataset_items(epoch);
+            assert!(check_dataset_items(epoch, items));
+            assert!(!check_dataset_items(epoch, items - 1));
+            assert!(!check_dataset_items(epoch, items + 2 * MAX_PRIME_GAP));
+            assert!(!check_dataset_items(epoch, items - 2 * MAX_PRIME_GAP));
+        }
+    }
+
+    #[test]
+    fn test_meets_difficulty() {
+        let mut result = [0u8; 32];
+        result[4] = 0x2b;
+        assert!(meets_difficulty(&result, 17_171_480_576));
+        assert!(!meets_difficulty(&result, 17_171_480_576 * 4));
+        assert!(meets_difficulty(&[0xff; 32], 1));
+        assert!(!meets_difficulty(&[0xff; 32], 2));
+        assert!(!meets_difficulty(&[0u8; 32], 0));
+    }
+
+    #[test]
+    fn test_verify_pow() {
+        let header = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        let (root, proofs) = dag_proofs(&MAINNET_BLOCK_1_DAG_ITEMS, 0);
+        let items = dataset_items(0);
+        let chain = MockCKBChain::default();
+        verify_pow(&chain, &header, &proofs, &root, items).unwrap();
+
+        let mut forged = header.clone();
+        forged.mix_hash[0] ^= 1;
+        assert_eq!(
+            verify_pow(&chain, &forged, &proofs, &root, items),
+            Err(Error::InvalidPow)
+        );
+        // the difficulty and the nonce are sealed, they change the items hashimoto reads
+        let mut forged = header.clone();
+        forged.difficulty *= 4;
+        assert!(verify_pow(&chain, &forged, &proofs, &root, items).is_err());
+        let mut forged = header.clone();
+        forged.nonce[7] ^= 1;
+        assert!(verify_pow(&chain, &forged, &proofs, &root, items).is_err());
+        let mut forged_proofs = proofs.clone();
+        forged_proofs[10].item[0] ^= 1;
+        assert_eq!(
+            verify_pow(&chain, &header, &forged_proofs, &root, items),
+            Err(Error::InvalidDagProof)
+        );
+        assert_eq!(
+            verify_pow(&chain, &header, &proofs[1..], &root, items),
+            Err(Error::InvalidDagProof)
+        );
+    }
+}
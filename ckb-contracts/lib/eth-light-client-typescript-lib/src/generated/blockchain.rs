@@ -0,0 +1,6 @@
+//! The types of `blockchain.mol` of ckb the schemas import, from ckb-standalone-types
+
+pub use ckb_std::ckb_types::packed::{
+    Byte32, Byte32Builder, Byte32Reader, Bytes, BytesBuilder, BytesReader, Uint32, Uint32Builder,
+    Uint32Reader, Uint64, Uint64Builder, Uint64Reader,
+};
@@ -0,0 +1,9 @@
+//! Molecule types of the `schemas` directory. `light_client` is generated by moleculec with the
+//! `schema` target of the Makefile, `blockchain` takes the types imported from the schema of ckb
+//! from ckb-standalone-types.
+
+#![allow(clippy::all)]
+#![allow(unused_imports)]
+
+pub mod blockchain;
+pub mod light_client;
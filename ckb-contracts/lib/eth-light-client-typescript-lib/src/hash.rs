@@ -0,0 +1,31 @@
+use blake2b_ref::Blake2bBuilder;
+use tiny_keccak::{Hasher, Keccak};
+
+const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+pub fn keccak512(data: &[u8]) -> [u8; 64] {
+    let mut hasher = Keccak::v512();
+    hasher.update(data);
+    let mut hash = [0u8; 64];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Blake2b-256 with the ckb personalization, as the script hashes and type ids of ckb
+pub fn blake2b_256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2bBuilder::new(32)
+        .personal(CKB_HASH_PERSONALIZATION)
+        .build();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
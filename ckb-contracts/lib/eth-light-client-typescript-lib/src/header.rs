@@ -0,0 +1,240 @@
+use crate::error::Error;
+use crate::hash::keccak256;
+use crate::rlp;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Number of fields of a header before London
+const HEADER_FIELDS: usize = 15;
+/// Number of fields of a header after London, Shanghai, Cancun and Prague, each fork appends
+/// fields to the header
+const FORK_HEADER_FIELDS: [usize; 4] = [16, 17, 20, 21];
+
+/// An Ethereum block header.
+///
+/// Difficulty and base fee are 256 bits integers in the protocol, headers whose difficulty or
+/// base fee does not fit in 128 bits are rejected as invalid, which leaves more than enough room
+/// for mainnet. The fields appended by forks are `None` in the headers of earlier blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// keccak256 of the RLP encoded header
+    pub hash: [u8; 32],
+    pub parent_hash: [u8; 32],
+    pub uncles_hash: [u8; 32],
+    pub coinbase: [u8; 20],
+    pub state_root: [u8; 32],
+    pub transactions_root: [u8; 32],
+    pub receipts_root: [u8; 32],
+    pub logs_bloom: Vec<u8>,
+    pub difficulty: u128,
+    pub number: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub timestamp: u64,
+    pub extra_data: Vec<u8>,
+    pub mix_hash: [u8; 32],
+    pub nonce: [u8; 8],
+    /// EIP-1559, from London
+    pub base_fee_per_gas: Option<u128>,
+    /// EIP-4895, from Shanghai
+    pub withdrawals_root: Option<[u8; 32]>,
+    /// EIP-4844, from Cancun
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    /// EIP-4788, from Cancun
+    pub parent_beacon_block_root: Option<[u8; 32]>,
+    /// EIP-7685, from Prague
+    pub requests_hash: Option<[u8; 32]>,
+}
+
+impl Header {
+    /// Decode a RLP encoded header
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let fields = rlp::decode(data)?.list()?;
+        if fields.len() != HEADER_FIELDS && !FORK_HEADER_FIELDS.contains(&fields.len()) {
+            return Err(Error::InvalidHeader);
+        }
+        let mut header = Header {
+            hash: keccak256(data),
+            parent_hash: [0u8; 32],
+            uncles_hash: [0u8; 32],
+            coinbase: [0u8; 20],
+            state_root: [0u8; 32],
+            transactions_root: [0u8; 32],
+            receipts_root: [0u8; 32],
+            logs_bloom: fields[6].bytes()?.to_vec(),
+            difficulty: fields[7].u128()?,
+            number: fields[8].u64()?,
+            gas_limit: fields[9].u64()?,
+            gas_used: fields[10].u64()?,
+            timestamp: fields[11].u64()?,
+            extra_data: fields[12].bytes()?.to_vec(),
+            mix_hash: [0u8; 32],
+            nonce: [0u8; 8],
+            base_fee_per_gas: fields.get(15).map(rlp::Item::u128).transpose()?,
+            withdrawals_root: fields.get(16).map(hash_field).transpose()?,
+            blob_gas_used: fields.get(17).map(rlp::Item::u64).transpose()?,
+            excess_blob_gas: fields.get(18).map(rlp::Item::u64).transpose()?,
+            parent_beacon_block_root: fields.get(19).map(hash_field).transpose()?,
+            requests_hash: fields.get(20).map(hash_field).transpose()?,
+        };
+        fields[0].fixed(&mut header.parent_hash)?;
+        fields[1].fixed(&mut header.uncles_hash)?;
+        fields[2].fixed(&mut header.coinbase)?;
+        fields[3].fixed(&mut header.state_root)?;
+        fields[4].fixed(&mut header.transactions_root)?;
+        fields[5].fixed(&mut header.receipts_root)?;
+        fields[13].fixed(&mut header.mix_hash)?;
+        fields[14].fixed(&mut header.nonce)?;
+        if header.logs_bloom.len() != 256 {
+            return Err(Error::InvalidHeader);
+        }
+        Ok(header)
+    }
+
+    /// RLP encode the header
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_fields(true)
+    }
+
+    /// RLP encode the header without the mix hash and the nonce, its hash is the input of the
+    /// proof of work. The fields appended by forks are kept, only the base fee is set while
+    /// ethash is live.
+    pub fn encode_without_seal(&self) -> Vec<u8> {
+        self.encode_fields(false)
+    }
+
+    fn encode_fields(&self, with_seal: bool) -> Vec<u8> {
+        let mut fields = Vec::new();
+        rlp::encode_bytes(&mut fields, &self.parent_hash);
+        rlp::encode_bytes(&mut fields, &self.uncles_hash);
+        rlp::encode_bytes(&mut fields, &self.coinbase);
+        rlp::encode_bytes(&mut fields, &self.state_root);
+        rlp::encode_bytes(&mut fields, &self.transactions_root);
+        rlp::encode_bytes(&mut fields, &self.receipts_root);
+        rlp::encode_bytes(&mut fields, &self.logs_bloom);
+        rlp::encode_u128(&mut fields, self.difficulty);
+        rlp::encode_u64(&mut fields, self.number);
+        rlp::encode_u64(&mut fields, self.gas_limit);
+        rlp::encode_u64(&mut fields, self.gas_used);
+        rlp::encode_u64(&mut fields, self.timestamp);
+        rlp::encode_bytes(&mut fields, &self.extra_data);
+        if with_seal {
+            rlp::encode_bytes(&mut fields, &self.mix_hash);
+            rlp::encode_bytes(&mut fields, &self.nonce);
+        }
+        if let Some(base_fee_per_gas) = self.base_fee_per_gas {
+            rlp::encode_u128(&mut fields, base_fee_per_gas);
+        }
+        if let Some(withdrawals_root) = self.withdrawals_root {
+            rlp::encode_bytes(&mut fields, &withdrawals_root);
+        }
+        if let Some(blob_gas_used) = self.blob_gas_used {
+            rlp::encode_u64(&mut fields, blob_gas_used);
+        }
+        if let Some(excess_blob_gas) = self.excess_blob_gas {
+            rlp::encode_u64(&mut fields, excess_blob_gas);
+        }
+        if let Some(parent_beacon_block_root) = self.parent_beacon_block_root {
+            rlp::encode_bytes(&mut fields, &parent_beacon_block_root);
+        }
+        if let Some(requests_hash) = self.requests_hash {
+            rlp::encode_bytes(&mut fields, &requests_hash);
+        }
+        let mut out = Vec::new();
+        rlp::encode_list(&mut out, &fields);
+        out
+    }
+}
+
+fn hash_field(item: &rlp::Item<'_>) -> Result<[u8; 32], Error> {
+    let mut hash = [0u8; 32];
+    item.fixed(&mut hash)?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::*;
+
+    #[test]
+    fn test_decode_header() {
+        let header = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        assert_eq!(header.hash.to_vec(), decode_hex(MAINNET_BLOCK_1_HASH));
+        assert_eq!(
+            header.parent_hash.to_vec(),
+            decode_hex(MAINNET_GENESIS_HASH)
+        );
+        assert_eq!(header.number, 1);
+        assert_eq!(header.difficulty, 17_171_480_576);
+        assert_eq!(header.gas_limit, 5000);
+        assert_eq!(header.timestamp, 1_438_269_988);
+        assert_eq!(header.extra_data, b"Geth/v1.0.0/linux/go1.4.2".to_vec());
+        assert_eq!(header.nonce.to_vec(), decode_hex("539bd4979fef1ec4"));
+
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        assert_eq!(genesis.hash.to_vec(), decode_hex(MAINNET_GENESIS_HASH));
+        assert_eq!(genesis.number, 0);
+    }
+
+    #[test]
+    fn test_encode_header() {
+        let data = decode_hex(MAINNET_BLOCK_1);
+        let header = Header::decode(&data).unwrap();
+        assert_eq!(header.encode(), data);
+        assert_eq!(
+            keccak256(&header.encode_without_seal()).to_vec(),
+            decode_hex("85913a3057ea8bec78cd916871ca73802e77724e014dda65add3405d02240eb7")
+        );
+    }
+
+    #[test]
+    fn test_fork_header_fields() {
+        let mut header = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        header.base_fee_per_gas = Some(1_000_000_000);
+        let data = header.encode();
+        header.hash = keccak256(&data);
+        assert_eq!(Header::decode(&data).unwrap(), header);
+        assert_eq!(
+            rlp::decode(&header.encode_without_seal())
+                .unwrap()
+                .list()
+                .unwrap()
+                .len(),
+            14
+        );
+
+        header.withdrawals_root = Some([1u8; 32]);
+        header.blob_gas_used = Some(131_072);
+        header.excess_blob_gas = Some(0);
+        header.parent_beacon_block_root = Some([2u8; 32]);
+        for &requests_hash in &[None, Some([3u8; 32])] {
+            header.requests_hash = requests_hash;
+            let data = header.encode();
+            header.hash = keccak256(&data);
+            assert_eq!(Header::decode(&data).unwrap(), header);
+        }
+
+        // the fields of a fork come together
+        header.parent_beacon_block_root = None;
+        header.requests_hash = None;
+        assert_eq!(Header::decode(&header.encode()), Err(Error::InvalidHeader));
+    }
+
+    #[test]
+    fn test_decode_invalid_header() {
+        let mut data = decode_hex(MAINNET_BLOCK_1);
+        // drop the nonce and shrink the list length accordingly
+        data.truncate(data.len() - 9);
+        data[2] -= 9;
+        assert_eq!(Header::decode(&data), Err(Error::InvalidHeader));
+        assert_eq!(Header::decode(&[0xc0]), Err(Error::InvalidHeader));
+        assert_eq!(Header::decode(&[0x80]), Err(Error::InvalidRlp));
+    }
+}
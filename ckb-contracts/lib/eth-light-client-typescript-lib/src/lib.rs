@@ -0,0 +1,1437 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod clique;
+pub mod config;
+pub mod consensus;
+pub mod difficulty;
+pub mod error;
+pub mod ethash;
+pub mod generated;
+pub mod hash;
+pub mod header;
+pub mod mmr;
+pub mod parlia;
+pub mod rlp;
+pub mod roots;
+pub mod state;
+#[cfg(test)]
+mod test_data;
+
+pub use ckb_env::debug;
+
+use ckb_env::cycles::Operation;
+use ckb_env::traits::{CkbChainInterface, ContractInterface};
+use ckb_std::ckb_constants::Source;
+use ckb_std::ckb_types::prelude::*;
+use ckb_std::error::SysError;
+use config::{CliqueConfig, Config, Consensus, ForkSchedule, ParliaConfig};
+use error::Error;
+use ethash::DagProof;
+use header::Header;
+use roots::{DatasetRoots, ROOT_SLOTS};
+use state::{CliqueClientState, HeaderChain, HeaderRecord, LightClientState, ParliaClientState};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        extern crate alloc;
+        use alloc::vec::Vec;
+    }
+}
+
+/// Headers a branch submits at most.
+///
+/// Verifying the work of a header reads `ethash::ACCESSES` dataset items with a merkle branch
+/// each, about 21 million cycles with the 26 levels of the dataset trees of 2022, and a
+/// transaction may use 70 million cycles. `test_submit_full_branch` of the tests crate submits
+/// a full branch of such headers to the release build of the contract in ckb-vm and checks that
+/// it fits, `test_submit_branch_over_limit` checks that a branch of one more header does not.
+pub const MAX_BRANCH_HEADERS: usize = 3;
+
+/// Headers a branch of sealed headers submits at most, in clique and parlia modes.
+///
+/// Recovering the signer of a header is estimated at `ckb_env::cycles::SECP256K1_RECOVER_CYCLES`,
+/// so a full branch stays below the 70 million cycles of a transaction.
+/// `test_submit_full_clique_branch` and `test_submit_full_parlia_branch` of the tests crate
+/// submit a full branch of each mode to the release build of the contract in ckb-vm and check
+/// that it fits.
+pub const MAX_SEALED_HEADERS: usize = 12;
+
+/// Type script of the cell which follows the Ethereum header chain.
+///
+/// The script args are a `config::Config`, which names the network by its chain id and its
+/// consensus mode, so one binary serves the cells of every network. Creating the cell,
+/// destroying it and changing its dataset roots require an input locked by the owner of the
+/// config, and the type id of the config makes the cell unique, see `verify_type_id`. The cell
+/// keeps the latest `ring_size` headers of the config, and an update consumes the cell and
+/// produces its successor with the same capacity. Every mode is a `ConsensusEngine`, and its
+/// witness, the checkpoint of a created cell or the branch of an update, is the `output_type`
+/// of the witness of the output cell, where every header is RLP encoded as a byte string:
+///
+/// - ethash: the cell data is a `state::LightClientState`, the latest headers with the
+///   `mmr::MerkleMountainRange` of the former ones and the dataset roots of the epochs the cell
+///   can verify headers of. The checkpoint is the RLP list
+///   `[header, total_difficulty, dataset_root, dataset_items]`, with the total difficulty of
+///   the chain up to the header and the dataset root and items of its epoch. A branch is the
+///   RLP list of its parent followed by at most `MAX_BRANCH_HEADERS` submissions from the
+///   oldest one, each the RLP list `[header, [proof, ...]]` with the `ethash::DagProof` of the
+///   items hashimoto reads. Every header is checked against its parent by
+///   `consensus::verify_header` with the fork schedule of the config and its work against the
+///   root of its epoch in the output table, and `state::HeaderChain::apply_branch` lets a
+///   heavier fork within the kept headers replace the tip.
+/// - clique: the cell follows a proof-of-authority chain, the cell data is a
+///   `state::CliqueClientState`. The checkpoint is described by `verify_clique_checkpoint` and
+///   an update submits a branch of sealed headers, see `load_sealed_branch`.
+/// - parlia: the cell follows a proof-of-staked-authority chain such as BNB Smart Chain, the
+///   cell data is a `state::ParliaClientState`. The checkpoint is described by
+///   `verify_parlia_checkpoint` and an update submits a branch of sealed headers as in clique.
+pub struct EthLightClientTypescript;
+
+impl<T: CkbChainInterface> ContractInterface<T> for EthLightClientTypescript {
+    type Error = Error;
+
+    fn verify(chain: &T) -> Result<(), Error> {
+        let config = Config::decode(&chain.load_script()?.args().raw_data())?;
+        let input = load_data(chain, Source::GroupInput)?;
+        let output = load_data(chain, Source::GroupOutput)?;
+        let (input, output) = match (input, output) {
+            (Some(input), Some(output)) => (input, output),
+            (None, Some(output)) => {
+                verify_owner(chain, &config.owner_lock_hash)?;
+                verify_type_id(chain, &config.type_id)?;
+                return engine(&config.consensus).verify_checkpoint(chain, &config, &output);
+            }
+            _ => return verify_owner(chain, &config.owner_lock_hash),
+        };
+        if chain.load_cell_capacity(0, Source::GroupInput)?
+            != chain.load_cell_capacity(0, Source::GroupOutput)?
+        {
+            return Err(Error::InvalidCapacity);
+        }
+        engine(&config.consensus).verify_update(chain, &config, &input, &output)
+    }
+}
+
+/// A consensus the light client follows, implemented by the parameters of its mode in the
+/// config. A mode brings its own cell data, which starts with the `state::HeaderChain` the
+/// bridge reads, its own witnesses and its own checkpoint.
+pub trait ConsensusEngine<T: CkbChainInterface> {
+    /// Check that the created light-client cell, whose data is `output`, is the state of the
+    /// checkpoint submitted with it, with a ring of the `ring_size` of the `config`
+    fn verify_checkpoint(&self, chain: &T, config: &Config, output: &[u8]) -> Result<(), Error>;
+
+    /// Verify an update of the light-client cell from the `input` data to the `output` data
+    fn verify_update(
+        &self,
+        chain: &T,
+        config: &Config,
+        input: &[u8],
+        output: &[u8],
+    ) -> Result<(), Error>;
+}
+
+fn engine<T: CkbChainInterface>(consensus: &Consensus) -> &dyn ConsensusEngine<T> {
+    match consensus {
+        Consensus::Ethash(forks) => forks,
+        Consensus::Clique(clique) => clique,
+        Consensus::Parlia(parlia) => parlia,
+    }
+}
+
+impl<T: CkbChainInterface> ConsensusEngine<T> for ForkSchedule {
+    fn verify_checkpoint(&self, chain: &T, config: &Config, output: &[u8]) -> Result<(), Error> {
+        verify_checkpoint(chain, config.ring_size, &LightClientState::decode(output)?)
+    }
+
+    fn verify_update(
+        &self,
+        chain: &T,
+        config: &Config,
+        input: &[u8],
+        output: &[u8],
+    ) -> Result<(), Error> {
+        verify_ethash_update(
+            chain,
+            config,
+            self,
+            LightClientState::decode(input)?,
+            LightClientState::decode(output)?,
+        )
+    }
+}
+
+impl<T: CkbChainInterface> ConsensusEngine<T> for CliqueConfig {
+    fn verify_checkpoint(&self, chain: &T, config: &Config, output: &[u8]) -> Result<(), Error> {
+        let output = CliqueClientState::decode(output)?;
+        verify_clique_checkpoint(chain, self, config.ring_size, &output)
+    }
+
+    fn verify_update(
+        &self,
+        chain: &T,
+        _config: &Config,
+        input: &[u8],
+        output: &[u8],
+    ) -> Result<(), Error> {
+        verify_clique_update(
+            chain,
+            self,
+            CliqueClientState::decode(input)?,
+            CliqueClientState::decode(output)?,
+        )
+    }
+}
+
+impl<T: CkbChainInterface> ConsensusEngine<T> for ParliaConfig {
+    fn verify_checkpoint(&self, chain: &T, config: &Config, output: &[u8]) -> Result<(), Error> {
+        let output = ParliaClientState::decode(output)?;
+        verify_parlia_checkpoint(chain, self, config.ring_size, &output)
+    }
+
+    fn verify_update(
+        &self,
+        chain: &T,
+        _config: &Config,
+        input: &[u8],
+        output: &[u8],
+    ) -> Result<(), Error> {
+        verify_parlia_update(
+            chain,
+            self,
+            ParliaClientState::decode(input)?,
+            ParliaClientState::decode(output)?,
+        )
+    }
+}
+
+/// Verify an update of the cell in ethash mode, which applies the submitted branch and may
+/// change the dataset roots
+fn verify_ethash_update<T: CkbChainInterface>(
+    chain: &T,
+    config: &Config,
+    forks: &ForkSchedule,
+    input: LightClientState,
+    output: LightClientState,
+) -> Result<(), Error> {
+    if input.roots != output.roots {
+        verify_owner(chain, &config.owner_lock_hash)?;
+        let oldest_epoch = output
+            .headers
+            .get(0)
+            .map(|record| ethash::epoch(record.number));
+        input.roots.verify_update(&output.roots, oldest_epoch)?;
+    }
+
+    let mut headers = input.headers;
+    if let Some(branch) = load_branch(chain)? {
+        headers.apply_branch(branch.headers.iter().map(|(header, _)| header))?;
+        // the branch links to a kept header, which is its parent
+        let mut parent = &branch.parent;
+        for (header, proofs) in &branch.headers {
+            if header.parent_hash != parent.hash {
+                return Err(Error::InvalidHeaderChain);
+            }
+            consensus::verify_header(forks, parent, header)?;
+            let (root, items) = output
+                .roots
+                .root(ethash::epoch(header.number))
+                .ok_or(Error::MissingDatasetRoot)?;
+            ethash::verify_pow(chain, header, proofs, &root, items)?;
+            debug!(chain, "header {}: {:?}", header.number, &header.hash);
+            parent = header;
+        }
+    }
+    if headers != output.headers {
+        return Err(Error::InvalidHeaderChain);
+    }
+    Ok(())
+}
+
+/// Verify an update of the cell in clique mode, which applies the submitted branch of sealed
+/// headers
+fn verify_clique_update<T: CkbChainInterface>(
+    chain: &T,
+    config: &CliqueConfig,
+    input: CliqueClientState,
+    output: CliqueClientState,
+) -> Result<(), Error> {
+    let state = match load_sealed_branch(chain)? {
+        Some((parent, headers)) => {
+            let state = clique::apply_branch(config, &input, &parent, &headers)?;
+            for header in &headers {
+                debug!(chain, "header {}: {:?}", header.number, &header.hash);
+            }
+            state
+        }
+        None => input,
+    };
+    if state.headers != output.headers {
+        return Err(Error::InvalidHeaderChain);
+    }
+    if state != output {
+        return Err(Error::InvalidSigners);
+    }
+    Ok(())
+}
+
+/// Load the data of the light-client cell in a group source, there is at most one
+fn load_data<T: CkbChainInterface>(chain: &T, source: Source) -> Result<Option<Vec<u8>>, Error> {
+    match chain.load_cell_data(1, source) {
+        Err(SysError::IndexOutOfBound) => {}
+        Ok(_) => return Err(Error::InvalidCellCount),
+        Err(err) => return Err(err.into()),
+    }
+    match chain.load_cell_data(0, source) {
+        Ok(data) => Ok(Some(data)),
+        Err(SysError::IndexOutOfBound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Load the `output_type` of the witness of the output cell, if any
+fn load_output_type<T: CkbChainInterface>(chain: &T) -> Result<Option<Vec<u8>>, Error> {
+    match chain.load_witness_args(0, Source::GroupOutput) {
+        Ok(witness) => Ok(witness
+            .output_type()
+            .to_opt()
+            .map(|output_type| output_type.raw_data().to_vec())),
+        Err(SysError::IndexOutOfBound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// A branch of submitted headers with the proofs of their work
+struct Branch {
+    /// The kept header the branch forks from
+    parent: Header,
+    headers: Vec<(Header, Vec<DagProof>)>,
+}
+
+/// Load the branch of headers submitted with the output cell, if any
+fn load_branch<T: CkbChainInterface>(chain: &T) -> Result<Option<Branch>, Error> {
+    let branch = match load_output_type(chain)? {
+        Some(branch) => branch,
+        None => return Ok(None),
+    };
+    let items = rlp::decode(&branch)?.list()?;
+    let (parent, submissions) = items.split_first().ok_or(Error::Encoding)?;
+    if submissions.len() > MAX_BRANCH_HEADERS && cfg!(not(feature = "unbounded-branch")) {
+        return Err(Error::TooManyHeaders);
+    }
+    let parent = decode_header(chain, parent.bytes()?)?;
+    let headers = submissions
+        .iter()
+        .map(|submission| {
+            let submission = submission.list()?;
+            if submission.len() != 2 {
+                return Err(Error::Encoding);
+            }
+            let header = decode_header(chain, submission[0].bytes()?)?;
+            let proofs = submission[1]
+                .list()?
+                .into_iter()
+                .map(DagProof::decode)
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok((header, proofs))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Some(Branch { parent, headers }))
+}
+
+/// Verify an update of the cell in parlia mode, which applies the submitted branch of sealed
+/// headers
+fn verify_parlia_update<T: CkbChainInterface>(
+    chain: &T,
+    config: &ParliaConfig,
+    input: ParliaClientState,
+    output: ParliaClientState,
+) -> Result<(), Error> {
+    let state = match load_sealed_branch(chain)? {
+        Some((parent, headers)) => {
+            let state = parlia::apply_branch(config, &input, &parent, &headers)?;
+            for header in &headers {
+                debug!(chain, "header {}: {:?}", header.number, &header.hash);
+            }
+            state
+        }
+        None => input,
+    };
+    if state.headers != output.headers {
+        return Err(Error::InvalidHeaderChain);
+    }
+    if state != output {
+        return Err(Error::InvalidValidators);
+    }
+    Ok(())
+}
+
+/// Load the branch of sealed headers submitted with the output cell in clique or parlia mode,
+/// if any: the RLP list of the RLP encoded parent of the branch followed by at most
+/// `MAX_SEALED_HEADERS` RLP encoded headers from the oldest one, all as byte strings
+fn load_sealed_branch<T: CkbChainInterface>(
+    chain: &T,
+) -> Result<Option<(Header, Vec<Header>)>, Error> {
+    let branch = match load_output_type(chain)? {
+        Some(branch) => branch,
+        None => return Ok(None),
+    };
+    let items = rlp::decode(&branch)?.list()?;
+    let (parent, headers) = items.split_first().ok_or(Error::Encoding)?;
+    if headers.len() > MAX_SEALED_HEADERS {
+        return Err(Error::TooManyHeaders);
+    }
+    let parent = decode_header(chain, parent.bytes()?)?;
+    let headers = headers
+        .iter()
+        .map(|header| {
+            let data = header.bytes()?;
+            // the sealing hash hashes the header once more, and its signer is recovered
+            chain.charge_cycles(Operation::Keccak256(data.len()));
+            chain.charge_cycles(Operation::Secp256k1Recover);
+            decode_header(chain, data)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Some((parent, headers)))
+}
+
+fn decode_header<T: CkbChainInterface>(chain: &T, data: &[u8]) -> Result<Header, Error> {
+    chain.charge_cycles(Operation::Keccak256(data.len()));
+    Header::decode(data)
+}
+
+/// Check that the created light-client cell is the state of the submitted checkpoint: the
+/// checkpoint header with its total difficulty, and the dataset root and items of its epoch
+fn verify_checkpoint<T: CkbChainInterface>(
+    chain: &T,
+    ring_size: usize,
+    output: &LightClientState,
+) -> Result<(), Error> {
+    let checkpoint = load_output_type(chain)?.ok_or(Error::InvalidCheckpoint)?;
+    let items = rlp::decode(&checkpoint)?.list()?;
+    if items.len() != 4 {
+        return Err(Error::Encoding);
+    }
+    let header = decode_header(chain, items[0].bytes()?)?;
+    let total_difficulty = items[1].u128()?;
+    let mut roots = [[0u8; 32]; ROOT_SLOTS];
+    items[2].fixed(&mut roots[0])?;
+    let mut dataset_items = [0u64; ROOT_SLOTS];
+    dataset_items[0] = items[3].u64()?;
+    if total_difficulty < header.difficulty {
+        return Err(Error::InvalidCheckpoint);
+    }
+    let state = LightClientState {
+        headers: HeaderChain::new(HeaderRecord::new(&header), total_difficulty)
+            .with_slots(ring_size),
+        roots: DatasetRoots {
+            first_epoch: ethash::epoch(header.number),
+            roots,
+            items: dataset_items,
+        },
+    };
+    state.roots.verify_items()?;
+    if &state != output {
+        return Err(Error::InvalidCheckpoint);
+    }
+    debug!(chain, "checkpoint {}: {:?}", header.number, &header.hash);
+    Ok(())
+}
+
+/// Check that the created light-client cell in clique mode is the state of the submitted
+/// checkpoint, the RLP list `[header, total_difficulty]` where `header` is the RLP encoded
+/// header of a checkpoint block as a byte string. The snapshot starts from the signers the
+/// header lists, without recents nor votes.
+fn verify_clique_checkpoint<T: CkbChainInterface>(
+    chain: &T,
+    config: &CliqueConfig,
+    ring_size: usize,
+    output: &CliqueClientState,
+) -> Result<(), Error> {
+    let checkpoint = load_output_type(chain)?.ok_or(Error::InvalidCheckpoint)?;
+    let items = rlp::decode(&checkpoint)?.list()?;
+    if items.len() != 2 {
+        return Err(Error::Encoding);
+    }
+    let header = decode_header(chain, items[0].bytes()?)?;
+    let total_difficulty = items[1].u128()?;
+    if header.number % config.epoch != 0 || total_difficulty < header.difficulty {
+        return Err(Error::InvalidCheckpoint);
+    }
+    let state = CliqueClientState {
+        headers: HeaderChain::new(HeaderRecord::new(&header), total_difficulty)
+            .with_slots(ring_size),
+        snapshot: clique::Snapshot::new(clique::checkpoint_signers(&header)?)?,
+        seals: [clique::Seal::default()].to_vec(),
+    };
+    if &state != output {
+        return Err(Error::InvalidCheckpoint);
+    }
+    debug!(chain, "checkpoint {}: {:?}", header.number, &header.hash);
+    Ok(())
+}
+
+/// Check that the created light-client cell in parlia mode is the state of the submitted
+/// checkpoint, the RLP list `[header, total_difficulty, [validator, ...], turn_length]` where
+/// `header` is the RLP encoded epoch header as a byte string, and the validators are the
+/// addresses of the ones which seal it, sorted, with their turn length. The snapshot starts
+/// from them, without recents, and applies the header.
+fn verify_parlia_checkpoint<T: CkbChainInterface>(
+    chain: &T,
+    config: &ParliaConfig,
+    ring_size: usize,
+    output: &ParliaClientState,
+) -> Result<(), Error> {
+    let checkpoint = load_output_type(chain)?.ok_or(Error::InvalidCheckpoint)?;
+    let items = rlp::decode(&checkpoint)?.list()?;
+    if items.len() != 4 {
+        return Err(Error::Encoding);
+    }
+    let header = decode_header(chain, items[0].bytes()?)?;
+    let total_difficulty = items[1].u128()?;
+    let validators = items[2]
+        .list()?
+        .iter()
+        .map(|item| {
+            let mut validator = [0u8; 20];
+            item.fixed(&mut validator)?;
+            Ok(validator)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let turn_length = items[3].u64()?;
+    if turn_length > u8::MAX as u64 {
+        return Err(Error::Encoding);
+    }
+    if header.number % config.epoch != 0 || total_difficulty < header.difficulty {
+        return Err(Error::InvalidCheckpoint);
+    }
+    let validators = parlia::ValidatorSet::new(validators, turn_length as u8)?;
+    let mut snapshot = parlia::Snapshot::new(validators);
+    chain.charge_cycles(Operation::Secp256k1Recover);
+    let signer = parlia::recover_signer(config, &header)?;
+    if signer != header.coinbase {
+        return Err(Error::InvalidSeal);
+    }
+    snapshot.verify_signer(&signer)?;
+    let listed = parlia::parse_validators(config, &header)?;
+    snapshot.apply(config, header.number, signer, Some(listed));
+    let state = ParliaClientState {
+        headers: HeaderChain::new(HeaderRecord::new(&header), total_difficulty)
+            .with_slots(ring_size),
+        snapshot,
+        signers: [signer].to_vec(),
+        listed: None,
+    };
+    if &state != output {
+        return Err(Error::InvalidCheckpoint);
+    }
+    debug!(chain, "checkpoint {}: {:?}", header.number, &header.hash);
+    Ok(())
+}
+
+/// Check that `type_id` is the blake2b hash of the first input of the transaction and of the
+/// index of the created cell in the outputs, as the type id of ckb. The input can only be
+/// consumed once, so a single cell is ever created with the script.
+fn verify_type_id<T: CkbChainInterface>(chain: &T, type_id: &[u8; 32]) -> Result<(), Error> {
+    let script_hash = chain.load_script_hash()?;
+    let mut index = 0;
+    while chain.load_cell_type_hash(index, Source::Output)? != Some(script_hash) {
+        index += 1;
+    }
+    let mut data = chain.load_input(0, Source::Input)?.as_slice().to_vec();
+    data.extend_from_slice(&(index as u64).to_le_bytes());
+    if &hash::blake2b_256(&data) != type_id {
+        return Err(Error::InvalidTypeId);
+    }
+    Ok(())
+}
+
+/// Check that an input is locked by the owner whose lock hash is `owner`
+fn verify_owner<T: CkbChainInterface>(chain: &T, owner: &[u8; 32]) -> Result<(), Error> {
+    for index in 0.. {
+        match chain.load_cell_lock_hash(index, Source::Input) {
+            Ok(lock_hash) if &lock_hash == owner => return Ok(()),
+            Ok(_) => {}
+            Err(SysError::IndexOutOfBound) => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(Error::Unauthorized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EthLightClientTypescript, MAX_BRANCH_HEADERS, MAX_SEALED_HEADERS};
+    use crate::clique::{Seal, Snapshot};
+    use crate::config::{Config, Consensus, ForkSchedule, ParliaConfig};
+    use crate::difficulty;
+    use crate::error::Error;
+    use crate::ethash::{self, DagProof};
+    use crate::hash::keccak256;
+    use crate::header::Header;
+    use crate::parlia::{self, ValidatorSet};
+    use crate::rlp;
+    use crate::roots::{DatasetRoots, ROOT_SLOTS};
+    use crate::state::{
+        CliqueClientState, HeaderChain, HeaderRecord, LightClientState, ParliaClientState,
+        HEADER_SLOTS,
+    };
+    use crate::test_data::*;
+    use ckb_env::mock::{blake2b_256, MockCKBChain, MockCell, ScriptGroupType};
+    use ckb_env::traits::ContractInterface;
+    use ckb_std::ckb_types::{packed::*, prelude::*};
+
+    const MAX_CYCLES: u64 = 70_000_000;
+
+    /// Encode a branch of `headers` on top of `parent`, each with the proofs of its work
+    fn encode_branch(parent: &Header, headers: &[(&Header, &[DagProof])]) -> Vec<u8> {
+        let mut items = Vec::new();
+        rlp::encode_bytes(&mut items, &parent.encode());
+        for (header, proofs) in headers {
+            let mut encoded_proofs = Vec::new();
+            for proof in proofs.iter() {
+                let mut siblings = Vec::new();
+                for sibling in &proof.branch {
+                    rlp::encode_bytes(&mut siblings, sibling);
+                }
+                let mut fields = Vec::new();
+                rlp::encode_bytes(&mut fields, &proof.item);
+                rlp::encode_list(&mut fields, &siblings);
+                rlp::encode_list(&mut encoded_proofs, &fields);
+            }
+            let mut fields = Vec::new();
+            rlp::encode_bytes(&mut fields, &header.encode());
+            rlp::encode_list(&mut fields, &encoded_proofs);
+            rlp::encode_list(&mut items, &fields);
+        }
+        let mut branch = Vec::new();
+        rlp::encode_list(&mut branch, &items);
+        branch
+    }
+
+    /// Encode a branch of `MAINNET_BLOCK_1` with its proofs, returns it together with the
+    /// dataset root of epoch 0
+    fn block_1_branch() -> (Vec<u8>, [u8; 32]) {
+        let (root, proofs) = dag_proofs(&MAINNET_BLOCK_1_DAG_ITEMS, 0);
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        (encode_branch(&genesis, &[(&block_1, &proofs[..])]), root)
+    }
+
+    /// State of a light client started from the mainnet genesis
+    fn genesis_state(first_epoch: u64, known: &[[u8; 32]]) -> LightClientState {
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let mut roots = [[0u8; 32]; ROOT_SLOTS];
+        roots[..known.len()].copy_from_slice(known);
+        let mut items = [0u64; ROOT_SLOTS];
+        for (slot, items) in items[..known.len()].iter_mut().enumerate() {
+            *items = ethash::dataset_items(first_epoch + slot as u64);
+        }
+        LightClientState {
+            headers: HeaderChain::new(HeaderRecord::new(&genesis), genesis.difficulty),
+            roots: DatasetRoots {
+                first_epoch,
+                roots,
+                items,
+            },
+        }
+    }
+
+    /// `state` with `MAINNET_BLOCK_1` appended
+    fn with_block_1(mut state: LightClientState) -> LightClientState {
+        let block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        state.headers.push(&block_1).unwrap();
+        state
+    }
+
+    fn owner_lock() -> Script {
+        Script::new_builder().args(vec![1u8].pack()).build()
+    }
+
+    /// The `index`th input of the transactions of the tests
+    fn cell_input(index: u32) -> CellInput {
+        let out_point = OutPoint::new_builder()
+            .tx_hash([3u8; 32].pack())
+            .index(index.pack())
+            .build();
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+
+    /// Type id of the light-client cell, created as the first output of a transaction
+    fn type_id() -> [u8; 32] {
+        let mut data = cell_input(0).as_slice().to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes());
+        blake2b_256(&data)
+    }
+
+    /// Encode the checkpoint of `header` with its total difficulty and the dataset root of its
+    /// epoch
+    fn encode_checkpoint(header: &Header, total_difficulty: u128, root: &[u8; 32]) -> Vec<u8> {
+        let mut items = Vec::new();
+        rlp::encode_bytes(&mut items, &header.encode());
+        rlp::encode_u128(&mut items, total_difficulty);
+        rlp::encode_bytes(&mut items, root);
+        rlp::encode_u64(
+            &mut items,
+            ethash::dataset_items(ethash::epoch(header.number)),
+        );
+        let mut checkpoint = Vec::new();
+        rlp::encode_list(&mut checkpoint, &items);
+        checkpoint
+    }
+
+    /// A transaction updating the light-client cell from `input` to `output` with the submitted
+    /// `branch`, or checkpoint when it creates the cell, with an input of the owner when `owner`
+    /// is set
+    fn chain_with_update(
+        input: Option<LightClientState>,
+        output: Option<LightClientState>,
+        branch: Option<Vec<u8>>,
+        owner: bool,
+    ) -> MockCKBChain {
+        chain_with_cells(
+            Consensus::Ethash(ForkSchedule::MAINNET),
+            input.map(|input| input.encode()),
+            output.map(|output| output.encode()),
+            branch,
+            owner,
+        )
+    }
+
+    /// A transaction updating the light-client cell of `consensus` from the `input` data to the
+    /// `output` data with the `witness`, as `chain_with_update`
+    fn chain_with_cells(
+        consensus: Consensus,
+        input: Option<Vec<u8>>,
+        output: Option<Vec<u8>>,
+        witness: Option<Vec<u8>>,
+        owner: bool,
+    ) -> MockCKBChain {
+        chain_with_config(config_of(consensus), input, output, witness, owner)
+    }
+
+    /// The config of a cell of the owner following the `consensus`, keeping `HEADER_SLOTS`
+    /// headers
+    fn config_of(consensus: Consensus) -> Config {
+        let chain_id = match &consensus {
+            Consensus::Parlia(parlia) => parlia.chain_id,
+            Consensus::Clique(_) => 5,
+            _ => 1,
+        };
+        Config {
+            owner_lock_hash: blake2b_256(owner_lock().as_slice()),
+            type_id: type_id(),
+            chain_id,
+            consensus,
+            confirmations: 0,
+            ring_size: HEADER_SLOTS,
+        }
+    }
+
+    fn chain_with_config(
+        config: Config,
+        input: Option<Vec<u8>>,
+        output: Option<Vec<u8>>,
+        witness: Option<Vec<u8>>,
+        owner: bool,
+    ) -> MockCKBChain {
+        let type_script = Script::new_builder().args(config.encode().pack()).build();
+        let cell_output = CellOutput::new_builder()
+            .capacity(100_000u64.pack())
+            .type_(
+                ScriptOpt::new_builder()
+                    .set(Some(type_script.clone()))
+                    .build(),
+            )
+            .build();
+
+        let mut inputs = Vec::new();
+        if let Some(input) = input {
+            inputs.push(MockCell::new(cell_output.clone(), input));
+        }
+        if owner {
+            let output = CellOutput::new_builder().lock(owner_lock()).build();
+            inputs.push(MockCell::new(output, Vec::new()));
+        }
+        let mut outputs = CellOutputVec::new_builder();
+        let mut outputs_data = BytesVec::new_builder();
+        if let Some(output) = output {
+            outputs = outputs.push(cell_output);
+            outputs_data = outputs_data.push(output.pack());
+        }
+        let witness = WitnessArgs::new_builder()
+            .output_type(
+                BytesOpt::new_builder()
+                    .set(witness.map(|witness| witness.pack()))
+                    .build(),
+            )
+            .build();
+        let cell_inputs = (0..inputs.len() as u32).map(cell_input).collect::<Vec<_>>();
+        let transaction = Transaction::new_builder()
+            .raw(
+                RawTransaction::new_builder()
+                    .inputs(CellInputVec::new_builder().set(cell_inputs).build())
+                    .outputs(outputs.build())
+                    .outputs_data(outputs_data.build())
+                    .build(),
+            )
+            .witnesses(
+                BytesVec::new_builder()
+                    .push(witness.as_slice().to_vec().pack())
+                    .build(),
+            )
+            .build();
+        let mut chain = MockCKBChain {
+            transaction,
+            inputs,
+            ..Default::default()
+        };
+        chain.set_script(type_script, ScriptGroupType::Type);
+        chain
+    }
+
+    #[test]
+    fn test_submit_header() {
+        let (branch, root) = block_1_branch();
+        let input = genesis_state(0, &[root]);
+        let output = with_block_1(input.clone());
+        let chain = chain_with_update(Some(input), Some(output), Some(branch), false);
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+        // a full branch of such headers fits in a transaction
+        assert!(chain.cycles() * (MAX_BRANCH_HEADERS as u64) < MAX_CYCLES);
+        assert_eq!(
+            chain.debug_messages(),
+            vec![format!(
+                "header 1: {:?}",
+                &decode_hex(MAINNET_BLOCK_1_HASH)[..]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_submit_batch() {
+        let (root, proofs) = dag_proofs(&MAINNET_BLOCK_1_DAG_ITEMS, 0);
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        // block 2 follows the consensus rules, but the proofs are the ones of block 1
+        let mut block_2 = block_1.clone();
+        block_2.parent_hash = block_1.hash;
+        block_2.number = 2;
+        block_2.timestamp = block_1.timestamp + 10;
+        block_2.difficulty =
+            difficulty::difficulty(&ForkSchedule::MAINNET, &block_1, block_2.timestamp).unwrap();
+        block_2.hash = keccak256(&block_2.encode());
+        let input = genesis_state(0, &[root]);
+        let mut output = with_block_1(input.clone());
+        output.headers.push(&block_2).unwrap();
+
+        // every header of the branch is verified
+        let branch = encode_branch(
+            &genesis,
+            &[(&block_1, &proofs[..]), (&block_2, &proofs[..])],
+        );
+        let chain = chain_with_update(Some(input.clone()), Some(output), Some(branch), false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidDagProof as i8
+        );
+        assert_eq!(
+            chain.debug_messages(),
+            vec![format!("header 1: {:?}", block_1.hash)]
+        );
+
+        let submissions = vec![(&block_1, &proofs[..]); MAX_BRANCH_HEADERS + 1];
+        let branch = encode_branch(&genesis, &submissions);
+        let chain = chain_with_update(Some(input.clone()), Some(input), Some(branch), false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::TooManyHeaders as i8
+        );
+    }
+
+    #[test]
+    fn test_submit_invalid_successor() {
+        let (branch, root) = block_1_branch();
+        let input = genesis_state(0, &[root]);
+
+        // the header is not appended, or the branch is missing
+        let chain = chain_with_update(
+            Some(input.clone()),
+            Some(input.clone()),
+            Some(branch.clone()),
+            false,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidHeaderChain as i8
+        );
+        let output = with_block_1(input.clone());
+        let chain = chain_with_update(Some(input.clone()), Some(output.clone()), None, false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidHeaderChain as i8
+        );
+        // the total difficulty of the tip is forged
+        let mut forged = output.clone();
+        forged.headers.tip_total_difficulty += 1;
+        let chain = chain_with_update(
+            Some(input.clone()),
+            Some(forged),
+            Some(branch.clone()),
+            false,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidHeaderChain as i8
+        );
+        // the capacity of the cell changes
+        let mut chain = chain_with_update(Some(input), Some(output), Some(branch), false);
+        chain.inputs[0].output = chain.inputs[0]
+            .output
+            .clone()
+            .as_builder()
+            .capacity(100_001u64.pack())
+            .build();
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidCapacity as i8
+        );
+    }
+
+    #[test]
+    fn test_submit_invalid_difficulty() {
+        let (root, proofs) = dag_proofs(&MAINNET_BLOCK_1_DAG_ITEMS, 0);
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let input = genesis_state(0, &[root]);
+        let mut block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        block_1.difficulty = 131_072;
+        block_1.hash = keccak256(&block_1.encode());
+        let mut output = input.clone();
+        output.headers.push(&block_1).unwrap();
+        let branch = encode_branch(&genesis, &[(&block_1, &proofs[..])]);
+        let chain = chain_with_update(Some(input.clone()), Some(output), Some(branch), false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidDifficulty as i8
+        );
+
+        // the parent of the branch must be the kept header it forks from
+        let block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        let mut parent = genesis;
+        parent.timestamp += 1;
+        let branch = encode_branch(&parent, &[(&block_1, &proofs[..])]);
+        let output = with_block_1(input.clone());
+        let chain = chain_with_update(Some(input), Some(output), Some(branch), false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidHeaderChain as i8
+        );
+    }
+
+    #[test]
+    fn test_reorg() {
+        let (branch, root) = block_1_branch();
+        let genesis = genesis_state(0, &[root]);
+        // a lighter block 1 is the tip
+        let mut input = genesis.clone();
+        let mut block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        block_1.hash = [1u8; 32];
+        block_1.difficulty = 1;
+        input.headers.push(&block_1).unwrap();
+
+        let output = with_block_1(genesis.clone());
+        let chain = chain_with_update(
+            Some(input.clone()),
+            Some(output.clone()),
+            Some(branch.clone()),
+            false,
+        );
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+
+        // the same block is not heavier than the tip
+        let chain = chain_with_update(
+            Some(output.clone()),
+            Some(output),
+            Some(branch.clone()),
+            false,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::StaleBranch as i8
+        );
+        // the fork point is not kept
+        let mut input = genesis;
+        input.headers = HeaderChain::new(HeaderRecord::new(&block_1), 1);
+        let chain = chain_with_update(Some(input.clone()), Some(input), Some(branch), false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::UnknownParent as i8
+        );
+    }
+
+    #[test]
+    fn test_submit_without_dataset_root() {
+        let (branch, root) = block_1_branch();
+        for input in [genesis_state(0, &[]), genesis_state(1, &[root])] {
+            let output = with_block_1(input.clone());
+            let chain = chain_with_update(Some(input), Some(output), Some(branch.clone()), false);
+            assert_eq!(
+                EthLightClientTypescript::run(&chain),
+                Error::MissingDatasetRoot as i8
+            );
+        }
+    }
+
+    #[test]
+    fn test_submit_with_wrong_dataset_root() {
+        let (branch, mut root) = block_1_branch();
+        root[0] ^= 1;
+        let input = genesis_state(0, &[root]);
+        let output = with_block_1(input.clone());
+        let chain = chain_with_update(Some(input), Some(output), Some(branch), false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidDagProof as i8
+        );
+    }
+
+    #[test]
+    fn test_append_dataset_root() {
+        let (branch, root) = block_1_branch();
+        let input = genesis_state(0, &[root]);
+        let output = genesis_state(0, &[root, [2u8; 32]]);
+        let chain = chain_with_update(Some(input.clone()), Some(output.clone()), None, false);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::Unauthorized as i8
+        );
+        let chain = chain_with_update(
+            Some(input.clone()),
+            Some(with_block_1(output)),
+            Some(branch),
+            true,
+        );
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+
+        // known roots are immutable, even for the owner
+        let output = genesis_state(0, &[[2u8; 32]]);
+        let chain = chain_with_update(Some(input.clone()), Some(output), None, true);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidDatasetRoots as i8
+        );
+        // the window can not move past the oldest header, roots are at most
+        // `ROOT_SLOTS - 1` epochs ahead of it
+        let output = genesis_state(1, &[[2u8; 32]]);
+        let chain = chain_with_update(Some(input), Some(output), None, true);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidDatasetRoots as i8
+        );
+    }
+
+    #[test]
+    fn test_create_and_destroy() {
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let state = genesis_state(0, &[[1u8; 32]]);
+        let checkpoint = encode_checkpoint(&genesis, genesis.difficulty, &[1u8; 32]);
+        for &owner in &[false, true] {
+            let expected = if owner { 0 } else { Error::Unauthorized as i8 };
+            let chain =
+                chain_with_update(None, Some(state.clone()), Some(checkpoint.clone()), owner);
+            assert_eq!(EthLightClientTypescript::run(&chain), expected);
+            let chain = chain_with_update(Some(state.clone()), None, None, owner);
+            assert_eq!(EthLightClientTypescript::run(&chain), expected);
+        }
+        let chain = chain_with_update(None, Some(state), Some(checkpoint), true);
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+        assert_eq!(
+            chain.debug_messages(),
+            vec![format!("checkpoint 0: {:?}", genesis.hash)]
+        );
+    }
+
+    #[test]
+    fn test_create_from_invalid_checkpoint() {
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let state = genesis_state(0, &[[1u8; 32]]);
+        let checkpoints = vec![
+            encode_checkpoint(&genesis, genesis.difficulty - 1, &[1u8; 32]),
+            encode_checkpoint(&genesis, genesis.difficulty + 1, &[1u8; 32]),
+            encode_checkpoint(&genesis, genesis.difficulty, &[2u8; 32]),
+        ];
+        for checkpoint in checkpoints {
+            let chain = chain_with_update(None, Some(state.clone()), Some(checkpoint), true);
+            assert_eq!(
+                EthLightClientTypescript::run(&chain),
+                Error::InvalidCheckpoint as i8
+            );
+        }
+        let chain = chain_with_update(None, Some(state.clone()), None, true);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidCheckpoint as i8
+        );
+
+        // the number of items of the dataset is checked
+        let mut wrong_items = state.clone();
+        wrong_items.roots.items[0] -= 1;
+        let mut items = Vec::new();
+        rlp::encode_bytes(&mut items, &genesis.encode());
+        rlp::encode_u128(&mut items, genesis.difficulty);
+        rlp::encode_bytes(&mut items, &[1u8; 32]);
+        rlp::encode_u64(&mut items, wrong_items.roots.items[0]);
+        let mut checkpoint = Vec::new();
+        rlp::encode_list(&mut checkpoint, &items);
+        let chain = chain_with_update(None, Some(wrong_items), Some(checkpoint), true);
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidDatasetRoots as i8
+        );
+
+        // the cell is created once, the first input of the transaction creating it is spent
+        let checkpoint = encode_checkpoint(&genesis, genesis.difficulty, &[1u8; 32]);
+        let mut chain = chain_with_update(None, Some(state), Some(checkpoint), true);
+        let raw = chain
+            .transaction
+            .raw()
+            .as_builder()
+            .inputs(CellInputVec::new_builder().push(cell_input(1)).build())
+            .build();
+        chain.transaction = chain.transaction.as_builder().raw(raw).build();
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidTypeId as i8
+        );
+    }
+
+    #[test]
+    fn test_create_with_ring_size() {
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let checkpoint = encode_checkpoint(&genesis, genesis.difficulty, &[1u8; 32]);
+        let config = Config {
+            ring_size: 4,
+            ..config_of(Consensus::Ethash(ForkSchedule::MAINNET))
+        };
+        let mut state = genesis_state(0, &[[1u8; 32]]);
+        let chain = chain_with_config(
+            config.clone(),
+            None,
+            Some(state.encode()),
+            Some(checkpoint.clone()),
+            true,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidCheckpoint as i8
+        );
+        state.headers = state.headers.with_slots(4);
+        let chain = chain_with_config(config, None, Some(state.encode()), Some(checkpoint), true);
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+    }
+
+    /// A clique state started from the checkpoint `header` of the `signers`
+    fn clique_state(header: &Header, signers: &[Signer]) -> CliqueClientState {
+        CliqueClientState {
+            headers: HeaderChain::new(HeaderRecord::new(header), header.difficulty),
+            snapshot: Snapshot::new(addresses(signers)).unwrap(),
+            seals: vec![Seal::default()],
+        }
+    }
+
+    fn encode_sealed_branch(parent: &Header, headers: &[Header]) -> Vec<u8> {
+        let mut items = Vec::new();
+        rlp::encode_bytes(&mut items, &parent.encode());
+        for header in headers {
+            rlp::encode_bytes(&mut items, &header.encode());
+        }
+        let mut branch = Vec::new();
+        rlp::encode_list(&mut branch, &items);
+        branch
+    }
+
+    #[test]
+    fn test_clique_update() {
+        let signers = new_signers(3);
+        let headers = clique_headers(&signers, 3);
+        let input = clique_state(&headers[0], &signers);
+        let mut output = input.clone();
+        output.headers.apply_branch(&headers[1..]).unwrap();
+        for header in &headers[1..] {
+            output.seals.push(Seal {
+                signer: crate::clique::recover_signer(header).unwrap(),
+                ..Default::default()
+            });
+        }
+        let branch = encode_sealed_branch(&headers[0], &headers[1..]);
+        let clique = Consensus::Clique(CLIQUE);
+        let chain = chain_with_cells(
+            clique.clone(),
+            Some(input.encode()),
+            Some(output.encode()),
+            Some(branch.clone()),
+            false,
+        );
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+        // a full branch of such headers fits by the estimates of the cost model, the tests crate
+        // checks it in ckb-vm
+        assert!(chain.cycles() / 2 * (MAX_SEALED_HEADERS as u64) < MAX_CYCLES);
+        assert_eq!(
+            chain.debug_messages(),
+            headers[1..]
+                .iter()
+                .map(|header| format!("header {}: {:?}", header.number, header.hash))
+                .collect::<Vec<_>>()
+        );
+
+        // the output is not the updated state
+        let mut forged = output.clone();
+        forged.seals[2].authorize = true;
+        for (forged, expected) in [
+            (forged, Error::InvalidSigners),
+            (input.clone(), Error::InvalidHeaderChain),
+        ] {
+            let chain = chain_with_cells(
+                clique.clone(),
+                Some(input.encode()),
+                Some(forged.encode()),
+                Some(branch.clone()),
+                false,
+            );
+            assert_eq!(EthLightClientTypescript::run(&chain), expected as i8);
+        }
+        // a header sealed by an outsider
+        let outsider = new_signers(4)
+            .into_iter()
+            .find(|signer| !addresses(&signers).contains(&signer.address))
+            .unwrap();
+        let header = clique_child(&headers[0], &outsider, false, &signers);
+        let chain = chain_with_cells(
+            clique.clone(),
+            Some(input.encode()),
+            Some(output.encode()),
+            Some(encode_sealed_branch(&headers[0], &[header])),
+            false,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::UnauthorizedSigner as i8
+        );
+        // too many headers
+        let headers = vec![headers[1].clone(); MAX_SEALED_HEADERS + 1];
+        let chain = chain_with_cells(
+            clique,
+            Some(input.encode()),
+            Some(output.encode()),
+            Some(encode_sealed_branch(&headers[0], &headers)),
+            false,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::TooManyHeaders as i8
+        );
+    }
+
+    #[test]
+    fn test_create_clique_checkpoint() {
+        let signers = new_signers(3);
+        let headers = clique_headers(&signers, 2);
+        let state = clique_state(&headers[0], &signers);
+        let clique = Consensus::Clique(CLIQUE);
+        let encode_checkpoint = |header: &Header, total_difficulty: u128| {
+            let mut items = Vec::new();
+            rlp::encode_bytes(&mut items, &header.encode());
+            rlp::encode_u128(&mut items, total_difficulty);
+            let mut checkpoint = Vec::new();
+            rlp::encode_list(&mut checkpoint, &items);
+            checkpoint
+        };
+        let checkpoint = encode_checkpoint(&headers[0], headers[0].difficulty);
+        let chain = chain_with_cells(
+            clique.clone(),
+            None,
+            Some(state.encode()),
+            Some(checkpoint.clone()),
+            true,
+        );
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+        assert_eq!(
+            chain.debug_messages(),
+            vec![format!("checkpoint 0: {:?}", headers[0].hash)]
+        );
+
+        // the state is not the one of the checkpoint, or the header is not a checkpoint
+        let mut other = state.clone();
+        other.snapshot = Snapshot::new(addresses(&signers[..2])).unwrap();
+        let cases = vec![
+            (other, checkpoint),
+            (
+                clique_state(&headers[1], &signers),
+                encode_checkpoint(&headers[1], headers[1].difficulty),
+            ),
+            (state, encode_checkpoint(&headers[0], 0)),
+        ];
+        for (output, checkpoint) in cases {
+            let chain = chain_with_cells(
+                clique.clone(),
+                None,
+                Some(output.encode()),
+                Some(checkpoint),
+                true,
+            );
+            assert_eq!(
+                EthLightClientTypescript::run(&chain),
+                Error::InvalidCheckpoint as i8
+            );
+        }
+    }
+
+    /// A parlia state started from the epoch header 0 of `parlia_headers`
+    fn parlia_state(header: &Header, validators: &[Signer]) -> ParliaClientState {
+        let set = ValidatorSet::new(addresses(validators), 1).unwrap();
+        let mut snapshot = parlia::Snapshot::new(set.clone());
+        snapshot.apply(&PARLIA, header.number, header.coinbase, Some(set));
+        ParliaClientState {
+            headers: HeaderChain::new(HeaderRecord::new(header), header.difficulty),
+            snapshot,
+            signers: vec![header.coinbase],
+            listed: None,
+        }
+    }
+
+    #[test]
+    fn test_parlia_update() {
+        let validators = new_signers(3);
+        let headers = parlia_headers(&validators, &validators, 3);
+        let input = parlia_state(&headers[0], &validators);
+        let mut output = input.clone();
+        output.headers.apply_branch(&headers[1..]).unwrap();
+        for header in &headers[1..] {
+            output.signers.push(header.coinbase);
+        }
+        let branch = encode_sealed_branch(&headers[0], &headers[1..]);
+        let parlia = Consensus::Parlia(PARLIA);
+        let chain = chain_with_cells(
+            parlia.clone(),
+            Some(input.encode()),
+            Some(output.encode()),
+            Some(branch.clone()),
+            false,
+        );
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+        assert_eq!(
+            chain.debug_messages(),
+            headers[1..]
+                .iter()
+                .map(|header| format!("header {}: {:?}", header.number, header.hash))
+                .collect::<Vec<_>>()
+        );
+
+        // the output is not the updated state
+        let mut forged = output.clone();
+        forged.signers.swap(1, 2);
+        for (forged, expected) in [
+            (forged, Error::InvalidValidators),
+            (input.clone(), Error::InvalidHeaderChain),
+        ] {
+            let chain = chain_with_cells(
+                parlia.clone(),
+                Some(input.encode()),
+                Some(forged.encode()),
+                Some(branch.clone()),
+                false,
+            );
+            assert_eq!(EthLightClientTypescript::run(&chain), expected as i8);
+        }
+        // the headers are sealed for another chain
+        let config = ParliaConfig {
+            chain_id: 97,
+            ..PARLIA
+        };
+        let chain = chain_with_cells(
+            Consensus::Parlia(config),
+            Some(input.encode()),
+            Some(output.encode()),
+            Some(branch),
+            false,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::InvalidSeal as i8
+        );
+    }
+
+    #[test]
+    fn test_create_parlia_checkpoint() {
+        let validators = new_signers(3);
+        let headers = parlia_headers(&validators, &validators, 2);
+        let state = parlia_state(&headers[0], &validators);
+        let parlia = Consensus::Parlia(PARLIA);
+        let encode_checkpoint = |header: &Header, validators: &[Signer], turn_length: u64| {
+            let mut items = Vec::new();
+            rlp::encode_bytes(&mut items, &header.encode());
+            rlp::encode_u128(&mut items, header.difficulty);
+            let mut list = Vec::new();
+            for validator in validators {
+                rlp::encode_bytes(&mut list, &validator.address);
+            }
+            rlp::encode_list(&mut items, &list);
+            rlp::encode_u64(&mut items, turn_length);
+            let mut checkpoint = Vec::new();
+            rlp::encode_list(&mut checkpoint, &items);
+            checkpoint
+        };
+        let checkpoint = encode_checkpoint(&headers[0], &validators, 1);
+        let chain = chain_with_cells(
+            parlia.clone(),
+            None,
+            Some(state.encode()),
+            Some(checkpoint.clone()),
+            true,
+        );
+        assert_eq!(EthLightClientTypescript::run(&chain), 0);
+        assert_eq!(
+            chain.debug_messages(),
+            vec![format!("checkpoint 0: {:?}", headers[0].hash)]
+        );
+
+        // the state is not the one of the checkpoint, or the header is not an epoch header
+        let mut other = state.clone();
+        other.snapshot.recents.clear();
+        let cases = vec![
+            (other, checkpoint),
+            (
+                state.clone(),
+                encode_checkpoint(&headers[0], &validators, 2),
+            ),
+            (
+                parlia_state(&headers[1], &validators),
+                encode_checkpoint(&headers[1], &validators, 1),
+            ),
+        ];
+        for (output, checkpoint) in cases {
+            let chain = chain_with_cells(
+                parlia.clone(),
+                None,
+                Some(output.encode()),
+                Some(checkpoint),
+                true,
+            );
+            assert_eq!(
+                EthLightClientTypescript::run(&chain),
+                Error::InvalidCheckpoint as i8
+            );
+        }
+        // the header is not sealed by one of the validators
+        let chain = chain_with_cells(
+            parlia,
+            None,
+            Some(state.encode()),
+            Some(encode_checkpoint(&headers[0], &validators[1..], 1)),
+            true,
+        );
+        assert_eq!(
+            EthLightClientTypescript::run(&chain),
+            Error::UnauthorizedSigner as i8
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Merkle mountain range of the headers which left the ring buffer of the light-client cell.
+//!
+//! A header leaves the ring buffer once `state::HEADER_SLOTS` headers are on top of it.
+//! Branches fork from kept headers only, so such a header is final and the range only grows.
+//! The leaves are the hashes of these headers from the oldest one, and a node is the keccak256
+//! of its two children. The range is a list of perfect trees, one for each bit set in the
+//! number of leaves from the highest tree. The cell keeps their roots, the peaks, so an
+//! inclusion proof is the path from a leaf to its peak, relative to the current number of
+//! leaves.
+
+use crate::error::Error;
+use crate::hash::keccak256;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Peaks kept in the cell, the range holds less than `2 ^ MMR_PEAKS` leaves
+pub const MMR_PEAKS: usize = 32;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MerkleMountainRange {
+    leaves: u64,
+    /// Roots of the trees, from the highest one
+    peaks: Vec<[u8; 32]>,
+}
+
+impl MerkleMountainRange {
+    /// Bytes of the encoded range: the number of leaves as a little endian u64, and the peaks
+    /// followed by zeros up to `MMR_PEAKS`
+    pub const SIZE: usize = 8 + 32 * MMR_PEAKS;
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&data[..8]);
+        let leaves = u64::from_le_bytes(bytes);
+        if leaves >> MMR_PEAKS != 0 {
+            return Err(Error::Encoding);
+        }
+        let peaks = data[8..]
+            .chunks(32)
+            .take(leaves.count_ones() as usize)
+            .map(|chunk| {
+                let mut peak = [0u8; 32];
+                peak.copy_from_slice(chunk);
+                peak
+            })
+            .collect();
+        Ok(MerkleMountainRange { leaves, peaks })
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.leaves.to_le_bytes());
+        for peak in &self.peaks {
+            out.extend_from_slice(peak);
+        }
+        out.resize(out.len() + 32 * (MMR_PEAKS - self.peaks.len()), 0);
+    }
+
+    pub fn leaves(&self) -> u64 {
+        self.leaves
+    }
+
+    pub fn peaks(&self) -> &[[u8; 32]] {
+        &self.peaks
+    }
+
+    /// Append `leaf`, merging the trees of the same height
+    pub fn push(&mut self, leaf: [u8; 32]) -> Result<(), Error> {
+        if (self.leaves + 1) >> MMR_PEAKS != 0 {
+            return Err(Error::InvalidHeaderChain);
+        }
+        let mut node = leaf;
+        let mut leaves = self.leaves;
+        while leaves & 1 == 1 {
+            let left = self.peaks.pop().ok_or(Error::Encoding)?;
+            node = merge(&left, &node);
+            leaves >>= 1;
+        }
+        self.peaks.push(node);
+        self.leaves += 1;
+        Ok(())
+    }
+
+    /// Root of the range, the peaks bagged from the lowest one, zeros when it is empty
+    pub fn root(&self) -> [u8; 32] {
+        let mut peaks = self.peaks.iter().rev();
+        let lowest = match peaks.next() {
+            Some(peak) => *peak,
+            None => return [0u8; 32],
+        };
+        peaks.fold(lowest, |root, peak| merge(peak, &root))
+    }
+
+    /// Check that `leaf` is the `index`th leaf, `siblings` being the siblings of the path
+    /// from the leaf to its peak, from the lowest one
+    pub fn verify(&self, index: u64, leaf: &[u8; 32], siblings: &[[u8; 32]]) -> Result<(), Error> {
+        if index >= self.leaves {
+            return Err(Error::InvalidMmrProof);
+        }
+        // find the tree of the leaf, trees cover `2 ^ height` leaves from the highest one
+        let mut start = 0;
+        let mut peak = 0;
+        let mut height = 64 - self.leaves.leading_zeros() as usize;
+        loop {
+            height -= 1;
+            if (self.leaves >> height) & 1 == 0 {
+                continue;
+            }
+            if index < start + (1 << height) {
+                break;
+            }
+            start += 1 << height;
+            peak += 1;
+        }
+        if siblings.len() != height {
+            return Err(Error::InvalidMmrProof);
+        }
+        let offset = index - start;
+        let mut node = *leaf;
+        for (level, sibling) in siblings.iter().enumerate() {
+            node = if (offset >> level) & 1 == 0 {
+                merge(&node, sibling)
+            } else {
+                merge(sibling, &node)
+            };
+        }
+        if node != self.peaks[peak] {
+            return Err(Error::InvalidMmrProof);
+        }
+        Ok(())
+    }
+}
+
+fn merge(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(left);
+    data[32..].copy_from_slice(right);
+    keccak256(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(index: u64) -> [u8; 32] {
+        keccak256(&index.to_le_bytes())
+    }
+
+    fn range_of(leaves: u64) -> MerkleMountainRange {
+        let mut range = MerkleMountainRange::default();
+        for index in 0..leaves {
+            range.push(leaf(index)).unwrap();
+        }
+        range
+    }
+
+    /// Siblings of the path of the `index`th leaf of the tree over `leaves`
+    fn path(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+        let mut siblings = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut index = index;
+        while level.len() > 1 {
+            siblings.push(level[index ^ 1]);
+            level = level
+                .chunks(2)
+                .map(|pair| merge(&pair[0], &pair[1]))
+                .collect();
+            index /= 2;
+        }
+        siblings
+    }
+
+    #[test]
+    fn test_push_leaves() {
+        let range = range_of(7);
+        assert_eq!(range.leaves(), 7);
+        let leaves: Vec<_> = (0..7).map(leaf).collect();
+        let tree_4 = merge(
+            &merge(&leaves[0], &leaves[1]),
+            &merge(&leaves[2], &leaves[3]),
+        );
+        let tree_2 = merge(&leaves[4], &leaves[5]);
+        assert_eq!(range.peaks(), &[tree_4, tree_2, leaves[6]]);
+        assert_eq!(range.root(), merge(&tree_4, &merge(&tree_2, &leaves[6])));
+        // the 8th leaf merges the three trees
+        let tree_8 = merge(&tree_4, &merge(&tree_2, &merge(&leaves[6], &leaf(7))));
+        assert_eq!(range_of(8).peaks(), &[tree_8]);
+        assert_eq!(range_of(8).root(), tree_8);
+        assert_eq!(MerkleMountainRange::default().root(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_encode_range() {
+        let range = range_of(5);
+        let mut data = Vec::new();
+        range.encode(&mut data);
+        assert_eq!(data.len(), MerkleMountainRange::SIZE);
+        assert_eq!(MerkleMountainRange::decode(&data).unwrap(), range);
+        assert_eq!(
+            MerkleMountainRange::decode(&data[1..]),
+            Err(Error::Encoding)
+        );
+        data[..8].copy_from_slice(&(1u64 << MMR_PEAKS).to_le_bytes());
+        assert_eq!(MerkleMountainRange::decode(&data), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let leaves: Vec<_> = (0..13).map(leaf).collect();
+        let range = range_of(13);
+        // trees of 8, 4 and 1 leaves
+        for (index, leaf) in leaves.iter().enumerate() {
+            let siblings = match index {
+                0..=7 => path(&leaves[..8], index),
+                8..=11 => path(&leaves[8..12], index - 8),
+                _ => Vec::new(),
+            };
+            range.verify(index as u64, leaf, &siblings).unwrap();
+            assert_eq!(
+                range.verify(index as u64, &[0u8; 32], &siblings),
+                Err(Error::InvalidMmrProof)
+            );
+        }
+        let siblings = path(&leaves[..8], 3);
+        assert_eq!(
+            range.verify(2, &leaves[3], &siblings),
+            Err(Error::InvalidMmrProof)
+        );
+        assert_eq!(
+            range.verify(3, &leaves[3], &siblings[..2]),
+            Err(Error::InvalidMmrProof)
+        );
+        assert_eq!(
+            range.verify(13, &leaves[3], &[]),
+            Err(Error::InvalidMmrProof)
+        );
+    }
+}
@@ -0,0 +1,743 @@
+//! Parlia proof of staked authority, as the parlia engine of bsc, which BNB Smart Chain runs.
+//!
+//! A header is sealed by one of the validators of the snapshot of its parent, the address in
+//! its coinbase: the last `EXTRA_SEAL` bytes of its extra data are the recoverable secp256k1
+//! signature of its sealing hash, which commits to the chain id. The validator in turn seals
+//! with difficulty 2, the others with difficulty 1. From Bohr the validator in turn seals
+//! `turn_length` consecutive headers, and a validator seals at most `turn_length` of the
+//! latest `ValidatorSet::check_len` headers. Epoch headers, every `epoch` blocks, list the next
+//! validators in their extra data, which take over `check_len` blocks later.
+//!
+//! The vote attestations of fast finality, which headers from Luban carry in their extra data,
+//! are not verified, the cell follows the heaviest chain of seals as in clique mode. The block
+//! time and the back off of the validators out of turn are not checked either.
+//!
+//! The signature recovery is charged as `ckb_env::cycles::Operation::Secp256k1Recover` when
+//! the branch is loaded, as in clique mode, and when the epoch header a cell starts from is
+//! verified.
+
+use crate::clique::{ecrecover, EXTRA_SEAL, EXTRA_VANITY};
+use crate::config::ParliaConfig;
+use crate::consensus::{MAX_GAS_LIMIT, MIN_GAS_LIMIT};
+use crate::difficulty::EMPTY_UNCLES_HASH;
+use crate::error::Error;
+use crate::hash::keccak256;
+use crate::header::Header;
+use crate::rlp;
+use crate::state::ParliaClientState;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Validators a set holds at most
+pub const MAX_VALIDATORS: usize = 64;
+/// Recent signers a snapshot holds at most, which bounds the `check_len` of its validators
+pub const MAX_RECENTS: usize = 512;
+/// The gas limit moves by less than `1 / GAS_LIMIT_BOUND_DIVISOR` of the parent one per block
+pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 256;
+/// Bytes of a validator in an epoch header from Luban, its address and its BLS public key
+pub const VALIDATOR_BYTES_LUBAN: usize = 20 + 48;
+
+const DIFF_IN_TURN: u128 = 2;
+const DIFF_NO_TURN: u128 = 1;
+
+/// The validators of the chain with their turn length
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidatorSet {
+    /// Sorted by address
+    pub validators: Vec<[u8; 20]>,
+    /// Consecutive headers the validator in turn seals, 1 before Bohr
+    pub turn_length: u8,
+}
+
+impl ValidatorSet {
+    /// Bytes of an encoded set: the number of validators in a byte followed by their slots,
+    /// then the turn length
+    pub const SIZE: usize = 1 + 20 * MAX_VALIDATORS + 1;
+
+    /// A set of the `validators`, sorted by address
+    pub fn new(validators: Vec<[u8; 20]>, turn_length: u8) -> Result<Self, Error> {
+        let set = ValidatorSet {
+            validators,
+            turn_length,
+        };
+        if set.validators.is_empty()
+            || set.validators.len() > MAX_VALIDATORS
+            || set.validators.windows(2).any(|pair| pair[0] >= pair[1])
+            || set.turn_length == 0
+            || set.check_len() as usize > MAX_RECENTS
+        {
+            return Err(Error::InvalidValidators);
+        }
+        Ok(set)
+    }
+
+    /// Decode a set, none from zeros
+    pub fn decode(data: &[u8]) -> Result<Option<Self>, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        if data.iter().all(|&byte| byte == 0) {
+            return Ok(None);
+        }
+        let len = data[0] as usize;
+        if len > MAX_VALIDATORS {
+            return Err(Error::Encoding);
+        }
+        let (validators, unused) = data[1..Self::SIZE - 1].split_at(20 * len);
+        if unused.iter().any(|&byte| byte != 0) {
+            return Err(Error::Encoding);
+        }
+        let validators = validators.chunks(20).map(address).collect();
+        let set = ValidatorSet::new(validators, data[Self::SIZE - 1]);
+        set.map(Some).map_err(|_| Error::Encoding)
+    }
+
+    /// Encode `set`, zeros for none
+    pub fn encode(set: Option<&Self>, out: &mut Vec<u8>) {
+        let start = out.len();
+        if let Some(set) = set {
+            out.push(set.validators.len() as u8);
+            for validator in &set.validators {
+                out.extend_from_slice(validator);
+            }
+        }
+        out.resize(start + Self::SIZE - 1, 0);
+        out.push(set.map_or(0, |set| set.turn_length));
+    }
+
+    /// Headers back from the parent of a header among which its validator sealed less than
+    /// `turn_length` ones. The validators an epoch header lists take over this many blocks
+    /// after it.
+    pub fn check_len(&self) -> u64 {
+        (self.validators.len() as u64 / 2 + 1) * self.turn_length as u64 - 1
+    }
+
+    /// Whether `signer` is the validator in turn to seal the header of `number`
+    pub fn in_turn(&self, number: u64, signer: &[u8; 20]) -> bool {
+        let turn = number / self.turn_length as u64;
+        let index = (turn % self.validators.len() as u64) as usize;
+        &self.validators[index] == signer
+    }
+}
+
+/// The validators of the chain at a header, with the signers of the latest headers and the
+/// validators of the latest epoch header until they take over
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub validators: ValidatorSet,
+    /// The signers of the latest `check_len` headers, the latest last
+    pub recents: Vec<[u8; 20]>,
+    pub pending: Option<ValidatorSet>,
+}
+
+impl Snapshot {
+    /// Bytes of the encoded snapshot: the validators, the number of recents as a little endian
+    /// u16 followed by their slots, and the pending validators
+    pub const SIZE: usize = ValidatorSet::SIZE + 2 + 20 * MAX_RECENTS + ValidatorSet::SIZE;
+
+    /// A snapshot of the `validators`, without recents nor pending validators
+    pub fn new(validators: ValidatorSet) -> Self {
+        Snapshot {
+            validators,
+            ..Default::default()
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let (validators, data) = data.split_at(ValidatorSet::SIZE);
+        let (recents, pending) = data.split_at(2 + 20 * MAX_RECENTS);
+        let len = u16::from_le_bytes([recents[0], recents[1]]) as usize;
+        if len > MAX_RECENTS {
+            return Err(Error::Encoding);
+        }
+        let (recents, unused) = recents[2..].split_at(20 * len);
+        if unused.iter().any(|&byte| byte != 0) {
+            return Err(Error::Encoding);
+        }
+        Ok(Snapshot {
+            validators: ValidatorSet::decode(validators)?.ok_or(Error::Encoding)?,
+            recents: recents.chunks(20).map(address).collect(),
+            pending: ValidatorSet::decode(pending)?,
+        })
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        ValidatorSet::encode(Some(&self.validators), out);
+        out.extend_from_slice(&(self.recents.len() as u16).to_le_bytes());
+        for signer in &self.recents {
+            out.extend_from_slice(signer);
+        }
+        out.resize(out.len() + 20 * (MAX_RECENTS - self.recents.len()), 0);
+        ValidatorSet::encode(self.pending.as_ref(), out);
+    }
+
+    /// Check that `signer` may seal the child of the header of the snapshot, as a validator
+    /// which sealed less than `turn_length` of the recent headers
+    pub fn verify_signer(&self, signer: &[u8; 20]) -> Result<(), Error> {
+        if self.validators.validators.binary_search(signer).is_err() {
+            return Err(Error::UnauthorizedSigner);
+        }
+        let sealed = self
+            .recents
+            .iter()
+            .filter(|recent| *recent == signer)
+            .count();
+        if sealed >= self.validators.turn_length as usize {
+            return Err(Error::UnauthorizedSigner);
+        }
+        Ok(())
+    }
+
+    /// Apply the header of `number` sealed by `signer`, with the validators it lists if it is
+    /// an epoch header
+    pub fn apply(
+        &mut self,
+        config: &ParliaConfig,
+        number: u64,
+        signer: [u8; 20],
+        listed: Option<ValidatorSet>,
+    ) {
+        self.recents.push(signer);
+        if listed.is_some() {
+            self.pending = listed;
+        }
+        if number > 0 && number % config.epoch == self.validators.check_len() {
+            if let Some(pending) = self.pending.take() {
+                self.validators = pending;
+            }
+        }
+        let check_len = self.validators.check_len() as usize;
+        if self.recents.len() > check_len {
+            let len = self.recents.len();
+            self.recents.drain(..len - check_len);
+        }
+    }
+}
+
+/// keccak256 of the RLP list of the chain id followed by the fields of the header without the
+/// seal of its extra data, the message its validator signs. The fields from London are only
+/// signed from Cancun, the fork parlia sets a zero parent beacon block root from.
+pub fn seal_hash(chain_id: u64, header: &Header) -> Result<[u8; 32], Error> {
+    let mut unsealed = header.clone();
+    let len = unsealed.extra_data.len().saturating_sub(EXTRA_SEAL);
+    unsealed.extra_data.truncate(len);
+    if unsealed.parent_beacon_block_root != Some([0u8; 32]) {
+        unsealed.base_fee_per_gas = None;
+        unsealed.withdrawals_root = None;
+        unsealed.blob_gas_used = None;
+        unsealed.excess_blob_gas = None;
+        unsealed.parent_beacon_block_root = None;
+    }
+    let encoded = unsealed.encode();
+    let fields = match rlp::decode(&encoded)? {
+        rlp::Item::List(fields) => fields,
+        rlp::Item::Bytes(_) => return Err(Error::InvalidRlp),
+    };
+    let mut signed = Vec::with_capacity(fields.len() + 9);
+    rlp::encode_u64(&mut signed, chain_id);
+    signed.extend_from_slice(fields);
+    let mut message = Vec::with_capacity(signed.len() + 9);
+    rlp::encode_list(&mut message, &signed);
+    Ok(keccak256(&message))
+}
+
+/// Recover the address of the validator which sealed `header`
+pub fn recover_signer(config: &ParliaConfig, header: &Header) -> Result<[u8; 20], Error> {
+    let len = header.extra_data.len();
+    if len < EXTRA_VANITY + EXTRA_SEAL {
+        return Err(Error::InvalidExtraData);
+    }
+    let hash = seal_hash(config.chain_id, header)?;
+    ecrecover(&hash, &header.extra_data[len - EXTRA_SEAL..])
+}
+
+/// The validators an epoch header lists in its extra data, between the vanity and the seal.
+///
+/// Before Luban they are addresses. From Luban they are the number of validators in a byte
+/// followed by the address and the BLS public key of every validator, then from Bohr the turn
+/// length in a byte, and the vote attestation of the header.
+pub fn parse_validators(config: &ParliaConfig, header: &Header) -> Result<ValidatorSet, Error> {
+    let len = header.extra_data.len();
+    if len < EXTRA_VANITY + EXTRA_SEAL {
+        return Err(Error::InvalidExtraData);
+    }
+    let data = &header.extra_data[EXTRA_VANITY..len - EXTRA_SEAL];
+    if header.number < config.luban {
+        if data.len() % 20 != 0 {
+            return Err(Error::InvalidExtraData);
+        }
+        let validators = data.chunks(20).map(address).collect();
+        return ValidatorSet::new(validators, 1);
+    }
+    let count = *data.first().ok_or(Error::InvalidExtraData)? as usize;
+    let end = 1 + count * VALIDATOR_BYTES_LUBAN;
+    if data.len() < end {
+        return Err(Error::InvalidExtraData);
+    }
+    let validators = data[1..end]
+        .chunks(VALIDATOR_BYTES_LUBAN)
+        .map(|data| address(&data[..20]))
+        .collect();
+    let turn_length = if header.number >= config.bohr {
+        *data.get(end).ok_or(Error::InvalidExtraData)?
+    } else {
+        1
+    };
+    ValidatorSet::new(validators, turn_length)
+}
+
+fn address(data: &[u8]) -> [u8; 20] {
+    let mut address = [0u8; 20];
+    address.copy_from_slice(data);
+    address
+}
+
+/// Verify `header` against its `parent`, whose snapshot is `snapshot`, and return its signer
+/// with the validators it lists if it is an epoch header
+pub fn verify_header(
+    config: &ParliaConfig,
+    snapshot: &Snapshot,
+    parent: &Header,
+    header: &Header,
+) -> Result<([u8; 20], Option<ValidatorSet>), Error> {
+    let len = header.extra_data.len();
+    if len < EXTRA_VANITY + EXTRA_SEAL {
+        return Err(Error::InvalidExtraData);
+    }
+    let listed = if header.number % config.epoch == 0 {
+        Some(parse_validators(config, header)?)
+    } else if header.number < config.luban && len != EXTRA_VANITY + EXTRA_SEAL {
+        // from Luban the extra data carries the vote attestation
+        return Err(Error::InvalidExtraData);
+    } else {
+        None
+    };
+    if header.uncles_hash != EMPTY_UNCLES_HASH {
+        return Err(Error::InvalidSeal);
+    }
+    if header.timestamp < parent.timestamp {
+        return Err(Error::InvalidTimestamp);
+    }
+    verify_gas(config, parent, header)?;
+
+    let signer = recover_signer(config, header)?;
+    if signer != header.coinbase {
+        return Err(Error::InvalidSeal);
+    }
+    snapshot.verify_signer(&signer)?;
+    let difficulty = if snapshot.validators.in_turn(header.number, &signer) {
+        DIFF_IN_TURN
+    } else {
+        DIFF_NO_TURN
+    };
+    if header.difficulty != difficulty {
+        return Err(Error::InvalidDifficulty);
+    }
+    Ok((signer, listed))
+}
+
+/// Verify the gas limit and gas used of `header`, and its base fee, which is zero from London
+fn verify_gas(config: &ParliaConfig, parent: &Header, header: &Header) -> Result<(), Error> {
+    if header.gas_limit > MAX_GAS_LIMIT {
+        return Err(Error::InvalidGasLimit);
+    }
+    if header.gas_used > header.gas_limit {
+        return Err(Error::InvalidGasUsed);
+    }
+    let diff = if header.gas_limit > parent.gas_limit {
+        header.gas_limit - parent.gas_limit
+    } else {
+        parent.gas_limit - header.gas_limit
+    };
+    if diff >= parent.gas_limit / GAS_LIMIT_BOUND_DIVISOR || header.gas_limit < MIN_GAS_LIMIT {
+        return Err(Error::InvalidGasLimit);
+    }
+    let base_fee = if header.number >= config.london {
+        Some(0)
+    } else {
+        None
+    };
+    if header.base_fee_per_gas != base_fee {
+        return Err(Error::InvalidBaseFee);
+    }
+    Ok(())
+}
+
+/// Apply a branch of sealed headers whose first header is the child of the kept `parent`, as
+/// `clique::apply_branch` does. The validators listed by a kept epoch header are the ones of
+/// the state.
+pub fn apply_branch(
+    config: &ParliaConfig,
+    state: &ParliaClientState,
+    parent: &Header,
+    branch: &[Header],
+) -> Result<ParliaClientState, Error> {
+    let mut headers = state.headers.clone();
+    headers.apply_branch(branch)?;
+    let oldest = state.headers.get(0).ok_or(Error::UnknownParent)?.number;
+    let fork = parent.number.saturating_sub(oldest) as usize;
+    if state.headers.get(fork).map(|record| record.hash) != Some(parent.hash) {
+        return Err(Error::InvalidHeaderChain);
+    }
+
+    // the signer of every header from the oldest kept one, with the validators it lists
+    let mut entries: Vec<([u8; 20], Option<ValidatorSet>)> = state.signers[..=fork]
+        .iter()
+        .enumerate()
+        .map(|(index, signer)| {
+            let number = oldest + index as u64;
+            let listed = if index > 0 && number % config.epoch == 0 {
+                state.listed.clone()
+            } else {
+                None
+            };
+            (*signer, listed)
+        })
+        .collect();
+    let mut snapshot = state.snapshot.clone();
+    for (index, (signer, listed)) in entries.iter().enumerate().skip(1) {
+        snapshot.apply(config, oldest + index as u64, *signer, listed.clone());
+    }
+    let mut parent = parent;
+    for header in branch {
+        if header.parent_hash != parent.hash {
+            return Err(Error::InvalidHeaderChain);
+        }
+        let (signer, listed) = verify_header(config, &snapshot, parent, header)?;
+        snapshot.apply(config, header.number, signer, listed.clone());
+        entries.push((signer, listed));
+        parent = header;
+    }
+
+    // the headers which left the ring move to the snapshot
+    let dropped = entries.len() - headers.len();
+    let mut snapshot = state.snapshot.clone();
+    for (index, (signer, listed)) in entries.iter().enumerate().take(dropped + 1).skip(1) {
+        snapshot.apply(config, oldest + index as u64, *signer, listed.clone());
+    }
+    let entries = entries.split_off(dropped);
+    let listed = entries
+        .iter()
+        .skip(1)
+        .rev()
+        .find_map(|(_, listed)| listed.clone());
+    Ok(ParliaClientState {
+        headers,
+        snapshot,
+        signers: entries.iter().map(|(signer, _)| *signer).collect(),
+        listed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{HeaderChain, HeaderRecord, HEADER_SLOTS};
+    use crate::test_data::*;
+    use crate::MAX_SEALED_HEADERS;
+
+    fn set(validators: &[Signer], turn_length: u8) -> ValidatorSet {
+        ValidatorSet::new(addresses(validators), turn_length).unwrap()
+    }
+
+    /// State of a light client started from the epoch header `header` of `validators`
+    fn checkpoint_state(header: &Header, validators: &[Signer]) -> ParliaClientState {
+        let mut snapshot = Snapshot::new(set(validators, 1));
+        let listed = parse_validators(&PARLIA, header).unwrap();
+        snapshot.apply(&PARLIA, header.number, header.coinbase, Some(listed));
+        ParliaClientState {
+            headers: HeaderChain::new(HeaderRecord::new(header), header.difficulty),
+            snapshot,
+            signers: vec![header.coinbase],
+            listed: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_validators() {
+        let validators = new_signers(3);
+        let header = parlia_epoch_header(40, &validators, 2);
+        assert_eq!(
+            parse_validators(&PARLIA, &header).unwrap(),
+            set(&validators, 2)
+        );
+        // the turn length is 1 before Bohr, and the validators are addresses before Luban
+        let config = ParliaConfig {
+            bohr: u64::MAX,
+            ..PARLIA
+        };
+        assert_eq!(
+            parse_validators(&config, &header).unwrap(),
+            set(&validators, 1)
+        );
+        let config = ParliaConfig {
+            luban: u64::MAX,
+            ..config
+        };
+        let mut header = header;
+        header.extra_data = vec![0u8; EXTRA_VANITY];
+        for validator in &validators {
+            header.extra_data.extend_from_slice(&validator.address);
+        }
+        header.extra_data.extend_from_slice(&[0u8; EXTRA_SEAL]);
+        assert_eq!(
+            parse_validators(&config, &header).unwrap(),
+            set(&validators, 1)
+        );
+        header.extra_data.remove(EXTRA_VANITY);
+        assert_eq!(
+            parse_validators(&config, &header),
+            Err(Error::InvalidExtraData)
+        );
+
+        // truncated, unsorted, without validators or with a zero turn length
+        let mut header = parlia_epoch_header(40, &validators, 1);
+        header
+            .extra_data
+            .truncate(EXTRA_VANITY + EXTRA_SEAL + VALIDATOR_BYTES_LUBAN);
+        assert_eq!(
+            parse_validators(&PARLIA, &header),
+            Err(Error::InvalidExtraData)
+        );
+        let mut reversed = new_signers(3);
+        reversed.reverse();
+        for header in &[
+            parlia_epoch_header(40, &reversed, 1),
+            parlia_epoch_header(40, &[], 1),
+            parlia_epoch_header(40, &validators, 0),
+        ] {
+            assert_eq!(
+                parse_validators(&PARLIA, header),
+                Err(Error::InvalidValidators)
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_header() {
+        let validators = new_signers(3);
+        let headers = parlia_headers(&validators, &validators, 3);
+        let state = checkpoint_state(&headers[0], &validators);
+        let mut snapshot = state.snapshot.clone();
+        verify_header(&PARLIA, &snapshot, &headers[0], &headers[1]).unwrap();
+        snapshot.apply(&PARLIA, 1, headers[1].coinbase, None);
+        verify_header(&PARLIA, &snapshot, &headers[1], &headers[2]).unwrap();
+
+        // the validator of the parent sealed its turn already
+        let snapshot = state.snapshot.clone();
+        let sealer = |header: &Header| {
+            validators
+                .iter()
+                .find(|validator| validator.address == header.coinbase)
+                .unwrap()
+        };
+        let header = parlia_child(&headers[0], sealer(&headers[0]), false, &validators, 1);
+        assert_eq!(
+            verify_header(&PARLIA, &snapshot, &headers[0], &header),
+            Err(Error::UnauthorizedSigner)
+        );
+        // out of turn with difficulty 1, and an outsider
+        let out_of_turn = validators
+            .iter()
+            .find(|validator| {
+                validator.address != headers[0].coinbase && validator.address != headers[1].coinbase
+            })
+            .unwrap();
+        let header = parlia_child(&headers[0], out_of_turn, false, &validators, 1);
+        verify_header(&PARLIA, &snapshot, &headers[0], &header).unwrap();
+        let outsider = new_signers(4)
+            .into_iter()
+            .find(|signer| !addresses(&validators).contains(&signer.address))
+            .unwrap();
+        let header = parlia_child(&headers[0], &outsider, false, &validators, 1);
+        assert_eq!(
+            verify_header(&PARLIA, &snapshot, &headers[0], &header),
+            Err(Error::UnauthorizedSigner)
+        );
+
+        let forge = |parent: &Header, forge: &dyn Fn(&mut Header)| {
+            let mut header = headers[1].clone();
+            forge(&mut header);
+            seal_parlia_header(&mut header, sealer(&headers[1]));
+            verify_header(&PARLIA, &snapshot, parent, &header)
+        };
+        assert_eq!(
+            forge(&headers[0], &|header| header.difficulty = 1),
+            Err(Error::InvalidDifficulty)
+        );
+        assert_eq!(
+            forge(&headers[0], &|header| header.coinbase = outsider.address),
+            Err(Error::InvalidSeal)
+        );
+        assert_eq!(
+            forge(&headers[0], &|header| header.uncles_hash = [0u8; 32]),
+            Err(Error::InvalidSeal)
+        );
+        assert_eq!(
+            forge(&headers[0], &|header| header.timestamp = 0),
+            Err(Error::InvalidTimestamp)
+        );
+        assert_eq!(
+            forge(&headers[0], &|header| header.gas_limit +=
+                header.gas_limit / 256),
+            Err(Error::InvalidGasLimit)
+        );
+        assert_eq!(
+            forge(&headers[0], &|header| header.base_fee_per_gas = Some(0)),
+            Err(Error::InvalidBaseFee)
+        );
+        assert_eq!(
+            forge(&headers[0], &|header| header
+                .extra_data
+                .truncate(EXTRA_SEAL)),
+            Err(Error::InvalidExtraData)
+        );
+        // the seal commits to the chain id
+        let config = ParliaConfig {
+            chain_id: 97,
+            ..PARLIA
+        };
+        assert_eq!(
+            verify_header(&config, &snapshot, &headers[0], &headers[1]),
+            Err(Error::InvalidSeal)
+        );
+        // only the extra data of epoch headers lists validators before Luban
+        let config = ParliaConfig {
+            luban: u64::MAX,
+            ..PARLIA
+        };
+        let mut header = headers[1].clone();
+        header.extra_data = parlia_epoch_header(1, &validators, 1).extra_data;
+        seal_parlia_header(&mut header, sealer(&headers[1]));
+        assert_eq!(
+            verify_header(&config, &snapshot, &headers[0], &header),
+            Err(Error::InvalidExtraData)
+        );
+    }
+
+    #[test]
+    fn test_rotate_validators() {
+        let all = new_signers(4);
+        let validators = &all[..3];
+        let headers = parlia_headers(validators, &all, PARLIA.epoch as usize + 3);
+        let mut snapshot = Snapshot::new(set(validators, 1));
+        for header in &headers {
+            let listed = if header.number % PARLIA.epoch == 0 {
+                Some(parse_validators(&PARLIA, header).unwrap())
+            } else {
+                None
+            };
+            snapshot.apply(&PARLIA, header.number, header.coinbase, listed);
+            // the validators listed at the epoch header take over a block later
+            if header.number == PARLIA.epoch {
+                assert_eq!(snapshot.validators, set(validators, 1));
+                assert_eq!(snapshot.pending, Some(set(&all, 1)));
+            }
+        }
+        assert_eq!(snapshot.validators, set(&all, 1));
+        assert_eq!(snapshot.pending, None);
+        assert_eq!(snapshot.recents.len(), 2);
+
+        // validators seal `turn_length` consecutive headers
+        let validators = set(&all, 3);
+        assert_eq!(validators.check_len(), 8);
+        assert!(validators.in_turn(5, &all[1].address));
+        assert!(validators.in_turn(12, &all[0].address));
+        let mut snapshot = Snapshot::new(validators);
+        for _ in 0..2 {
+            snapshot.verify_signer(&all[1].address).unwrap();
+            snapshot.apply(&PARLIA, 5, all[1].address, None);
+        }
+        snapshot.verify_signer(&all[1].address).unwrap();
+        snapshot.apply(&PARLIA, 5, all[1].address, None);
+        assert_eq!(
+            snapshot.verify_signer(&all[1].address),
+            Err(Error::UnauthorizedSigner)
+        );
+    }
+
+    #[test]
+    fn test_encode_snapshot() {
+        let all = new_signers(4);
+        let snapshot = Snapshot {
+            validators: set(&all[..3], 2),
+            recents: vec![all[0].address, all[2].address],
+            pending: Some(set(&all, 1)),
+        };
+        let mut data = Vec::new();
+        snapshot.encode(&mut data);
+        assert_eq!(data.len(), Snapshot::SIZE);
+        assert_eq!(Snapshot::decode(&data).unwrap(), snapshot);
+        assert_eq!(Snapshot::decode(&data[1..]), Err(Error::Encoding));
+
+        let snapshot = Snapshot::new(set(&all, 1));
+        let mut data = Vec::new();
+        snapshot.encode(&mut data);
+        assert_eq!(Snapshot::decode(&data).unwrap(), snapshot);
+        // the validators are sorted, and the unused slots are zeros
+        let mut unsorted = data.clone();
+        unsorted[1..21].copy_from_slice(&all[3].address);
+        assert_eq!(Snapshot::decode(&unsorted), Err(Error::Encoding));
+        data[Snapshot::SIZE - 2] = 1;
+        assert_eq!(Snapshot::decode(&data), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_apply_branch() {
+        let all = new_signers(4);
+        let validators = &all[..3];
+        let len = PARLIA.epoch as usize + 5;
+        let headers = parlia_headers(validators, &all, len);
+        let mut state = checkpoint_state(&headers[0], validators);
+        for start in (1..len).step_by(MAX_SEALED_HEADERS) {
+            let end = core::cmp::min(start + MAX_SEALED_HEADERS, len);
+            state =
+                apply_branch(&PARLIA, &state, &headers[start - 1], &headers[start..end]).unwrap();
+        }
+        assert_eq!(state.headers.len(), HEADER_SLOTS);
+        assert_eq!(state.headers.tip().unwrap().hash, headers[len - 1].hash);
+        assert_eq!(state.listed, Some(set(&all, 1)));
+        let oldest = len - HEADER_SLOTS;
+        assert_eq!(state.signers.len(), HEADER_SLOTS);
+        assert_eq!(state.signers[0], headers[oldest].coinbase);
+
+        // the snapshot is the one of the oldest kept header
+        let mut snapshot = checkpoint_state(&headers[0], validators).snapshot;
+        for header in &headers[1..=oldest] {
+            snapshot.apply(&PARLIA, header.number, header.coinbase, None);
+        }
+        assert_eq!(state.snapshot, snapshot);
+
+        // a fork from a kept header replays the takeover of the validators listed by the kept
+        // epoch header
+        let len = PARLIA.epoch as usize + 2;
+        let mut state = checkpoint_state(&headers[0], validators);
+        for start in (1..len).step_by(MAX_SEALED_HEADERS) {
+            let end = core::cmp::min(start + MAX_SEALED_HEADERS, len);
+            state =
+                apply_branch(&PARLIA, &state, &headers[start - 1], &headers[start..end]).unwrap();
+        }
+        assert_eq!(state.listed, Some(set(&all, 1)));
+        let fork = PARLIA.epoch as usize;
+        let number = fork as u64 + 2;
+        let in_turn = set(&all, 1).in_turn(number, &all[3].address);
+        let branch = [parlia_child(&headers[fork + 1], &all[3], in_turn, &all, 1)];
+        let next = apply_branch(&PARLIA, &state, &headers[fork + 1], &branch).unwrap();
+        assert_eq!(next.headers.tip().unwrap().hash, branch[0].hash);
+        assert_eq!(next.listed, state.listed);
+        let mut branch = vec![parlia_child(&headers[fork], &all[3], true, &all, 1)];
+        branch.push(parlia_child(&branch[0], &all[0], true, &all, 1));
+        assert_eq!(
+            apply_branch(&PARLIA, &state, &headers[fork], &branch),
+            Err(Error::UnauthorizedSigner)
+        );
+    }
+}
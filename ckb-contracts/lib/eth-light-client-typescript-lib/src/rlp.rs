@@ -0,0 +1,233 @@
+//! Recursive length prefix serialization, see appendix B of the Ethereum yellow paper.
+//!
+//! Decoding borrows from the encoded data and only accepts the canonical encoding, so an item
+//! has exactly one valid serialization and hashes of decoded data can be trusted.
+
+use crate::error::Error;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// A decoded item, lists hold their encoded payload and are decoded lazily
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item<'a> {
+    Bytes(&'a [u8]),
+    List(&'a [u8]),
+}
+
+/// Decode the item `data` consists of
+pub fn decode(data: &[u8]) -> Result<Item<'_>, Error> {
+    let (item, rest) = decode_prefix(data)?;
+    if !rest.is_empty() {
+        return Err(Error::InvalidRlp);
+    }
+    Ok(item)
+}
+
+/// Decode the item at the start of `data`, returns it and the data after it
+pub fn decode_prefix(data: &[u8]) -> Result<(Item<'_>, &[u8]), Error> {
+    let prefix = *data.first().ok_or(Error::InvalidRlp)?;
+    match prefix {
+        0x00..=0x7f => Ok((Item::Bytes(&data[..1]), &data[1..])),
+        0x80..=0xb7 => {
+            let (payload, rest) = split(&data[1..], (prefix - 0x80) as usize)?;
+            // a single byte below 0x80 is its own encoding
+            if payload.len() == 1 && payload[0] < 0x80 {
+                return Err(Error::InvalidRlp);
+            }
+            Ok((Item::Bytes(payload), rest))
+        }
+        0xb8..=0xbf => {
+            let (payload, rest) = long_payload(&data[1..], (prefix - 0xb7) as usize)?;
+            Ok((Item::Bytes(payload), rest))
+        }
+        0xc0..=0xf7 => {
+            let (payload, rest) = split(&data[1..], (prefix - 0xc0) as usize)?;
+            Ok((Item::List(payload), rest))
+        }
+        0xf8..=0xff => {
+            let (payload, rest) = long_payload(&data[1..], (prefix - 0xf7) as usize)?;
+            Ok((Item::List(payload), rest))
+        }
+    }
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+    if data.len() < len {
+        return Err(Error::InvalidRlp);
+    }
+    Ok(data.split_at(len))
+}
+
+/// Split a payload whose length is encoded in the `len_of_len` bytes before it
+fn long_payload(data: &[u8], len_of_len: usize) -> Result<(&[u8], &[u8]), Error> {
+    let (len, data) = split(data, len_of_len)?;
+    if len[0] == 0 || len_of_len > core::mem::size_of::<usize>() {
+        return Err(Error::InvalidRlp);
+    }
+    let len = len
+        .iter()
+        .fold(0usize, |len, &byte| (len << 8) | byte as usize);
+    // shorter payloads must use the short form
+    if len < 56 {
+        return Err(Error::InvalidRlp);
+    }
+    split(data, len)
+}
+
+impl<'a> Item<'a> {
+    pub fn bytes(&self) -> Result<&'a [u8], Error> {
+        match *self {
+            Item::Bytes(bytes) => Ok(bytes),
+            Item::List(_) => Err(Error::InvalidRlp),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<Item<'a>>, Error> {
+        let mut payload = match *self {
+            Item::List(payload) => payload,
+            Item::Bytes(_) => return Err(Error::InvalidRlp),
+        };
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            let (item, rest) = decode_prefix(payload)?;
+            items.push(item);
+            payload = rest;
+        }
+        Ok(items)
+    }
+
+    /// Copy a byte string of exactly `out.len()` bytes, such as a hash or an address
+    pub fn fixed(&self, out: &mut [u8]) -> Result<(), Error> {
+        let bytes = self.bytes()?;
+        if bytes.len() != out.len() {
+            return Err(Error::InvalidRlp);
+        }
+        out.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    pub fn u64(&self) -> Result<u64, Error> {
+        Ok(self.uint(8)? as u64)
+    }
+
+    pub fn u128(&self) -> Result<u128, Error> {
+        self.uint(16)
+    }
+
+    /// Big endian integer without leading zeros, of at most `max_len` bytes
+    fn uint(&self, max_len: usize) -> Result<u128, Error> {
+        let bytes = self.bytes()?;
+        if bytes.len() > max_len || bytes.first() == Some(&0) {
+            return Err(Error::InvalidRlp);
+        }
+        Ok(bytes
+            .iter()
+            .fold(0u128, |value, &byte| (value << 8) | byte as u128))
+    }
+}
+
+pub fn encode_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        out.push(bytes[0]);
+    } else {
+        encode_length(out, bytes.len(), 0x80);
+        out.extend_from_slice(bytes);
+    }
+}
+
+pub fn encode_u64(out: &mut Vec<u8>, value: u64) {
+    encode_u128(out, value as u128)
+}
+
+pub fn encode_u128(out: &mut Vec<u8>, value: u128) {
+    let bytes = value.to_be_bytes();
+    let leading_zeros = (value.leading_zeros() / 8) as usize;
+    encode_bytes(out, &bytes[leading_zeros..])
+}
+
+/// Wrap the concatenated encodings of the list items
+pub fn encode_list(out: &mut Vec<u8>, payload: &[u8]) {
+    encode_length(out, payload.len(), 0xc0);
+    out.extend_from_slice(payload);
+}
+
+fn encode_length(out: &mut Vec<u8>, len: usize, offset: u8) {
+    if len < 56 {
+        out.push(offset + len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let leading_zeros = (len.leading_zeros() / 8) as usize;
+        out.push(offset + 55 + (bytes.len() - leading_zeros) as u8);
+        out.extend_from_slice(&bytes[leading_zeros..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(&[0x05]).unwrap(), Item::Bytes(&[0x05]));
+        assert_eq!(decode(&[0x80]).unwrap(), Item::Bytes(&[]));
+        assert_eq!(
+            decode(&[0x83, b'd', b'o', b'g']).unwrap(),
+            Item::Bytes(b"dog")
+        );
+        let list = [0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g'];
+        assert_eq!(
+            decode(&list).unwrap().list().unwrap(),
+            vec![Item::Bytes(b"cat"), Item::Bytes(b"dog")]
+        );
+        assert_eq!(decode(&[0x82, 0x04, 0x00]).unwrap().u64().unwrap(), 1024);
+        assert_eq!(decode(&[0x80]).unwrap().u64().unwrap(), 0);
+
+        let mut long = vec![0xb8, 56];
+        long.extend_from_slice(&[1u8; 56]);
+        assert_eq!(decode(&long).unwrap(), Item::Bytes(&[1u8; 56]));
+    }
+
+    #[test]
+    fn test_decode_non_canonical() {
+        // single byte wrapped in a string, length of a short string in the long form
+        assert_eq!(decode(&[0x81, 0x05]), Err(Error::InvalidRlp));
+        let mut long = vec![0xb8, 3];
+        long.extend_from_slice(b"dog");
+        assert_eq!(decode(&long), Err(Error::InvalidRlp));
+        // leading zeros in a length and in an integer
+        assert_eq!(decode(&[0xb9, 0x00, 0x38]), Err(Error::InvalidRlp));
+        assert_eq!(
+            decode(&[0x82, 0x00, 0x01]).unwrap().u64(),
+            Err(Error::InvalidRlp)
+        );
+        // truncated data and trailing bytes
+        assert_eq!(decode(&[0x83, b'd', b'o']), Err(Error::InvalidRlp));
+        assert_eq!(decode(&[0x80, 0x80]), Err(Error::InvalidRlp));
+        assert_eq!(decode(&[]), Err(Error::InvalidRlp));
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut list = Vec::new();
+        encode_bytes(&mut list, b"cat");
+        encode_u64(&mut list, 0);
+        encode_u64(&mut list, 0x7f);
+        encode_u128(&mut list, 1024);
+        encode_bytes(&mut list, &[1u8; 56]);
+        let mut out = Vec::new();
+        encode_list(&mut out, &list);
+
+        let items = decode(&out).unwrap().list().unwrap();
+        assert_eq!(items[0], Item::Bytes(b"cat"));
+        assert_eq!(items[1].u64().unwrap(), 0);
+        assert_eq!(items[2].u64().unwrap(), 0x7f);
+        assert_eq!(items[3].u128().unwrap(), 1024);
+        assert_eq!(items[4], Item::Bytes(&[1u8; 56]));
+        assert_eq!(&out[..2], &[0xf8, list.len() as u8]);
+    }
+}
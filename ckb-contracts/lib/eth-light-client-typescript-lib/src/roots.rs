@@ -0,0 +1,196 @@
+//! Table of the ethash dataset roots of a window of epochs, kept in the light-client cell.
+//!
+//! The window covers `ROOT_SLOTS` epochs from `first_epoch`, a zero root is an epoch whose
+//! root is not known yet. Each root comes with the number of items of its dataset, a prime the
+//! owner computes off-chain with `ethash::dataset_items`, the contract only checks it with
+//! `ethash::check_dataset_items` when the root is appended. The owner of the light client
+//! appends the roots of future epochs and slides the window forward, but known roots never
+//! change and the window may not start after the epoch of the oldest header the light client
+//! keeps, so roots are at most `ROOT_SLOTS - 1` epochs ahead of the chain.
+
+use crate::error::Error;
+use crate::ethash;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Epochs covered by the table, an epoch lasts about 5 days
+pub const ROOT_SLOTS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetRoots {
+    pub first_epoch: u64,
+    pub roots: [[u8; 32]; ROOT_SLOTS],
+    /// Number of items of the dataset of each epoch, ignored while its root is not known
+    pub items: [u64; ROOT_SLOTS],
+}
+
+impl DatasetRoots {
+    /// Bytes of the encoded table
+    pub const SIZE: usize = 8 + 40 * ROOT_SLOTS;
+
+    /// Decode the table from `first_epoch` as a little endian u64 followed by the roots and by
+    /// the item counts as little endian u64
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let mut first_epoch = [0u8; 8];
+        first_epoch.copy_from_slice(&data[..8]);
+        let mut roots = [[0u8; 32]; ROOT_SLOTS];
+        for (root, data) in roots.iter_mut().zip(data[8..].chunks(32)) {
+            root.copy_from_slice(data);
+        }
+        let mut items = [0u64; ROOT_SLOTS];
+        for (items, data) in items.iter_mut().zip(data[8 + 32 * ROOT_SLOTS..].chunks(8)) {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            *items = u64::from_le_bytes(bytes);
+        }
+        Ok(DatasetRoots {
+            first_epoch: u64::from_le_bytes(first_epoch),
+            roots,
+            items,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::SIZE);
+        data.extend_from_slice(&self.first_epoch.to_le_bytes());
+        for root in &self.roots {
+            data.extend_from_slice(root);
+        }
+        for items in &self.items {
+            data.extend_from_slice(&items.to_le_bytes());
+        }
+        data
+    }
+
+    /// The known root of `epoch` with the number of items of its dataset, `None` if it is out
+    /// of the window or not known yet
+    pub fn root(&self, epoch: u64) -> Option<([u8; 32], u64)> {
+        let slot = epoch.checked_sub(self.first_epoch)? as usize;
+        self.roots
+            .get(slot)
+            .filter(|root| **root != [0u8; 32])
+            .map(|root| (*root, self.items[slot]))
+    }
+
+    /// Check the item counts of the known roots, which the contract does not compute
+    pub fn verify_items(&self) -> Result<(), Error> {
+        for slot in 0..ROOT_SLOTS {
+            let epoch = self.first_epoch + slot as u64;
+            if self.roots[slot] != [0u8; 32]
+                && !ethash::check_dataset_items(epoch, self.items[slot])
+            {
+                return Err(Error::InvalidDatasetRoots);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `next` only appends roots and slides the window forward, up to
+    /// `oldest_epoch` which is the epoch of the oldest header the light client keeps
+    pub fn verify_update(&self, next: &Self, oldest_epoch: Option<u64>) -> Result<(), Error> {
+        let max_first_epoch = match oldest_epoch {
+            Some(epoch) if epoch > self.first_epoch => epoch,
+            _ => self.first_epoch,
+        };
+        if next.first_epoch < self.first_epoch || next.first_epoch > max_first_epoch {
+            return Err(Error::InvalidDatasetRoots);
+        }
+        let shift = (next.first_epoch - self.first_epoch) as usize;
+        for slot in shift..ROOT_SLOTS {
+            let next_slot = slot - shift;
+            if self.roots[slot] != [0u8; 32]
+                && (self.roots[slot] != next.roots[next_slot]
+                    || self.items[slot] != next.items[next_slot])
+            {
+                return Err(Error::InvalidDatasetRoots);
+            }
+        }
+        next.verify_items()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots(first_epoch: u64, known: &[u8]) -> DatasetRoots {
+        let mut roots = [[0u8; 32]; ROOT_SLOTS];
+        let mut items = [0u64; ROOT_SLOTS];
+        for (slot, &byte) in known.iter().enumerate() {
+            roots[slot] = [byte; 32];
+            items[slot] = ethash::dataset_items(first_epoch + slot as u64);
+        }
+        DatasetRoots {
+            first_epoch,
+            roots,
+            items,
+        }
+    }
+
+    #[test]
+    fn test_encode_roots() {
+        let table = roots(7, &[1, 2]);
+        let data = table.encode();
+        assert_eq!(data.len(), DatasetRoots::SIZE);
+        assert_eq!(DatasetRoots::decode(&data).unwrap(), table);
+        assert_eq!(DatasetRoots::decode(&data[1..]), Err(Error::Encoding));
+
+        assert_eq!(table.root(6), None);
+        assert_eq!(table.root(7), Some(([1u8; 32], ethash::dataset_items(7))));
+        assert_eq!(table.root(8), Some(([2u8; 32], ethash::dataset_items(8))));
+        assert_eq!(table.root(9), None);
+        assert_eq!(table.root(7 + ROOT_SLOTS as u64), None);
+    }
+
+    #[test]
+    fn test_update_roots() {
+        let table = roots(7, &[1, 2]);
+        // append
+        table.verify_update(&roots(7, &[1, 2, 3]), None).unwrap();
+        // slide the window up to the oldest header
+        table.verify_update(&roots(8, &[2, 3]), Some(8)).unwrap();
+        table.verify_update(&roots(9, &[3]), Some(9)).unwrap();
+        assert_eq!(
+            table.verify_update(&roots(8, &[2, 3]), None),
+            Err(Error::InvalidDatasetRoots)
+        );
+        assert_eq!(
+            table.verify_update(&roots(9, &[3]), Some(8)),
+            Err(Error::InvalidDatasetRoots)
+        );
+        // known roots are immutable and the window never moves back
+        assert_eq!(
+            table.verify_update(&roots(7, &[1, 3]), None),
+            Err(Error::InvalidDatasetRoots)
+        );
+        assert_eq!(
+            table.verify_update(&roots(8, &[3]), Some(8)),
+            Err(Error::InvalidDatasetRoots)
+        );
+        assert_eq!(
+            table.verify_update(&roots(6, &[0, 1, 2]), None),
+            Err(Error::InvalidDatasetRoots)
+        );
+        // so are their item counts, and appended ones are checked
+        let mut next = roots(7, &[1, 2, 3]);
+        next.items[1] -= 2;
+        assert_eq!(
+            table.verify_update(&next, None),
+            Err(Error::InvalidDatasetRoots)
+        );
+        let mut next = roots(7, &[1, 2, 3]);
+        next.items[2] += 1024;
+        assert_eq!(
+            table.verify_update(&next, None),
+            Err(Error::InvalidDatasetRoots)
+        );
+    }
+}
@@ -0,0 +1,686 @@
+//! Data of the light-client cell.
+//!
+//! The cell keeps the latest `ring_size` headers of the chain it follows, at most `HEADER_SLOTS`,
+//! in a ring buffer, together with the total difficulty of the tip and the `MerkleMountainRange` of
+//! the headers which left the ring, followed by the `DatasetRoots` table, or by the signers in
+//! clique and parlia modes. The data has a fixed size, so the cell keeps its capacity as headers
+//! are appended.
+
+use crate::clique::{Seal, Snapshot};
+use crate::error::Error;
+use crate::header::Header;
+use crate::mmr::MerkleMountainRange;
+use crate::parlia;
+use crate::roots::DatasetRoots;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "std")] {
+    } else {
+        use alloc::vec::Vec;
+    }
+}
+
+/// Headers kept in the ring buffer at most, the `ring_size` of the config picks how many
+pub const HEADER_SLOTS: usize = 32;
+
+/// The fields of a header the light client keeps
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderRecord {
+    pub hash: [u8; 32],
+    pub number: u64,
+    pub receipts_root: [u8; 32],
+    pub difficulty: u128,
+}
+
+impl HeaderRecord {
+    pub const SIZE: usize = 32 + 8 + 32 + 16;
+
+    pub fn new(header: &Header) -> Self {
+        HeaderRecord {
+            hash: header.hash,
+            number: header.number,
+            receipts_root: header.receipts_root,
+            difficulty: header.difficulty,
+        }
+    }
+
+    fn decode(data: &[u8]) -> Self {
+        let mut record = HeaderRecord::default();
+        record.hash.copy_from_slice(&data[..32]);
+        record.number = u64::from_le_bytes(le_bytes(&data[32..40]));
+        record.receipts_root.copy_from_slice(&data[40..72]);
+        record.difficulty = u128::from_le_bytes(le_bytes(&data[72..88]));
+        record
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.hash);
+        out.extend_from_slice(&self.number.to_le_bytes());
+        out.extend_from_slice(&self.receipts_root);
+        out.extend_from_slice(&self.difficulty.to_le_bytes());
+    }
+}
+
+/// Ring buffer of the latest headers of the chain, the newest one is the tip
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderChain {
+    pub tip_total_difficulty: u128,
+    /// Slot of the tip
+    tip: usize,
+    /// Number of slots in use
+    len: usize,
+    /// Number of slots of the ring, the records after them stay unused
+    slots: usize,
+    records: Vec<HeaderRecord>,
+    /// The headers before the oldest kept one
+    history: MerkleMountainRange,
+}
+
+impl HeaderChain {
+    /// Bytes of the encoded chain: the total difficulty as a little endian u128, the slot of
+    /// the tip, the number of slots in use and the number of slots of the ring as little endian
+    /// u32, the slots, and the history
+    pub const SIZE: usize =
+        16 + 4 + 4 + 4 + HeaderRecord::SIZE * HEADER_SLOTS + MerkleMountainRange::SIZE;
+
+    /// Start a chain from a trusted header whose total difficulty is `total_difficulty`
+    pub fn new(record: HeaderRecord, total_difficulty: u128) -> Self {
+        let mut records = Vec::with_capacity(HEADER_SLOTS);
+        records.push(record);
+        records.resize(HEADER_SLOTS, HeaderRecord::default());
+        HeaderChain {
+            tip_total_difficulty: total_difficulty,
+            tip: 0,
+            len: 1,
+            slots: HEADER_SLOTS,
+            records,
+            history: MerkleMountainRange::default(),
+        }
+    }
+
+    /// Keep at most `slots` headers, the `ring_size` of the config, in place of `HEADER_SLOTS`
+    pub fn with_slots(mut self, slots: usize) -> Self {
+        self.slots = slots;
+        self
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let tip = u32::from_le_bytes(le_bytes(&data[16..20])) as usize;
+        let len = u32::from_le_bytes(le_bytes(&data[20..24])) as usize;
+        let slots = u32::from_le_bytes(le_bytes(&data[24..28])) as usize;
+        if slots == 0 || slots > HEADER_SLOTS || tip >= slots || len > slots {
+            return Err(Error::Encoding);
+        }
+        let (records, history) = data[28..].split_at(HeaderRecord::SIZE * HEADER_SLOTS);
+        Ok(HeaderChain {
+            tip_total_difficulty: u128::from_le_bytes(le_bytes(&data[..16])),
+            tip,
+            len,
+            slots,
+            records: records
+                .chunks(HeaderRecord::SIZE)
+                .map(HeaderRecord::decode)
+                .collect(),
+            history: MerkleMountainRange::decode(history)?,
+        })
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.tip_total_difficulty.to_le_bytes());
+        out.extend_from_slice(&(self.tip as u32).to_le_bytes());
+        out.extend_from_slice(&(self.len as u32).to_le_bytes());
+        out.extend_from_slice(&(self.slots as u32).to_le_bytes());
+        for record in &self.records {
+            record.encode(out);
+        }
+        self.history.encode(out);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of headers the ring keeps at most
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    pub fn tip(&self) -> Option<&HeaderRecord> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// The `index`th kept header, from the oldest one
+    pub fn get(&self, index: usize) -> Option<&HeaderRecord> {
+        if index >= self.len {
+            return None;
+        }
+        self.records
+            .get((self.tip + 1 + index + self.slots - self.len) % self.slots)
+    }
+
+    /// The range of the headers which left the ring, the `leaves()` headers before the oldest
+    /// kept one
+    pub fn history(&self) -> &MerkleMountainRange {
+        &self.history
+    }
+
+    /// Check that `hash` is the header of `number` in the chain, either a kept header or a
+    /// leaf of the history whose path to its peak has the `siblings`
+    pub fn verify_inclusion(
+        &self,
+        number: u64,
+        hash: &[u8; 32],
+        siblings: &[[u8; 32]],
+    ) -> Result<(), Error> {
+        let oldest = self.get(0).ok_or(Error::InvalidMmrProof)?;
+        if number >= oldest.number {
+            let record = self.get((number - oldest.number) as usize);
+            if !siblings.is_empty() || record.map(|record| &record.hash) != Some(hash) {
+                return Err(Error::InvalidMmrProof);
+            }
+            return Ok(());
+        }
+        let index = self
+            .history
+            .leaves()
+            .checked_sub(oldest.number - number)
+            .ok_or(Error::InvalidMmrProof)?;
+        self.history.verify(index, hash, siblings)
+    }
+
+    /// Append `header` on top of the tip, the oldest header moves to the history when the ring
+    /// is full
+    pub fn push(&mut self, header: &Header) -> Result<(), Error> {
+        let tip = self.tip().ok_or(Error::InvalidHeaderChain)?;
+        if header.parent_hash != tip.hash || header.number != tip.number + 1 {
+            return Err(Error::InvalidHeaderChain);
+        }
+        self.tip_total_difficulty = self
+            .tip_total_difficulty
+            .checked_add(header.difficulty)
+            .ok_or(Error::InvalidHeader)?;
+        self.tip = (self.tip + 1) % self.slots;
+        if self.len < self.slots {
+            self.len += 1;
+        } else {
+            self.history.push(self.records[self.tip].hash)?;
+        }
+        self.records[self.tip] = HeaderRecord::new(header);
+        Ok(())
+    }
+
+    /// Drop the headers after the `len` oldest ones
+    fn truncate(&mut self, len: usize) {
+        while self.len > len {
+            self.tip_total_difficulty -= self.records[self.tip].difficulty;
+            self.tip = (self.tip + self.slots - 1) % self.slots;
+            self.len -= 1;
+        }
+    }
+
+    /// Apply a branch of headers whose first header is the child of a kept header.
+    ///
+    /// A branch on top of the tip extends the chain. A branch forking below the tip replaces
+    /// the orphaned headers when its total difficulty is higher than the one of the tip, ties
+    /// keep the current tip. Forks deeper than the kept headers are rejected.
+    pub fn apply_branch<'a, I>(&mut self, branch: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = &'a Header>,
+    {
+        let mut branch = branch.into_iter().peekable();
+        let parent_hash = branch.peek().ok_or(Error::Encoding)?.parent_hash;
+        let fork = (0..self.len)
+            .rev()
+            .find(|&index| self.get(index).map(|record| record.hash) == Some(parent_hash))
+            .ok_or(Error::UnknownParent)?;
+        let mut next = self.clone();
+        next.truncate(fork + 1);
+        for header in branch {
+            next.push(header)?;
+        }
+        if fork + 1 < self.len && next.tip_total_difficulty <= self.tip_total_difficulty {
+            return Err(Error::StaleBranch);
+        }
+        *self = next;
+        Ok(())
+    }
+}
+
+/// Data of the light-client cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightClientState {
+    pub headers: HeaderChain,
+    pub roots: DatasetRoots,
+}
+
+impl LightClientState {
+    pub const SIZE: usize = HeaderChain::SIZE + DatasetRoots::SIZE;
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        Ok(LightClientState {
+            headers: HeaderChain::decode(&data[..HeaderChain::SIZE])?,
+            roots: DatasetRoots::decode(&data[HeaderChain::SIZE..])?,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::SIZE);
+        self.headers.encode(&mut data);
+        data.extend_from_slice(&self.roots.encode());
+        data
+    }
+}
+
+/// Data of the light-client cell in clique mode.
+///
+/// The headers start the data as in `LightClientState`. The snapshot is the one of the oldest
+/// kept header, and the seals are the ones of the kept headers from the oldest one, so a branch
+/// forking from any kept header is verified against the snapshot of its parent without
+/// recovering the signers of the kept headers again. The seal of the oldest header is in the
+/// snapshot already, it is zeros for the checkpoint the cell starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliqueClientState {
+    pub headers: HeaderChain,
+    pub snapshot: Snapshot,
+    pub seals: Vec<Seal>,
+}
+
+impl CliqueClientState {
+    pub const SIZE: usize = HeaderChain::SIZE + Snapshot::SIZE + Seal::SIZE * HEADER_SLOTS;
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let (headers, data) = data.split_at(HeaderChain::SIZE);
+        let (snapshot, seals) = data.split_at(Snapshot::SIZE);
+        let headers = HeaderChain::decode(headers)?;
+        let (seals, unused) = seals.split_at(Seal::SIZE * headers.len());
+        if unused.iter().any(|&byte| byte != 0) {
+            return Err(Error::Encoding);
+        }
+        let seals = seals
+            .chunks(Seal::SIZE)
+            .map(Seal::decode)
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(CliqueClientState {
+            headers,
+            snapshot: Snapshot::decode(snapshot)?,
+            seals,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::SIZE);
+        self.headers.encode(&mut data);
+        self.snapshot.encode(&mut data);
+        for seal in &self.seals {
+            seal.encode(&mut data);
+        }
+        data.resize(Self::SIZE, 0);
+        data
+    }
+}
+
+/// Data of the light-client cell in parlia mode.
+///
+/// The headers start the data as in `LightClientState`. As in `CliqueClientState`, the snapshot
+/// is the one of the oldest kept header, and the signers are the ones of the kept headers from
+/// the oldest one. The listed validators are the ones of the epoch header kept after the
+/// oldest one, if any, zeros otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParliaClientState {
+    pub headers: HeaderChain,
+    pub snapshot: parlia::Snapshot,
+    pub signers: Vec<[u8; 20]>,
+    pub listed: Option<parlia::ValidatorSet>,
+}
+
+impl ParliaClientState {
+    pub const SIZE: usize =
+        HeaderChain::SIZE + parlia::Snapshot::SIZE + 20 * HEADER_SLOTS + parlia::ValidatorSet::SIZE;
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != Self::SIZE {
+            return Err(Error::Encoding);
+        }
+        let (headers, data) = data.split_at(HeaderChain::SIZE);
+        let (snapshot, data) = data.split_at(parlia::Snapshot::SIZE);
+        let (signers, listed) = data.split_at(20 * HEADER_SLOTS);
+        let headers = HeaderChain::decode(headers)?;
+        let (signers, unused) = signers.split_at(20 * headers.len());
+        if unused.iter().any(|&byte| byte != 0) {
+            return Err(Error::Encoding);
+        }
+        Ok(ParliaClientState {
+            headers,
+            snapshot: parlia::Snapshot::decode(snapshot)?,
+            signers: signers
+                .chunks(20)
+                .map(|data| {
+                    let mut signer = [0u8; 20];
+                    signer.copy_from_slice(data);
+                    signer
+                })
+                .collect(),
+            listed: parlia::ValidatorSet::decode(listed)?,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::SIZE);
+        self.headers.encode(&mut data);
+        self.snapshot.encode(&mut data);
+        for signer in &self.signers {
+            data.extend_from_slice(signer);
+        }
+        data.resize(Self::SIZE - parlia::ValidatorSet::SIZE, 0);
+        parlia::ValidatorSet::encode(self.listed.as_ref(), &mut data);
+        data
+    }
+}
+
+fn le_bytes<A: AsMut<[u8]> + Default>(data: &[u8]) -> A {
+    let mut bytes = A::default();
+    bytes.as_mut().copy_from_slice(data);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::*;
+
+    fn record(number: u64) -> HeaderRecord {
+        HeaderRecord {
+            hash: [number as u8; 32],
+            number,
+            receipts_root: [0xaa; 32],
+            difficulty: 1000 + number as u128,
+        }
+    }
+
+    /// A header extending `record`
+    fn child(record: &HeaderRecord) -> Header {
+        let mut header = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        header.parent_hash = record.hash;
+        header.number = record.number + 1;
+        header.hash = [header.number as u8; 32];
+        header
+    }
+
+    #[test]
+    fn test_encode_state() {
+        let mut headers = HeaderChain::new(record(0), 1000);
+        headers.push(&child(&record(0))).unwrap();
+        let state = LightClientState {
+            headers,
+            roots: DatasetRoots::decode(&[7u8; DatasetRoots::SIZE]).unwrap(),
+        };
+        let data = state.encode();
+        assert_eq!(data.len(), LightClientState::SIZE);
+        assert_eq!(LightClientState::decode(&data).unwrap(), state);
+        assert_eq!(LightClientState::decode(&data[1..]), Err(Error::Encoding));
+        let mut data = data;
+        data[16] = HEADER_SLOTS as u8;
+        assert_eq!(LightClientState::decode(&data), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_encode_clique_state() {
+        let mut headers = HeaderChain::new(record(0), 1000);
+        headers.push(&child(&record(0))).unwrap();
+        let seal = Seal {
+            signer: [1u8; 20],
+            candidate: [2u8; 20],
+            authorize: true,
+        };
+        let state = CliqueClientState {
+            headers,
+            snapshot: Snapshot::new(vec![[1u8; 20], [3u8; 20]]).unwrap(),
+            seals: vec![Seal::default(), seal],
+        };
+        let data = state.encode();
+        assert_eq!(data.len(), CliqueClientState::SIZE);
+        assert_eq!(CliqueClientState::decode(&data).unwrap(), state);
+        assert_eq!(CliqueClientState::decode(&data[1..]), Err(Error::Encoding));
+        // a seal per kept header
+        let mut data = data;
+        data[CliqueClientState::SIZE - 1] = 1;
+        assert_eq!(CliqueClientState::decode(&data), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_encode_parlia_state() {
+        let mut headers = HeaderChain::new(record(0), 1000);
+        headers.push(&child(&record(0))).unwrap();
+        let validators = parlia::ValidatorSet::new(vec![[1u8; 20], [3u8; 20]], 1).unwrap();
+        let state = ParliaClientState {
+            headers,
+            snapshot: parlia::Snapshot::new(validators.clone()),
+            signers: vec![[1u8; 20], [3u8; 20]],
+            listed: Some(validators),
+        };
+        let data = state.encode();
+        assert_eq!(data.len(), ParliaClientState::SIZE);
+        assert_eq!(ParliaClientState::decode(&data).unwrap(), state);
+        assert_eq!(ParliaClientState::decode(&data[1..]), Err(Error::Encoding));
+        // a signer per kept header
+        let mut data = data;
+        data[ParliaClientState::SIZE - parlia::ValidatorSet::SIZE - 1] = 1;
+        assert_eq!(ParliaClientState::decode(&data), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_push_header() {
+        let genesis = Header::decode(&decode_hex(MAINNET_GENESIS)).unwrap();
+        let block_1 = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+        let mut headers = HeaderChain::new(HeaderRecord::new(&genesis), genesis.difficulty);
+        headers.push(&block_1).unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers.tip(), Some(&HeaderRecord::new(&block_1)));
+        assert_eq!(
+            headers.tip_total_difficulty,
+            genesis.difficulty + block_1.difficulty
+        );
+        // not on top of the tip
+        assert_eq!(headers.push(&block_1), Err(Error::InvalidHeaderChain));
+        let mut orphan = child(&record(1));
+        orphan.parent_hash = block_1.hash;
+        orphan.number = 3;
+        assert_eq!(headers.push(&orphan), Err(Error::InvalidHeaderChain));
+    }
+
+    /// A chain of `len` headers from `record(0)`, with headers of `difficulty`
+    fn chain_of(len: usize, difficulty: u128) -> HeaderChain {
+        let mut headers = HeaderChain::new(record(0), 1000);
+        for _ in 1..len {
+            let mut header = child(headers.tip().unwrap());
+            header.difficulty = difficulty;
+            headers.push(&header).unwrap();
+        }
+        headers
+    }
+
+    /// A branch of `len` headers forking on top of `record`, marked with `fork` in their hashes
+    fn branch(record: &HeaderRecord, len: usize, difficulty: u128, fork: u8) -> Vec<Header> {
+        let mut branch: Vec<Header> = Vec::new();
+        for _ in 0..len {
+            let parent = branch.last().map(HeaderRecord::new).unwrap_or(*record);
+            let mut header = child(&parent);
+            header.hash[0] = fork;
+            header.difficulty = difficulty;
+            branch.push(header);
+        }
+        branch
+    }
+
+    #[test]
+    fn test_apply_branch() {
+        let headers = chain_of(5, 100);
+        assert_eq!(headers.tip_total_difficulty, 1400);
+
+        // extend the tip
+        let mut next = headers.clone();
+        next.apply_branch(&branch(headers.tip().unwrap(), 2, 100, 0xf0))
+            .unwrap();
+        assert_eq!(next.len(), 7);
+        assert_eq!(next.tip_total_difficulty, 1600);
+
+        // reorg the 2 latest headers with a heavier branch
+        let fork = headers.get(2).unwrap();
+        let mut next = headers.clone();
+        next.apply_branch(&branch(fork, 2, 101, 0xf0)).unwrap();
+        assert_eq!(next.len(), 5);
+        assert_eq!(next.tip_total_difficulty, 1402);
+        assert_eq!(next.tip().unwrap().hash[0], 0xf0);
+        assert_eq!(next.get(2), Some(fork));
+        // a longer branch wins with the same difficulty per header
+        let mut next = headers.clone();
+        next.apply_branch(&branch(fork, 3, 100, 0xf0)).unwrap();
+        assert_eq!(next.tip().unwrap().number, 5);
+
+        // ties and lighter branches keep the current tip
+        let mut next = headers.clone();
+        assert_eq!(
+            next.apply_branch(&branch(fork, 2, 100, 0xf0)),
+            Err(Error::StaleBranch)
+        );
+        assert_eq!(
+            next.apply_branch(&branch(fork, 1, 199, 0xf0)),
+            Err(Error::StaleBranch)
+        );
+        assert_eq!(next, headers);
+        // the branch must link
+        let mut broken = branch(fork, 2, 200, 0xf0);
+        broken[1].parent_hash = [0xee; 32];
+        assert_eq!(next.apply_branch(&broken), Err(Error::InvalidHeaderChain));
+        assert_eq!(next.apply_branch(&[]), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn test_deep_reorg() {
+        let headers = chain_of(HEADER_SLOTS + 3, 100);
+        let oldest = *headers.get(0).unwrap();
+        assert_eq!(oldest.number, 3);
+        // the oldest kept header can be the fork point
+        let mut next = headers.clone();
+        next.apply_branch(&branch(&oldest, HEADER_SLOTS, 100, 0xf0))
+            .unwrap();
+        assert_eq!(next.len(), HEADER_SLOTS);
+        assert_eq!(next.get(0).unwrap().number, 4);
+        assert_eq!(
+            next.tip_total_difficulty,
+            headers.tip_total_difficulty + 100
+        );
+        // the parent of the fork point is dropped already
+        let dropped = HeaderRecord {
+            hash: [2u8; 32],
+            number: 2,
+            ..oldest
+        };
+        let mut next = headers.clone();
+        assert_eq!(
+            next.apply_branch(&branch(&dropped, HEADER_SLOTS + 2, 100, 0xf0)),
+            Err(Error::UnknownParent)
+        );
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut headers = HeaderChain::new(record(0), 1000);
+        for number in 0..HEADER_SLOTS as u64 + 5 {
+            let tip = *headers.tip().unwrap();
+            assert_eq!(tip.number, number);
+            headers.push(&child(&tip)).unwrap();
+        }
+        assert_eq!(headers.len(), HEADER_SLOTS);
+        assert_eq!(headers.get(0).unwrap().number, 6);
+        assert_eq!(headers.tip().unwrap().number, HEADER_SLOTS as u64 + 5);
+        assert_eq!(headers.get(HEADER_SLOTS), None);
+        for index in 1..HEADER_SLOTS {
+            assert_eq!(
+                headers.get(index).unwrap().number,
+                headers.get(index - 1).unwrap().number + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_ring_size() {
+        let mut headers = HeaderChain::new(record(0), 1000).with_slots(4);
+        for number in 0..10 {
+            let tip = *headers.tip().unwrap();
+            assert_eq!(tip.number, number);
+            headers.push(&child(&tip)).unwrap();
+        }
+        assert_eq!(headers.slots(), 4);
+        assert_eq!(headers.len(), 4);
+        assert_eq!(headers.get(0).unwrap().number, 7);
+        assert_eq!(headers.get(4), None);
+        assert_eq!(headers.history().leaves(), 7);
+
+        let mut data = Vec::new();
+        headers.encode(&mut data);
+        assert_eq!(HeaderChain::decode(&data).unwrap(), headers);
+        // the tip and the slots in use are within the ring
+        for &(offset, value) in &[(16, 4), (20, 5), (24, 0), (24, HEADER_SLOTS as u8 + 1)] {
+            let mut data = data.clone();
+            data[offset] = value;
+            assert_eq!(HeaderChain::decode(&data), Err(Error::Encoding));
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let headers = chain_of(HEADER_SLOTS + 3, 100);
+        // the 3 headers before the oldest kept one are in the history
+        assert_eq!(headers.history().leaves(), 3);
+        headers
+            .verify_inclusion(0, &[0u8; 32], &[[1u8; 32]])
+            .unwrap();
+        headers
+            .verify_inclusion(1, &[1u8; 32], &[[0u8; 32]])
+            .unwrap();
+        headers.verify_inclusion(2, &[2u8; 32], &[]).unwrap();
+        assert_eq!(
+            headers.verify_inclusion(1, &[0u8; 32], &[[1u8; 32]]),
+            Err(Error::InvalidMmrProof)
+        );
+        // kept headers need no proof
+        let tip = headers.tip().unwrap();
+        headers
+            .verify_inclusion(tip.number, &tip.hash, &[])
+            .unwrap();
+        headers.verify_inclusion(3, &[3u8; 32], &[]).unwrap();
+        assert_eq!(
+            headers.verify_inclusion(3, &[3u8; 32], &[[2u8; 32]]),
+            Err(Error::InvalidMmrProof)
+        );
+        assert_eq!(
+            headers.verify_inclusion(tip.number + 1, &tip.hash, &[]),
+            Err(Error::InvalidMmrProof)
+        );
+
+        // a reorg from the oldest kept header moves it to the history
+        let mut next = headers.clone();
+        let oldest = *headers.get(0).unwrap();
+        next.apply_branch(&branch(&oldest, HEADER_SLOTS, 100, 0xf0))
+            .unwrap();
+        assert_eq!(next.history().leaves(), 4);
+        assert_eq!(
+            next.verify_inclusion(3, &[3u8; 32], &[]),
+            Err(Error::InvalidMmrProof)
+        );
+    }
+}
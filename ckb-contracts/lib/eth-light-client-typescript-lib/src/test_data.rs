@@ -0,0 +1,877 @@
+//! Ethereum mainnet data used by the tests, and the synthetic clique and parlia chains of the
+//! tests of the clique and parlia modes
+
+use crate::clique::{self, EXTRA_SEAL, EXTRA_VANITY};
+use crate::config::{CliqueConfig, ParliaConfig};
+use crate::difficulty::EMPTY_UNCLES_HASH;
+use crate::ethash::{dataset_items, merkle_depth, DagProof};
+use crate::hash::keccak256;
+use crate::header::Header;
+use crate::parlia::{self, ValidatorSet};
+use std::collections::HashMap;
+
+/// RLP of the mainnet genesis header, its hash is `MAINNET_GENESIS_HASH`
+pub const MAINNET_GENESIS: &str = concat!(
+    "f90214a00000000000000000000000000000000000000000000000000000000000000000a01dcc4de8dec75d",
+    "7aab85b567b6ccd41ad312451b948a7413f0a142fd40d4934794000000000000000000000000000000000000",
+    "0000a0d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544a056e81f171bcc55a6",
+    "ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a056e81f171bcc55a6ff8345e692c0f86e5b48e0",
+    "1b996cadc001622fb5e363b421b9010000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000850400000000808213888080a011bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cb",
+    "db7a38e1e50b1b82faa000000000000000000000000000000000000000000000000000000000000000008800",
+    "00000000000042",
+);
+pub const MAINNET_GENESIS_HASH: &str =
+    "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3";
+
+/// RLP of the mainnet header 1, its hash is `MAINNET_BLOCK_1_HASH`
+pub const MAINNET_BLOCK_1: &str = concat!(
+    "f90211a0d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3a01dcc4de8dec75d",
+    "7aab85b567b6ccd41ad312451b948a7413f0a142fd40d493479405a56e2d52c817161883f50c441c3228cfe5",
+    "4d9fa0d67e4d450343046425ae4271474353857ab860dbc0a1dde64b41b5cd3a532bf3a056e81f171bcc55a6",
+    "ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a056e81f171bcc55a6ff8345e692c0f86e5b48e0",
+    "1b996cadc001622fb5e363b421b9010000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+    "00000000000000008503ff80000001821388808455ba422499476574682f76312e302e302f6c696e75782f67",
+    "6f312e342e32a0969b900de27b6ac6a67742365dd65f55a0526c41fd18e1b16f1a1215c2e66f5988539bd497",
+    "9fef1ec4",
+);
+pub const MAINNET_BLOCK_1_HASH: &str =
+    "88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6";
+
+/// The 64 dataset items hashimoto reads for `MAINNET_BLOCK_1`, as (index, item)
+pub const MAINNET_BLOCK_1_DAG_ITEMS: [(u64, &str); 64] = [
+    (
+        5422682,
+        concat!(
+            "a80f6471d1005e44d3a04844dbaf14eb29d39525fcd09a1528e104f3b4e74929",
+            "de635ae66b1049c843bb4d5b71ea821094d82501354a6170ac5bbc93a6555112",
+            "6d9d5bf3c3b87b6ed651caa07730980dbf00f053463391dd86ad6b46c3195741",
+            "257279b0fc6e6ffa378030bf170611408a59813d6edad3236e560a83c403c0c7",
+        ),
+    ),
+    (
+        9085,
+        concat!(
+            "76a07646ff60af33adac13cca2f9ccfa984f59ccea6804ac50bca5af8bdee190",
+            "0832e0169c39a1e09426b10ed9f4784030d6329e6280d1c4334deae1003db1c8",
+            "cc9a7b130c21e4f200b78eb717971c00a971fc1c34751b045e70edb37bd7fe3b",
+            "cbca7ffce5b4c59ce9571e4db02c3da7ee185232fd2367990a51541ce74eb5af",
+        ),
+    ),
+    (
+        2989967,
+        concat!(
+            "b8a016d44163dd3174fe295175b7ad37b3a0c5a7011e8060f3b2eb3d8b82716c",
+            "4c0606b15e7ece9523bf468e42c0c8a396044989395b80441bc187ef760d6777",
+            "1292e8bb354ced50ec36a5b3952165c0ad94b903b4d537346cf57f992a59b283",
+            "410148d5c4c35f59f30188f825ff8f3328fd3bc7d498f4c80956788084cc21ba",
+        ),
+    ),
+    (
+        1781707,
+        concat!(
+            "4706db88ad551c32021534a8fad05aaff2dbd9221a1f970e37a6ab46cf28dcbe",
+            "edb7670ccbc1a69939032cddaefc5b2d0dd78d6fbe38c1f7bc1c8dc52d351932",
+            "71d197a8b486fafe8f54712e107e726ae9b5b32b0b1c7f968d772be7ad9f93c9",
+            "4b0d33c64ff51b1a4bbfaf7edcd05ae8c4a87003164acdb7885d32900a10a390",
+        ),
+    ),
+    (
+        6051640,
+        concat!(
+            "0f13c7b196dd2a8e76e8c82ddc3f0bd9d6101d705c6bba517927828a78210c5f",
+            "9855cf2a5be4bacfef152d351c662a90b98e981356b304ba6a1e68098f93f71b",
+            "7c2fe8de3b225bbf186ec25433f31def7f38c8bc1ef714de455b2bdc5b2ce48d",
+            "3ad1e4b81a1cee80df9a8601dd164ac9f43e73997a57ece02408f9cbdc9d1db5",
+        ),
+    ),
+    (
+        3601608,
+        concat!(
+            "cf97ce2e37f92a9cfd3ca4fa11cb80861b42ee8f7be3b5b40d54c7464514e2ec",
+            "991e677285342e4f2550e71f949a36b433fc775d801cd09935ac64dc84e8e18c",
+            "4c549f30262e5194cdc31bf6a8f403858f1d08bd10bafc0814473d132437bd95",
+            "dd763594a33c24170bf903501d476803d88fac22b51d3fd5427ecc0978b48ba5",
+        ),
+    ),
+    (
+        2643136,
+        concat!(
+            "17dcfa84342bca62df8988701ab2778856a109d609e1cd7aed2bcb3d0d80fd40",
+            "756bcd685dc96735388ef38afdf19a9528b5486507dc57f39296aed2a3e0211a",
+            "b88cd19eb07529e4e5d70bf55cffa34b9e47052e5ee61c137470ebd03db05365",
+            "3be4c96ddb4e0cbd951e5de04cbfaf5971dc6aac858f6c89b1be20d20deb057b",
+        ),
+    ),
+    (
+        7835986,
+        concat!(
+            "b50d0115215950e408ca0da0669ae7dacd0fe3e4930690a2a8941ed956ecbe67",
+            "b97566a95ea5a5145d983906cf9ee190e1ad3e750f55202245465b03fe5caacd",
+            "077135616b00ece2e8652e97ea624a1f2b1d6602d4c3947f9353271ca8d7e54e",
+            "db5bc574c6fe2aa19af7aba2ab9aeeacd365ba3ba04b477d390b916b85cffd2f",
+        ),
+    ),
+    (
+        2149120,
+        concat!(
+            "60c848fe806abc1b5502c7b8e1fabf80ea08c9c9ff71f0b6244f64c6b725f445",
+            "f509f2e16843b712b1bff39c52892c201f708e48b1dd3b6d26cfa526ab0adca8",
+            "c3c04bc6cb358839c1aca4faf9c19ace9735bdd62de8e2672ca540a662020ed6",
+            "aea6baa85584fae8f40098e9ba866b343882d69e1e66ce5a7fc2ba75f4977c90",
+        ),
+    ),
+    (
+        7339031,
+        concat!(
+            "1917e3bf106144918ca1dc5b2ebe43e6870378f5b38860e2382dedae4e5a987d",
+            "4338f23dd5c7ec6e8d00fd459ec517b79a32df359d26ea62991166d2eb8d9487",
+            "698ae65cb82ec2c0f3cbf2ad18808406b73f33f4c0396b6705a3c7373c64d473",
+            "ac507374528a709441cf354177f1be3ac57f892c733345472d38cd7bc014075c",
+        ),
+    ),
+    (
+        707402,
+        concat!(
+            "57508f963e863d22c800a7434771e2872548747f782df46d4aa81c1db9beb326",
+            "db7eb999d4353cac88d9b6a9761342fc6975cc881ede5f2477e444a606b5e37d",
+            "6aa3bd66ed95ad59b1e7da5d97dbdbd22e318856a168eea33ecc809bd72390fc",
+            "5e2232ad55ca613fb2534403d08140fb806c415afc3fa291e4c4bbb10f856ca5",
+        ),
+    ),
+    (
+        1000418,
+        concat!(
+            "7a85de0523513423c785723580b565be9d560d770b1c4a5d89dca49f5d39bd6b",
+            "e3c88c2454233f503b16c857b9e5fd4e0c52cd9f8e097de677cce63bba26f753",
+            "cf7a6c4cb12991461c5b68d4f71e8b81aa56514d2503a4aaea86ca32de4dbc65",
+            "c7eecf2e292ee3b3ac88b0ed23bf928b81d40b8ae085dacb141e79a893f7bc63",
+        ),
+    ),
+    (
+        7570908,
+        concat!(
+            "31405fe923a086c0067f92fe1659fb0b0033cd155d2897fce6233e320f44756e",
+            "ffd5c633b07552fd2fc6ce8c6606149a6027372f3f897ffe01ac9127dc2c959d",
+            "b391a0d9f55ae98a4c59a6c3f7a4e6cf1deb6c5c79139789b5742c2099b8f95c",
+            "cee71bf0fa5b6aea18e5245195391db108ddebaa1777b9afcce20f98f5be8ae2",
+        ),
+    ),
+    (
+        4557514,
+        concat!(
+            "a6317d7604c8b7d5a3acbf91c1e494d702936188bf104fad16d174b464f37640",
+            "30f1fb6081137404e93bf7830e4b7bb909730a4a88ee564b510623cc0e59b143",
+            "789ed7da93363a5fc7852e6bd3659e48c8fe909fd8729c6e9f71ab51516dfcf1",
+            "3c5b10b3b060a523da5bb7968b05c1e372ea1b22fd8dbdfce7a6d1075deb198b",
+        ),
+    ),
+    (
+        4299649,
+        concat!(
+            "82c5ea442e2c5974f44517abbed2b963b2c1d7f6cbdbc49b22dbe7df5ca44199",
+            "bf09b137cf360f776ce4975e819e5e03ec5bb7fe6748cc93d6ca1b3e22176cac",
+            "9f2a6a1f52cd99c363dd7c0b1c5d1e2ac300c849a915a7544082aad569768736",
+            "ccce65a85e99023e57a0e9e25b888a9844d75570ed2e958a6a8b1fc3d93a3bdf",
+        ),
+    ),
+    (
+        4588619,
+        concat!(
+            "d81aa20a5a31191ae43a75b2c7314fee21d07eac4dffb7c3eff1a568dde07838",
+            "1e1f2da9d3d94417048c030859e7927d45a1919f568537864f3b303cd6913c42",
+            "6bf5d7aff6622c8e404e15cdb29259b4f287ed2b85b062808d0e1924ed5c8ce0",
+            "b6423414bf0a3510850fd7c4791d5c80a749a6217058def4e8cf9e80736d166e",
+        ),
+    ),
+    (
+        7017766,
+        concat!(
+            "63ef57ff388ee710dbe64d1a158d10504e7f5f5c748e6699f803f335c3be8409",
+            "ff246ab212056cc8f08b68f371a3ea0d7f1c8ad7a6f654991ccaa291fc9dd26f",
+            "c6abab1adfec8b306762d13c1359611e59bc9f5a494db38e4ac67113bcb18350",
+            "38a25c89ee37a74a697ed9afb1de9eedb25bbaeb7c71fdcc3f4feba56ea9bcd8",
+        ),
+    ),
+    (
+        3137362,
+        concat!(
+            "e9e8ca1caf0ff5e3895b22b28084d87c29a5a0c49ae02df436528abecbe8637f",
+            "41f3efd9734006fcf42bb3381e5bf1a7640d12eeab6b393762be1b0e626f63df",
+            "c3365a8a7aaa026b8a62cccb71ec3c3ed5d9ffe404bcb1fba5a8385323fcd2ff",
+            "3584e4a162c3fa9d4865eedd60c6efd19640440344cc8c204c47d3a224dc6daa",
+        ),
+    ),
+    (
+        1932488,
+        concat!(
+            "8b54532a566a3dc4a26da670d797eaeb261ee34f7dc35430a0a8f6ed7f2bb279",
+            "d0e3e4d8b87bb967516b7128c517c8dc7137ff681fd8d73a4f248513b87e6fe0",
+            "db624e0bed6223f18a1438a017aebcf8f09eaeb07d992d3e68ff9c52005f683a",
+            "f18a2b4126cb42702ddcda8049ede01ad7cf6a9f7ac4589538a1a5be59f02d92",
+        ),
+    ),
+    (
+        1875687,
+        concat!(
+            "dbee17585e5d3dbe245f010952426ff429edc2ce963944125a717c2f9f9b8e6f",
+            "0315e66634bf2305ac3dacd8b504ae23e0f04396b9a22e8943648b440dcb4863",
+            "6aefd1284710fe2eef0efb3694d93b329025864e134014e380d359d75918afa4",
+            "bfa3de4b81a29be8040e1d1691d896d9f731636859684a35cc62ba84929ca8fb",
+        ),
+    ),
+    (
+        3679787,
+        concat!(
+            "5c5b9b7e91755ffb9fb5772a55fd54836614a23e8245b5b6e203f9ea22fd5c2d",
+            "5d71d85d83c9deabca4275b26323ac07230777313127d5dc7f4dc7761455628d",
+            "91e579eb8eb7d023bc55423330067aeb4c07766f8c035a9ff35714f55f083856",
+            "7b03788c536f17ef3c9e480b2561c232eadf94d162d5ee2068a8f34c9415305a",
+        ),
+    ),
+    (
+        1938325,
+        concat!(
+            "86b23c219ed0f9feb52879bff8c9fc9bbc4b39acd850d019bd1be9a2bfea4d4e",
+            "1f46c533f22b67acba15db4f12789e33633be6acfb43759eea9d258ff3f79600",
+            "812d340ae4152a9d667b6d04e6372469c2467d83191702da611dd2af18c0eb7c",
+            "ddd4989d51a0faa71aab7a60d8c466b1dd612b0af8b2c069a9e14786a282e098",
+        ),
+    ),
+    (
+        8287608,
+        concat!(
+            "c12c3fc708ac703fbd596b0b9348e0a2ae9c6db6b15108df4c6a21e4cd68fac7",
+            "158c4fdd81e3a599d7bb21685b8bba09fb010c12999e5a851679a4804a6211cd",
+            "7336bad6792dc792f11878a64b919f37a2ef87af12e5541dbf258a81696f745f",
+            "4ede697c155c493637631c2e42c3c3f1a5fb0f88dde5bfbe3b930e269c72925c",
+        ),
+    ),
+    (
+        523213,
+        concat!(
+            "b5b7245698224c5412278e00d1799c7b27a0688a40ead8f4d7206ab88a8a2149",
+            "5b541bf2f9663f268ee9c4094759b328ca4890a04414ef90ad80ef9210ccaa79",
+            "f92c35302940c632d49c4e68d7e29c063f2a2b3daa9a73e07bb93da8b2723d60",
+            "458fc2c73d19013ea6fc52188a7372a6d38a0f73db76bebb4336075e2c0be10b",
+        ),
+    ),
+    (
+        214329,
+        concat!(
+            "a859aaf2bb3a0d53f0251837cee9c457b38087b0b24918981ff56c5fcda4c7d3",
+            "7d50ce9375bdf81d5ce52be577a97bac5224b8feaec4939a39402a601ccc8128",
+            "a575d9ae79574c81ac617ff1c26ad7cd88d3e3c2eedcec251cfbd517749a1fd9",
+            "38de14685b466dbd320b4d05068fc2c49fb49a7555b4cf7a6e91f5336b53c2c6",
+        ),
+    ),
+    (
+        2494492,
+        concat!(
+            "1e9dc42e2347d7c18bd0eab19f01cc1773cef41a4bb644e7c0d6b13138559978",
+            "8b51ce40a04cd8ee16bace6a5aa3e9ed061c646e662b18a1e87eea1d4e1b9022",
+            "1e489aaa797a4f0700f712317be298fa835206244f2751af68526861c19c5bba",
+            "a89c06d77f0d4e110b8405741e5acac556c49e4f2aa516a34ee9dbba47d4c14f",
+        ),
+    ),
+    (
+        6920852,
+        concat!(
+            "3ad9f70530e9e8310ba87339bf98dfe84bd3ac6d8f8d132d212a1fb9ef860e39",
+            "48cb60483598ebd5364739929374a5cf4705189fd310390982357d5238e483ac",
+            "f9bc6a4da704df32d6cbef187ccf9612a0949ae7f601254511ad38c2a29f090e",
+            "524dd8503eb9368fdc5d615da6ed3447bf23f43509211a95948782e312c4b7bb",
+        ),
+    ),
+    (
+        3988220,
+        concat!(
+            "c24c30ffd2e6a13741127696eeca13e49044b91341d0425fd502c88864c58543",
+            "4b5946f58350dc82c56390f01205369ca8724a1625171024fd927b45cd6c0d7b",
+            "16ae23b4566441f3a837e1c9186144dd4f89dd3199696a3fdfd0044460a80179",
+            "a6036c9129d4486b1a6beff94a2b68d2d4e55fc8f712a2062fb6f66c2f81eb5d",
+        ),
+    ),
+    (
+        8061922,
+        concat!(
+            "2eff911cdd96a9b600a25b90e4ae45e321781fe8996528686daf1fa434446da5",
+            "43b2e5cb1fa1d0e340b02de323108499a835b73868d7dac705a1184f0878e892",
+            "6d7abfa53a829ddf73f61004e44e184e4b7710afe665de8cf224b5ae98cea695",
+            "b5b10fcdb20a280a3acbaddc2889ed7bf8fab5490e1ef36c56a1ef8a7d08a471",
+        ),
+    ),
+    (
+        4405605,
+        concat!(
+            "9b81acd6521544d5d1eda863778c3baa6d52ab68b729fa7bc227fbf93ac66c18",
+            "5b92b12f6408bed2739c279bb5e1c89c743463a64e7c7b8f78db6963d033bf72",
+            "aa060eaf4333adaf5123e980b45e48aed18dbbb10b58b65b9d852a4e2f331322",
+            "8c6e732d598ebbb13788638eb905e9857605c59842771b7b29a066a7cda60c86",
+        ),
+    ),
+    (
+        8287293,
+        concat!(
+            "43ae4f4028042d0852237a30268c71e549281b1a98faeea07ce8fd8705b9b253",
+            "c9d683c4e3c6a57523f95860f395f805bb7b33f5f82a2021fa9d242e2796dede",
+            "120c6328700a8e9380394f5c156309f4742cd15b2cd645d83c88fb0141cab38b",
+            "c80f3c16281913fd6ebb5ac5a610479beec29c6dfd8fc3f816b069baa6df4081",
+        ),
+    ),
+    (
+        8076894,
+        concat!(
+            "ad32452b975883e36c0924abda28697f0f9281621c7183abd77fe35346349222",
+            "dff35d743d42048fc6611d5cdc2f2e33348b141e5149d839ab0f6ade982d0330",
+            "53319c7a91739cf6f7ac3f2329c5f53ac0542c68e634b38067e3e95634cd164d",
+            "2cc01678c7947bd2590ee77fd54f71bf0abb7fc14faef20490ec85f3c2fbb82f",
+        ),
+    ),
+    (
+        7788898,
+        concat!(
+            "15686c2b5f54b99d2e5b837e3f3e8e201326ded3a23e6a36ddb5b3e64891b1f5",
+            "603eefc8a9ce7cc2cf78a032bbb183c3131fe95fdceee359cc5fa9c645b320eb",
+            "5a7fd7887ae9f95f9fbe155185261f1a0ef78258b2abaa81c0921a195f343250",
+            "b860917c7707a21723eef3675cba35c1c3416d42028f071a4b406a28b585ed2a",
+        ),
+    ),
+    (
+        3330713,
+        concat!(
+            "3283f340d69d6cc55e21b33366fbd5083385e22af34f7372cb895b4775f9b71c",
+            "70f7818d73a959f35a8d0a75029e6d57277dd44f280b7da876bc765743895e1f",
+            "96dfaa713b1aabf766ad3c84f72ebedc75458a61cdbe425005f60e2bcc82eb63",
+            "eef0bc7d3bcbc00266c00c3128cdb4a7007888755fbd7ea1a94c909e32b5b3e2",
+        ),
+    ),
+    (
+        5091840,
+        concat!(
+            "de3305397ac5b20af950ba7061e8ae1bfb408c3da8242010effe9ce6cbe0d239",
+            "a514fb96ae058f5c414723fb85cb9cba21b60ea4ac03bae5f5bdeb28306af460",
+            "10fbb2eff496a2848b7dfbd217c1e549b3af74b7f40b0a459478414f09d86ad8",
+            "cad64f02eb445971b7cf450e561a6256a08eb29854456d0fd9eb8de6876045e5",
+        ),
+    ),
+    (
+        6924993,
+        concat!(
+            "7cbc3076ae6ba89b807be8dcabe7dc03f4f617400bf3a06c8cadc54c78f43bf1",
+            "35e7afdd60d7d6f996c3ea23d0de614ad6f0742b0dc0d514d589e37e7ba1518b",
+            "2ce673727e957c8d4470b46a0daba28a927b9420914a0b6e953cd95c12f7e139",
+            "413da423eb01648ec924ec4a257e72edc87a8e6a07851acdd137c9872ed290fa",
+        ),
+    ),
+    (
+        1627068,
+        concat!(
+            "f3b9d2c759b438cdfce1e95ec8620fea6dd23268af6eccf198b970cac3278b0e",
+            "fffd6a238c121b9d9f9c339675c82041ff76f605ff8608f63a6e7be04e36d8e7",
+            "ba3e41a5a78276668c812507d1b165b6f98d22cfb4a4a7d29731fad0217d082d",
+            "21bd196aad7ac59c3811caceba1c976e6526b78940e9bd694e3c57acbf4068e1",
+        ),
+    ),
+    (
+        5172052,
+        concat!(
+            "4f16d34bb6f8bc54b3d643f67f3fee3c30f2843a557728698e7b8d79000644c3",
+            "19478b5592019bddc9959c961ccb254aa85a57875137f9c95cb7456f63799119",
+            "c30b88a0b74449a290b5ae4b2289421607eb4168662d9f88d5409799084d6c26",
+            "920d8f9d6af40c4976e3976014049f9fefdd3ef9667b81825857469a0e452f60",
+        ),
+    ),
+    (
+        2125721,
+        concat!(
+            "8aa3e1fbfff99122da59d58ad0331e0364b9e1234bd1ce1e9b76ecc45683455f",
+            "83cf4226b3d5d153f04509d1b5331833e6eeb6e867849cff44e97b2c46312f83",
+            "9e0018c32f619964f894151966428006d89814f8b492495e35a5c68e4f9d3df0",
+            "d710f09e952c6a1049e8f0f47c161b9a8283711ffeb5a05bda607c8bb5327765",
+        ),
+    ),
+    (
+        6304315,
+        concat!(
+            "4f34cf8601cfa4099264bf722dca1135e65fd5dc3fb12e5c1a6b77da032b76b8",
+            "165f2a187c95123aff318a71aed139c9e2640c0a664889fb5ce5485cb7846951",
+            "3d97e5b4178b068e055471095ff02af21b7470167702a81d6ace9a31b2c373a1",
+            "e77ab8f24d1c8c1e36854fd7591f215729cc6b076c624fe3bc9403214b927a3b",
+        ),
+    ),
+    (
+        3177286,
+        concat!(
+            "975b88cae6e890a1fc40d7b54cd62bd740698ff9b368e9d7fff5b6af727a4489",
+            "64ebbb40c67b11e7ac9ff59c680b92071b4423d400419814616041f0289475d9",
+            "40b2de1cf95420b065b21e4dd079159b05e517c0ad2fa852c9965bf063c1426a",
+            "9d2a11417dac49902847730c6281ae92eb3795b197fb39f3c865b576bfa72907",
+        ),
+    ),
+    (
+        2231103,
+        concat!(
+            "7f2d30a19ac32a0592aa45ee3de16f927fd100c21f6223d204d218a65172c7af",
+            "0d82fcee707b33f09ae20605547e360e5b22d27d4fdfe28dc2f88a2c02970a86",
+            "3faaf49debed360a5f0a5aeae606525649b9ab640148bb05ee5a2dae739ff9f8",
+            "65c90720a724092f0f5ae20df86a8df12320d2ee6cd3c053c1634752210fad29",
+        ),
+    ),
+    (
+        427614,
+        concat!(
+            "16c1b16a1cb0efa50395f25d9c2f6be9fcbdd6049bc8bf85753ec4dc1e7c3a7f",
+            "f293a1cd84cda3ef04f1a74ced2ce7451b1cb4fa11d14a6f007a210f9d7471ee",
+            "6ee0d30a43bf38391706f7afa3caf85138ebc188dd75a9f9c927aa8b48096388",
+            "6df89b7add9c8282528c7e209c8f057aed78405f199f2d1c8eb23cca757db6a6",
+        ),
+    ),
+    (
+        5072027,
+        concat!(
+            "96a4910d5804a2edfdf4aa932f199f77aeb23d355ec287c853e402277bee06c2",
+            "faf2814e09a1ac65bdc2304c6a500e631f907215f5abf5c3c81521e742cb989e",
+            "0b93db539e546e0020fc5047d8031792001d0cb99719e7490370edf383a0097d",
+            "584a549d3a2c7178aeb690a773b753fb5e62e509033db4628ce5d160cadee2e8",
+        ),
+    ),
+    (
+        5329636,
+        concat!(
+            "1a2bad0989e8f7883cfdd831e414b0ae53e577c4a3c2ca988d668ececc6c066d",
+            "f670f8679b51718a844b4e6461c3c80f2f652795a0f8c3b5475705734350d383",
+            "6d03b3c7a096326b527e56b9351aa4be52fdff3773ef926be4c9ac955d3c1295",
+            "d386a6b4c3b57a654e2635ad6d91c6d070ce461322addfd0a929637971c3a632",
+        ),
+    ),
+    (
+        2104324,
+        concat!(
+            "3102c9adb2078512813dc0d7787c26e9e44996fac58408bdd380d3fa0feddf89",
+            "28199422b69fa9f85abcaa1a2a61842b2462dfe0270c39a4d72027e3c34e37b9",
+            "acc359dee873a110c190a697d3d8f7296cf6a58eebdc2a997c9eea1e041cada0",
+            "89d98b5103c4d78befb83963f094ebe48071173c41ae1e7d1dff2f5404875ddf",
+        ),
+    ),
+    (
+        894437,
+        concat!(
+            "e2bc91678ec8236362eb625e6a2d5c6bcb3e7e9752a5231fd83426061149a8f3",
+            "ac30001e045162c8cec01759db115609f745274cb1c28b12df79d303a9beb3f0",
+            "46ac7dbb5da64dbb3e399163f611ce3f119da49fc5411742ebc673628438a4d0",
+            "1005ea421ac43dd4848f2a44b4e0eb90feac60c64b75028cbc94352120b05c9e",
+        ),
+    ),
+    (
+        4934490,
+        concat!(
+            "1aa19562cc6a28ee98193c8edfb8bf9b45d00100f452bc66035faea397ed2dcb",
+            "72d6ba1aeed843ab5ff1282a359e7a950c4d12c9bd5078e43d0cff9f6c140b04",
+            "901e598f61cbadf7e3f3e09020f077a9bfa02414697514e32859729a2ee63179",
+            "b82e94c851a3ce96c2185581aa14cf38e5a1ad6a80fee87fa84f5689a99f049d",
+        ),
+    ),
+    (
+        3701877,
+        concat!(
+            "0de60b229a38ad2ece6b9a72ff223463843fa58e029bbbaaa68447fcf3f7f4a4",
+            "e882e567ee6427d2fdc0b3b3fb655221e2ee64f2a15f7c6b0d6a9f25b3ea7494",
+            "e69304f216860d49a8d0bdfc7ec1e6280fc4941af8950da5e0a084c3aa383d00",
+            "156ae4c29fa75bdbfd13455a49b493ec11d3802f2f2238fe986ff6a4ac45fae9",
+        ),
+    ),
+    (
+        3896454,
+        concat!(
+            "947561147c8c721e1b4f7ef1695a328711d3c08f571c850fe1f8821d518b18ed",
+            "fa295218c29bfdcf0dd56c3fa619a1a09b6f540c7b9972055c4c3b77b302c8db",
+            "a58b6be22693f605fc696d0a9c2f2982864e519b697a4d632c843e028917cc02",
+            "df9a8fe861ca74a3c475d75687cf665d375faf9ee4989cf64a58fec70be3cb39",
+        ),
+    ),
+    (
+        1055204,
+        concat!(
+            "aaa52cfa1a4e76f1e6d30cba135d280c3a6371c04f86db3e28ce75829b7f3968",
+            "6d9e36e880d494e6c6d6ae33fe489be8f702ba343607e6866e3fed11acaa01ca",
+            "488a82ae4833c5cc1eb51f95dae00e22645368fc8a32713141d454db47ce5d3c",
+            "22ee88ac7352218309742f17c6381986f054d75049e0c222aa68aef9a600ab76",
+        ),
+    ),
+    (
+        4171181,
+        concat!(
+            "1b3f90b0cc1dac135e9ddf41d3f96e92ced072b451ff667a667b0a0add318fa0",
+            "d23aa7b99b8bcf3c5886fa1c17c2b8bb32bbdb0a1f35c785d3c57ad0f55a53e1",
+            "0ea7d478e46551aab9bd05d053b1e8023b629970527a0eaab5a1f60b008686e6",
+            "96fa8030135db086b53439a9922ed2e96894c509987e429c72655bf6c35c99a6",
+        ),
+    ),
+    (
+        8090932,
+        concat!(
+            "8606f6fd02c7533b0bad30242a03526b46b7fa9069b57e28d2dcd8955597fa3e",
+            "518383ecaa062ca9d168399f09f074252d1d10ef1a4462c4766ae88ce1e64f8c",
+            "633f9b86da15a3b3ca420ecffb07f8a2b079fcc4182c72ce6a9a56c21b0e3736",
+            "e3d0330bfa5a54c468e7bfb3bf2c1d96a21f4dc35f882b66c9d65892a0ef996d",
+        ),
+    ),
+    (
+        6019343,
+        concat!(
+            "5ddba38ceb4ef71396c965f22dca56ac883a11dbf9f4fbb3a9baab7ea4644876",
+            "09a538820f1a2e327775f081f657eb9ba3b788dc6ff6ba0d19c34ad387f31a7a",
+            "912f25896589752dc9ff783b2d9b582bfb3d6ec90252d10c8ed6545e53ebf171",
+            "bdb10507de4ff224a01aa75b922510035bbd73cfd23db30c70cdcf2c459f5e39",
+        ),
+    ),
+    (
+        5438119,
+        concat!(
+            "b8849d085b657bb093831e2701588451258d5344cff2930aa34f475c0f78bc77",
+            "b7510ace9ed49b29b52ee3e33eba82760fe53fce5fc14d4b61d92779e38ae559",
+            "e2b30f2e96d48c81a90b5850bcdb853a1815c5c0498c472f67a107392144626d",
+            "960a65470336a1d5bb3dd9cd6e07efa4697ec716831f33346bd2495eabddfcc2",
+        ),
+    ),
+    (
+        2298366,
+        concat!(
+            "c7f5f9917fd75a70e0d0b827043a024c4ac8ae3122e648ae15583146ec8a0417",
+            "dbad181738dd597eabdcf2d5194deb2cfa85cc8e1db9444cb5f8e21b1c6d9654",
+            "b7175145bafb40b1ae1027836426e1d6b700c54398d6c6e6ffbbe403552489fb",
+            "567e058868c06d999e425c05efdbcf28da0f3aa4615388a9a95839e6fd83b451",
+        ),
+    ),
+    (
+        164906,
+        concat!(
+            "b34fb1e2edc2e6a428cefd819712f699f5ba311ab66f54173825d330231d08df",
+            "f34c9c00e39991b26d579e0ea581e69a9d5d49f654530ffcee2e55192ff3b31f",
+            "ab15477c0c3b9986145c3ddccaae5857783ace590e184e59446a3f77794cd2be",
+            "27cf7bf75629a177dc7b13ee4c4b02880fe7035ea8831f1f656ef6773a7812d4",
+        ),
+    ),
+    (
+        5697613,
+        concat!(
+            "3ce1e344055efa42704b3a4b731251b28bebc7758180c5496bc52833617bebb6",
+            "126e5616bfc9bcd47dda1a3f580fb5096e8aa88936dfc68b8f7fe4a0b272ecaa",
+            "477864884ed8fc429628ac41e14cd04632afc65f0ab05186d98f8c3561511c1a",
+            "b935a5f437cfe73fab552f71c36b6077d1d9ec70940796fb42d509c98bc6e575",
+        ),
+    ),
+    (
+        2301706,
+        concat!(
+            "67331bebc731bdba45116001fc38d893f1812880c2e21c6eadb86f96501d19ea",
+            "5da8e183db0a8278b25e8ab00ef448acb9249166037d88df325a6b3d672ca7d6",
+            "11af18b70f488b7c8863e9c7d7b1a56c43226c9e8ae2b565da37eea257194bbb",
+            "1e41a19140de7fdf5087afd34ae8308966eb054c7843a64048e456d63f694a8b",
+        ),
+    ),
+    (
+        4296656,
+        concat!(
+            "7a522c71fe2efe356bb9949c1550af668fed821f604c8167eb894c9bec217534",
+            "fc909dae34f928671b234c734ac7a1664325a47f78a02f82a8fd0136b902d3ea",
+            "1876da0b529c64befdc5c93f68ece44941ff33e0453be05ecdfc9b46d5621eac",
+            "f49fb92d51c2d17fd5a0cb41fe279d874d92cba971cc24e3a63ae642db90c660",
+        ),
+    ),
+    (
+        2972741,
+        concat!(
+            "f392765791bc075372a92f59f556b5adafc8d00455e5e047cfd99b58e5f9b6c4",
+            "ad8ed679aefee40ff177fc76227cff3d2a0969d7d0118601f00c13d6f31bcd63",
+            "d3dffa00c6ee51b7e3e996813e059c57c04a37557f7b6ea62d3cd82f5958157a",
+            "6cfe1da9471aeffc7e86a522f7cc2c418215b5ddae613d1f409ffc8621c9a179",
+        ),
+    ),
+    (
+        1806834,
+        concat!(
+            "a1d8a9835104023656cee668739e07df8a93a34bd34038bda9c3b8e1fa648001",
+            "bec9d8360d9fd4fc9f9e09c4607a9c1c04fc0f629dabccee3d8f7f960a5427bd",
+            "53d58c52161e616e9da436688a81317fd17844268bb3bdd78a3fc592f97c7ba9",
+            "942ae00513c77b8d0a09ca2211035b5eb55d5908511ae47b4eac5ee62afae9d4",
+        ),
+    ),
+    (
+        5755969,
+        concat!(
+            "31a3dd4f2c6dd1077d3ef9d29af56e9a661a9d9aef92688a16d5d3e94b800c57",
+            "5e50f637ee76e3072a45943a46ccb36eca907da20e49ef53b125458374b59625",
+            "270fac89a7951733a4ad4cad0fab03e6be778421d66549d171c90f3da0f222ff",
+            "685dc6d29cce6cdbe98882553162a6815d2841978a62c6b3df628724663f1cf0",
+        ),
+    ),
+    (
+        86125,
+        concat!(
+            "a62e810fa9d378f3d14c1e48e27a95154c9648c2465e9b12b91715501c3722fe",
+            "4e8a4609635480fd0ab616830f1a38b0e4729d26fe6b98ee12dd25714a6cc17f",
+            "fd30bb88b21cfb63e7ab931a0d8665670625f0461a92d2baf67452d253feb805",
+            "36e98f551b12206f307c37d051cd6801d8c84e8fceb5df704c8f0a0544e8d0fb",
+        ),
+    ),
+];
+
+pub fn decode_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("hex"))
+        .collect()
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut node = [0u8; 64];
+    node[..32].copy_from_slice(left);
+    node[32..].copy_from_slice(right);
+    keccak256(&node)
+}
+
+/// Root and proofs of the dataset tree of `epoch` in which only `items` are set and all the
+/// other leaves are zero, the real dataset is too large to build in tests
+pub fn dag_proofs(items: &[(u64, &str)], epoch: u64) -> ([u8; 32], Vec<DagProof>) {
+    let depth = merkle_depth(dataset_items(epoch));
+    let mut zero = vec![[0u8; 32]];
+    for level in 0..depth {
+        let node = hash_pair(&zero[level], &zero[level]);
+        zero.push(node);
+    }
+    let mut levels: Vec<HashMap<u64, [u8; 32]>> = vec![items
+        .iter()
+        .map(|(index, item)| (*index, keccak256(&decode_hex(item))))
+        .collect()];
+    for level in 0..depth {
+        let nodes = &levels[level];
+        let parents = nodes
+            .keys()
+            .map(|index| {
+                let child = |index| nodes.get(&index).unwrap_or(&zero[level]);
+                let parent = index / 2;
+                (parent, hash_pair(child(parent * 2), child(parent * 2 + 1)))
+            })
+            .collect();
+        levels.push(parents);
+    }
+    let proofs = items
+        .iter()
+        .map(|(index, item)| DagProof {
+            item: decode_hex(item),
+            branch: (0..depth)
+                .map(|level| {
+                    let sibling = (index >> level) ^ 1;
+                    *levels[level].get(&sibling).unwrap_or(&zero[level])
+                })
+                .collect(),
+        })
+        .collect();
+    (levels[depth][&0], proofs)
+}
+
+/// The clique chain of the tests, with a checkpoint every 16 blocks and without London
+pub const CLIQUE: CliqueConfig = CliqueConfig {
+    period: 15,
+    epoch: 16,
+    london: u64::MAX,
+};
+
+/// A signer of the clique and parlia chains of the tests
+pub struct Signer {
+    pub address: [u8; 20],
+    secret: libsecp256k1::SecretKey,
+}
+
+/// `count` signers, sorted by address as in a snapshot
+pub fn new_signers(count: u8) -> Vec<Signer> {
+    let mut signers: Vec<Signer> = (1..=count)
+        .map(|index| {
+            let secret = libsecp256k1::SecretKey::parse(&[index; 32]).unwrap();
+            let pubkey = libsecp256k1::PublicKey::from_secret_key(&secret);
+            let mut address = [0u8; 20];
+            address.copy_from_slice(&keccak256(&pubkey.serialize()[1..])[12..]);
+            Signer { address, secret }
+        })
+        .collect();
+    signers.sort_by_key(|signer| signer.address);
+    signers
+}
+
+pub fn addresses(signers: &[Signer]) -> Vec<[u8; 20]> {
+    signers.iter().map(|signer| signer.address).collect()
+}
+
+/// An unsealed checkpoint header of `number` listing the `signers`
+pub fn clique_checkpoint(number: u64, signers: &[Signer]) -> Header {
+    let mut header = Header::decode(&decode_hex(MAINNET_BLOCK_1)).unwrap();
+    header.number = number;
+    header.timestamp = 1_600_000_000 + 15 * number;
+    header.parent_hash = [0u8; 32];
+    header.uncles_hash = EMPTY_UNCLES_HASH;
+    header.coinbase = [0u8; 20];
+    header.difficulty = 1;
+    header.mix_hash = [0u8; 32];
+    header.nonce = [0u8; 8];
+    header.extra_data = vec![0u8; EXTRA_VANITY];
+    for signer in signers {
+        header.extra_data.extend_from_slice(&signer.address);
+    }
+    header.extra_data.extend_from_slice(&[0u8; EXTRA_SEAL]);
+    header.hash = keccak256(&header.encode());
+    header
+}
+
+/// A header on top of `parent` sealed by `signer` without a vote, in turn with difficulty 2
+/// or out of turn with difficulty 1. A checkpoint child lists the `signers` of its parent
+/// snapshot.
+pub fn clique_child(parent: &Header, signer: &Signer, in_turn: bool, signers: &[Signer]) -> Header {
+    let mut header = clique_checkpoint(parent.number + 1, signers);
+    if header.number % CLIQUE.epoch != 0 {
+        header.extra_data = vec![0u8; EXTRA_VANITY + EXTRA_SEAL];
+    }
+    header.parent_hash = parent.hash;
+    header.timestamp = parent.timestamp + CLIQUE.period;
+    header.difficulty = if in_turn { 2 } else { 1 };
+    seal_header(&mut header, signer);
+    header
+}
+
+/// A clique chain of `len` headers from the checkpoint 0, each sealed by the signer in turn
+pub fn clique_headers(signers: &[Signer], len: usize) -> Vec<Header> {
+    let mut headers = vec![clique_checkpoint(0, signers)];
+    while headers.len() < len {
+        let parent = headers.last().unwrap();
+        let signer = &signers[((parent.number + 1) % signers.len() as u64) as usize];
+        headers.push(clique_child(parent, signer, true, signers));
+    }
+    headers
+}
+
+/// Seal `header` by `signer` and update its hash
+pub fn seal_header(header: &mut Header, signer: &Signer) {
+    let hash = clique::seal_hash(header);
+    seal(header, &hash, signer);
+}
+
+fn seal(header: &mut Header, hash: &[u8; 32], signer: &Signer) {
+    let message = libsecp256k1::Message::parse(hash);
+    let (signature, recovery_id) = libsecp256k1::sign(&message, &signer.secret);
+    let seal = header.extra_data.len() - EXTRA_SEAL;
+    header.extra_data[seal..seal + 64].copy_from_slice(&signature.serialize());
+    header.extra_data[seal + 64] = recovery_id.serialize();
+    header.hash = keccak256(&header.encode());
+}
+
+/// The parlia chain of the tests, from Luban and Bohr, with an epoch header every 40 blocks and
+/// without London
+pub const PARLIA: ParliaConfig = ParliaConfig {
+    chain_id: 56,
+    epoch: 40,
+    luban: 0,
+    bohr: 0,
+    london: u64::MAX,
+};
+
+/// An unsealed epoch header of `number` listing the `validators` with zero BLS keys and the
+/// `turn_length`
+pub fn parlia_epoch_header(number: u64, validators: &[Signer], turn_length: u8) -> Header {
+    let mut header = clique_checkpoint(number, &[]);
+    header.timestamp = 1_600_000_000 + 3 * number;
+    header.difficulty = 2;
+    header.extra_data = vec![0u8; EXTRA_VANITY];
+    header.extra_data.push(validators.len() as u8);
+    for validator in validators {
+        header.extra_data.extend_from_slice(&validator.address);
+        header.extra_data.extend_from_slice(&[0u8; 48]);
+    }
+    header.extra_data.push(turn_length);
+    header.extra_data.extend_from_slice(&[0u8; EXTRA_SEAL]);
+    header.hash = keccak256(&header.encode());
+    header
+}
+
+/// A header on top of `parent` sealed by `signer`, in turn with difficulty 2 or out of turn
+/// with difficulty 1. An epoch header lists the `validators` with the `turn_length`.
+pub fn parlia_child(
+    parent: &Header,
+    signer: &Signer,
+    in_turn: bool,
+    validators: &[Signer],
+    turn_length: u8,
+) -> Header {
+    let mut header = parlia_epoch_header(parent.number + 1, validators, turn_length);
+    if header.number % PARLIA.epoch != 0 {
+        header.extra_data = vec![0u8; EXTRA_VANITY + EXTRA_SEAL];
+    }
+    header.parent_hash = parent.hash;
+    header.timestamp = parent.timestamp + 3;
+    header.coinbase = signer.address;
+    header.difficulty = if in_turn { 2 } else { 1 };
+    seal_parlia_header(&mut header, signer);
+    header
+}
+
+/// A parlia chain of `len` headers from the epoch header 0 of the `validators`, each sealed by
+/// the validator in turn unless it sealed recently. The later epoch headers list the `next`
+/// validators.
+pub fn parlia_headers(validators: &[Signer], next: &[Signer], len: usize) -> Vec<Header> {
+    let signer = |address: &[u8; 20]| {
+        validators
+            .iter()
+            .chain(next)
+            .find(|signer| &signer.address == address)
+            .unwrap()
+    };
+    let set = |validators: &[Signer]| ValidatorSet::new(addresses(validators), 1).unwrap();
+    let mut snapshot = parlia::Snapshot::new(set(validators));
+    let mut header = parlia_epoch_header(0, validators, 1);
+    header.coinbase = validators[0].address;
+    seal_parlia_header(&mut header, &validators[0]);
+    snapshot.apply(&PARLIA, 0, header.coinbase, Some(set(validators)));
+    let mut headers = vec![header];
+    while headers.len() < len {
+        let parent = headers.last().unwrap();
+        let number = parent.number + 1;
+        let turn = (number % snapshot.validators.validators.len() as u64) as usize;
+        let in_turn = snapshot.validators.validators[turn];
+        // the validator in turn may have sealed the parent as the validators change
+        let sealer = if snapshot.verify_signer(&in_turn).is_ok() {
+            in_turn
+        } else {
+            *snapshot
+                .validators
+                .validators
+                .iter()
+                .find(|validator| snapshot.verify_signer(validator).is_ok())
+                .unwrap()
+        };
+        let header = parlia_child(parent, signer(&sealer), sealer == in_turn, next, 1);
+        let listed = if number % PARLIA.epoch == 0 {
+            Some(set(next))
+        } else {
+            None
+        };
+        snapshot.apply(&PARLIA, number, header.coinbase, listed);
+        headers.push(header);
+    }
+    headers
+}
+
+/// Seal `header` by the validator `signer` and update its hash
+pub fn seal_parlia_header(header: &mut Header, signer: &Signer) {
+    let hash = parlia::seal_hash(PARLIA.chain_id, header).unwrap();
+    seal(header, &hash, signer);
+}
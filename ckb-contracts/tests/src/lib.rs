@@ -1,3 +1,4 @@
+use ckb_testtool::context::Context;
 use ckb_tool::ckb_types::bytes::Bytes;
 use std::env;
 use std::fs;
@@ -8,6 +9,7 @@ use std::str::FromStr;
 mod tests;
 
 const TEST_ENV_VAR: &str = "CAPSULE_TEST_ENV";
+const TRACE_DIR_ENV_VAR: &str = "CKB_ENV_TRACE_DIR";
 
 pub enum TestEnv {
     Debug,
@@ -28,13 +30,19 @@ impl FromStr for TestEnv {
 
 pub struct Loader(PathBuf);
 
-impl Default for Loader {
-    fn default() -> Self {
-        let test_env = match env::var(TEST_ENV_VAR) {
+impl TestEnv {
+    /// The build the tests run against, set by capsule
+    pub fn from_env() -> Self {
+        match env::var(TEST_ENV_VAR) {
             Ok(val) => val.parse().expect("test env"),
             Err(_) => TestEnv::Debug,
-        };
-        Self::with_test_env(test_env)
+        }
+    }
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self::with_test_env(TestEnv::from_env())
     }
 }
 
@@ -44,12 +52,27 @@ impl Loader {
             TestEnv::Debug => "debug",
             TestEnv::Release => "release",
         };
+        Self::with_build_dir(load_prefix)
+    }
+
+    /// Loader of the contracts built with the `record` feature by `make test-record`
+    pub fn record() -> Self {
+        Self::with_build_dir("record")
+    }
+
+    /// Loader of the contracts built with the `unbounded-branch` feature by
+    /// `make test-branch-limit`
+    pub fn unbounded_branch() -> Self {
+        Self::with_build_dir("unbounded-branch")
+    }
+
+    fn with_build_dir(name: &str) -> Self {
         let dir = env::current_dir().unwrap();
         let mut base_path = PathBuf::new();
         base_path.push(dir);
         base_path.push("..");
         base_path.push("build");
-        base_path.push(load_prefix);
+        base_path.push(name);
         Loader(base_path)
     }
 
@@ -59,3 +82,22 @@ impl Loader {
         fs::read(path).expect("binary").into()
     }
 }
+
+/// Write the debug messages captured by `context` to `$CKB_ENV_TRACE_DIR/<name>.trace`.
+///
+/// Contracts built with the `record` feature output a trace of their syscalls as debug
+/// messages, the file replays the run natively with `ckb_env::replay::ReplayCKBChain`.
+/// Nothing is written when the variable is not set.
+pub fn write_trace(context: &Context, name: &str) {
+    let dir = match env::var(TRACE_DIR_ENV_VAR) {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => return,
+    };
+    let messages: Vec<String> = context
+        .captured_messages()
+        .into_iter()
+        .map(|message| message.message)
+        .collect();
+    fs::create_dir_all(&dir).expect("trace dir");
+    fs::write(dir.join(format!("{}.trace", name)), messages.join("\n")).expect("write trace");
+}
@@ -1,18 +1,50 @@
 use super::*;
-use ckb_testtool::context::Context;
+use ckb_env::record::TRACE_PREFIX;
+use ckb_env::replay::ReplayCKBChain;
+use ckb_env::traits::ContractInterface;
+use ckb_testtool::{builtin::ALWAYS_SUCCESS, context::Context};
 use ckb_tool::ckb_types::{
     bytes::Bytes,
-    core::TransactionBuilder,
+    core::{TransactionBuilder, TransactionView},
     packed::*,
     prelude::*,
 };
+use eth_bridge_typescript_lib::error::Error;
+use eth_bridge_typescript_lib::EthBridgeTypescript;
+use eth_light_client_typescript_lib::clique::{self, Seal, EXTRA_SEAL, EXTRA_VANITY, MAX_SIGNERS};
+use eth_light_client_typescript_lib::config::{
+    CliqueConfig, Config, Consensus, ForkSchedule, ParliaConfig,
+};
+use eth_light_client_typescript_lib::consensus::{self, MAXIMUM_EXTRA_DATA_SIZE};
+use eth_light_client_typescript_lib::difficulty::{self, EMPTY_UNCLES_HASH, MINIMUM_DIFFICULTY};
+use eth_light_client_typescript_lib::ethash::{self, DagProof, ITEM_BYTES};
+use eth_light_client_typescript_lib::hash::keccak256;
+use eth_light_client_typescript_lib::header::Header;
+use eth_light_client_typescript_lib::parlia::{self, ValidatorSet, MAX_VALIDATORS};
+use eth_light_client_typescript_lib::rlp;
+use eth_light_client_typescript_lib::roots::{DatasetRoots, ROOT_SLOTS};
+use eth_light_client_typescript_lib::state::{
+    CliqueClientState, HeaderChain, HeaderRecord, LightClientState, ParliaClientState,
+    HEADER_SLOTS,
+};
+use eth_light_client_typescript_lib::{MAX_BRANCH_HEADERS, MAX_SEALED_HEADERS};
 
 const MAX_CYCLES: u64 = 10_000_000;
+/// Cycles a transaction may use on chain
+const MAX_TX_CYCLES: u64 = 70_000_000;
+/// Block the branch of `test_submit_full_branch` starts after, in epoch 385 whose dataset tree
+/// has 26 levels as the ones of 2022, early enough after Gray Glacier to leave the difficulty
+/// at its minimum
+const FULL_BRANCH_PARENT: u64 = 11_550_000;
+/// Nonces of the headers of the branch of `test_submit_full_branch`, which meet the minimum
+/// difficulty over the zero dataset, and of the header `test_submit_branch_over_limit` adds
+const FULL_BRANCH_NONCES: [u64; MAX_BRANCH_HEADERS + 1] = [563, 97_832, 154_315, 237_253];
 
 #[test]
 fn test_basic() {
     // deploy contract
     let mut context = Context::default();
+    context.set_capture_debug(true);
     let contract_bin: Bytes = Loader::default().load_binary("eth-bridge-typescript");
     let out_point = context.deploy_cell(contract_bin);
 
@@ -57,9 +89,527 @@ fn test_basic() {
         .build();
     let tx = context.complete_tx(tx);
 
+    // run, the trace is written whatever the result
+    let result = context.verify_tx(&tx, MAX_CYCLES);
+    write_trace(&context, "test_basic");
+    let cycles = result.expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+/// Needs the bridge contract built with the `record` feature, run it with `make test-record`
+#[test]
+#[ignore]
+fn test_replay_failed_run() {
+    // deploy contracts
+    let mut context = Context::default();
+    context.set_capture_debug(true);
+    let contract_bin: Bytes = Loader::record().load_binary("eth-bridge-typescript");
+    let out_point = context.deploy_cell(contract_bin);
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+
+    // prepare scripts
+    let lock_script = context
+        .build_script(&always_success_out_point, Default::default())
+        .expect("script");
+    let type_script = context
+        .build_script(&out_point, Bytes::from(vec![0u8; 32]))
+        .expect("script");
+    let cell_deps = vec![
+        CellDep::new_builder().out_point(out_point).build(),
+        CellDep::new_builder()
+            .out_point(always_success_out_point)
+            .build(),
+    ];
+
+    // prepare cells, the proof of the bridge cell is not an RLP list
+    let input_out_point = context.create_cell(
+        CellOutput::new_builder()
+            .capacity(1000u64.pack())
+            .lock(lock_script.clone())
+            .build(),
+        Bytes::new(),
+    );
+    let input = CellInput::new_builder()
+        .previous_output(input_out_point)
+        .build();
+    let output = CellOutput::new_builder()
+        .capacity(1000u64.pack())
+        .lock(lock_script)
+        .type_(Some(type_script).pack())
+        .build();
+    let witness = WitnessArgs::new_builder()
+        .output_type(Some(Bytes::from(vec![0x80u8])).pack())
+        .build();
+
+    // build transaction
+    let tx = TransactionBuilder::default()
+        .input(input)
+        .output(output)
+        .output_data(Bytes::new().pack())
+        .cell_deps(cell_deps)
+        .witness(witness.as_bytes().pack())
+        .build();
+    let tx = context.complete_tx(tx);
+
+    // run
+    let result = context.verify_tx(&tx, MAX_CYCLES);
+    write_trace(&context, "test_replay_failed_run");
+    assert!(result.is_err());
+
+    // the contract traced its syscalls, the run replays natively
+    let messages: Vec<String> = context
+        .captured_messages()
+        .into_iter()
+        .map(|message| message.message)
+        .collect();
+    assert!(messages
+        .iter()
+        .any(|message| message.starts_with(TRACE_PREFIX)));
+    let chain = ReplayCKBChain::from_trace(messages.iter().map(String::as_str));
+    assert_eq!(EthBridgeTypescript::run(&chain), Error::Encoding as i8);
+}
+
+/// A chain with every fork up to Gray Glacier from genesis
+const FORKS: ForkSchedule = ForkSchedule {
+    homestead: 0,
+    byzantium: 0,
+    constantinople: 0,
+    muir_glacier: 0,
+    london: 0,
+    arrow_glacier: 0,
+    gray_glacier: 0,
+};
+
+/// A header with every field at its largest size, as a header of 2022
+fn full_header(number: u64) -> Header {
+    let mut header = Header {
+        hash: [0u8; 32],
+        parent_hash: [1u8; 32],
+        uncles_hash: EMPTY_UNCLES_HASH,
+        coinbase: [2u8; 20],
+        state_root: [3u8; 32],
+        transactions_root: [4u8; 32],
+        receipts_root: [5u8; 32],
+        logs_bloom: vec![0xffu8; 256],
+        difficulty: MINIMUM_DIFFICULTY,
+        number,
+        gas_limit: 30_000_000,
+        gas_used: 15_000_000,
+        timestamp: 1_650_000_000,
+        extra_data: vec![6u8; MAXIMUM_EXTRA_DATA_SIZE],
+        mix_hash: [0u8; 32],
+        nonce: [0u8; 8],
+        base_fee_per_gas: Some(consensus::INITIAL_BASE_FEE),
+        withdrawals_root: None,
+        blob_gas_used: None,
+        excess_blob_gas: None,
+        parent_beacon_block_root: None,
+        requests_hash: None,
+    };
+    header.hash = keccak256(&header.encode());
+    header
+}
+
+/// Seal the child of `parent` with `nonce` over a dataset of `items` zero items, the tree of the
+/// dataset has the depth of the real one so its proofs cost as much
+fn seal_child(parent: &Header, items: u64, nonce: u64) -> Header {
+    let mut header = full_header(parent.number + 1);
+    header.parent_hash = parent.hash;
+    header.timestamp = parent.timestamp + 13;
+    header.difficulty =
+        difficulty::difficulty(&FORKS, parent, header.timestamp).expect("difficulty");
+    header.base_fee_per_gas = Some(consensus::base_fee(FORKS.london, parent).expect("base fee"));
+    header.nonce = nonce.to_be_bytes();
+    let seal_hash = keccak256(&header.encode_without_seal());
+    let (mix_hash, _) =
+        ethash::hashimoto(&seal_hash, &header.nonce, items, |_, _| Ok([0u8; ITEM_BYTES]))
+            .expect("hashimoto");
+    header.mix_hash = mix_hash;
+    header.hash = keccak256(&header.encode());
+    header
+}
+
+/// Root and proof of the zero items of a dataset with `items` items
+fn zero_dataset(items: u64) -> ([u8; 32], DagProof) {
+    let mut node = keccak256(&[0u8; ITEM_BYTES]);
+    let mut branch = Vec::new();
+    for _ in 0..ethash::merkle_depth(items) {
+        branch.push(node);
+        let mut pair = [0u8; 64];
+        pair[..32].copy_from_slice(&node);
+        pair[32..].copy_from_slice(&node);
+        node = keccak256(&pair);
+    }
+    let proof = DagProof {
+        item: vec![0u8; ITEM_BYTES],
+        branch,
+    };
+    (node, proof)
+}
+
+/// Encode a branch of `headers` on top of `parent`, every item hashimoto reads is `proof`
+fn encode_branch(parent: &Header, headers: &[Header], proof: &DagProof) -> Vec<u8> {
+    let mut siblings = Vec::new();
+    for sibling in &proof.branch {
+        rlp::encode_bytes(&mut siblings, sibling);
+    }
+    let mut fields = Vec::new();
+    rlp::encode_bytes(&mut fields, &proof.item);
+    rlp::encode_list(&mut fields, &siblings);
+    let mut proofs = Vec::new();
+    for _ in 0..ethash::ACCESSES {
+        rlp::encode_list(&mut proofs, &fields);
+    }
+
+    let mut items = Vec::new();
+    rlp::encode_bytes(&mut items, &parent.encode());
+    for header in headers {
+        let mut fields = Vec::new();
+        rlp::encode_bytes(&mut fields, &header.encode());
+        rlp::encode_list(&mut fields, &proofs);
+        rlp::encode_list(&mut items, &fields);
+    }
+    let mut branch = Vec::new();
+    rlp::encode_list(&mut branch, &items);
+    branch
+}
+
+/// A transaction updating the light-client cell of the `consensus` from the `input` data to the
+/// `output` data with the `branch`, verified by the contract `contract_bin`
+fn update_tx(
+    context: &mut Context,
+    contract_bin: Bytes,
+    chain_id: u64,
+    consensus: Consensus,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    branch: Vec<u8>,
+) -> TransactionView {
+    // deploy contracts
+    let out_point = context.deploy_cell(contract_bin);
+    let always_success_out_point = context.deploy_cell(ALWAYS_SUCCESS.clone());
+
+    // prepare scripts
+    let lock_script = context
+        .build_script(&always_success_out_point, Default::default())
+        .expect("script");
+    let config = Config {
+        owner_lock_hash: [0u8; 32],
+        type_id: [0u8; 32],
+        chain_id,
+        consensus,
+        confirmations: 15,
+        ring_size: HEADER_SLOTS,
+    };
+    let type_script = context
+        .build_script(&out_point, Bytes::from(config.encode()))
+        .expect("script");
+    let cell_deps = vec![
+        CellDep::new_builder().out_point(out_point).build(),
+        CellDep::new_builder()
+            .out_point(always_success_out_point)
+            .build(),
+    ];
+
+    // prepare cells
+    let cell = CellOutput::new_builder()
+        .capacity(1000u64.pack())
+        .lock(lock_script)
+        .type_(Some(type_script).pack())
+        .build();
+    let input_out_point = context.create_cell(cell.clone(), Bytes::from(input));
+    let input = CellInput::new_builder()
+        .previous_output(input_out_point)
+        .build();
+    let witness = WitnessArgs::new_builder()
+        .output_type(Some(Bytes::from(branch)).pack())
+        .build();
+
+    // build transaction
+    let tx = TransactionBuilder::default()
+        .input(input)
+        .output(cell)
+        .output_data(Bytes::from(output).pack())
+        .cell_deps(cell_deps)
+        .witness(witness.as_bytes().pack())
+        .build();
+    context.complete_tx(tx)
+}
+
+/// A transaction submitting a branch of `length` ethash headers of `FULL_BRANCH_NONCES` to the
+/// light-client contract `contract_bin`
+fn full_branch_tx(context: &mut Context, contract_bin: Bytes, length: usize) -> TransactionView {
+    let epoch = ethash::epoch(FULL_BRANCH_PARENT + 1);
+    let items = ethash::dataset_items(epoch);
+    assert_eq!(ethash::merkle_depth(items), 26);
+    let (root, proof) = zero_dataset(items);
+    let parent = full_header(FULL_BRANCH_PARENT);
+    let mut headers: Vec<Header> = Vec::new();
+    for &nonce in &FULL_BRANCH_NONCES[..length] {
+        let header = seal_child(headers.last().unwrap_or(&parent), items, nonce);
+        headers.push(header);
+    }
+    let mut roots = [[0u8; 32]; ROOT_SLOTS];
+    roots[0] = root;
+    let mut dataset_items = [0u64; ROOT_SLOTS];
+    dataset_items[0] = items;
+    let input_state = LightClientState {
+        headers: HeaderChain::new(HeaderRecord::new(&parent), parent.difficulty),
+        roots: DatasetRoots {
+            first_epoch: epoch,
+            roots,
+            items: dataset_items,
+        },
+    };
+    let mut output_state = input_state.clone();
+    for header in &headers {
+        output_state.headers.push(header).expect("push");
+    }
+    update_tx(
+        context,
+        contract_bin,
+        1,
+        Consensus::Ethash(FORKS),
+        input_state.encode(),
+        output_state.encode(),
+        encode_branch(&parent, &headers, &proof),
+    )
+}
+
+/// A full branch of ethash headers fits in a transaction. Debug builds are not optimized, so the
+/// budget is only checked against the release build, `capsule test --release` of `make build`.
+#[test]
+fn test_submit_full_branch() {
+    let mut context = Context::default();
+    context.set_capture_debug(true);
+    let contract_bin: Bytes = Loader::default().load_binary("eth-light-client-typescript");
+    let tx = full_branch_tx(&mut context, contract_bin, MAX_BRANCH_HEADERS);
+
+    // run
+    let max_cycles = match TestEnv::from_env() {
+        TestEnv::Release => MAX_TX_CYCLES,
+        TestEnv::Debug => u64::MAX,
+    };
+    let result = context.verify_tx(&tx, max_cycles);
+    write_trace(&context, "test_submit_full_branch");
+    let cycles = result.expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+/// A valid branch of one more header than `MAX_BRANCH_HEADERS` does not fit in a transaction.
+/// Needs the release build of the light-client contract with the `unbounded-branch` feature,
+/// run it with `make test-branch-limit`
+#[test]
+#[ignore]
+fn test_submit_branch_over_limit() {
+    let mut context = Context::default();
+    let contract_bin: Bytes = Loader::unbounded_branch().load_binary("eth-light-client-typescript");
+    let tx = full_branch_tx(&mut context, contract_bin, MAX_BRANCH_HEADERS + 1);
+
+    // run, the branch is valid but runs out of the cycles of a transaction
+    let cycles = context
+        .verify_tx(&tx, u64::MAX)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+    assert!(cycles > MAX_TX_CYCLES);
+}
+
+/// The clique network of `test_submit_full_clique_branch`, with London from genesis
+const CLIQUE: CliqueConfig = CliqueConfig {
+    period: 15,
+    epoch: 30_000,
+    london: 0,
+};
+
+/// The parlia network of `test_submit_full_parlia_branch`, with every fork up to Bohr and
+/// London from genesis
+const PARLIA: ParliaConfig = ParliaConfig {
+    chain_id: 56,
+    epoch: 200,
+    luban: 0,
+    bohr: 0,
+    london: 0,
+};
+
+/// A signer of clique headers or a validator of parlia headers
+struct Signer {
+    address: [u8; 20],
+    secret: libsecp256k1::SecretKey,
+}
+
+/// `count` signers sorted by address
+fn new_signers(count: u8) -> Vec<Signer> {
+    let mut signers: Vec<Signer> = (1..=count)
+        .map(|index| {
+            let secret = libsecp256k1::SecretKey::parse(&[index; 32]).expect("secret");
+            let pubkey = libsecp256k1::PublicKey::from_secret_key(&secret);
+            let mut address = [0u8; 20];
+            address.copy_from_slice(&keccak256(&pubkey.serialize()[1..])[12..]);
+            Signer { address, secret }
+        })
+        .collect();
+    signers.sort_by_key(|signer| signer.address);
+    signers
+}
+
+/// The child of `parent` with the largest fields, `period` seconds later, whose extra data is
+/// the vanity followed by an empty seal
+fn unsealed_child(parent: &Header, period: u64) -> Header {
+    let mut header = full_header(parent.number + 1);
+    header.parent_hash = parent.hash;
+    header.timestamp = parent.timestamp + period;
+    header.extra_data = vec![0u8; EXTRA_VANITY + EXTRA_SEAL];
+    header
+}
+
+/// Seal `header` with the signature of its sealing `hash` by `signer` and update its hash
+fn seal(header: &mut Header, hash: &[u8; 32], signer: &Signer) {
+    let message = libsecp256k1::Message::parse(hash);
+    let (signature, recovery_id) = libsecp256k1::sign(&message, &signer.secret);
+    let seal = header.extra_data.len() - EXTRA_SEAL;
+    header.extra_data[seal..seal + 64].copy_from_slice(&signature.serialize());
+    header.extra_data[seal + 64] = recovery_id.serialize();
+    header.hash = keccak256(&header.encode());
+}
+
+/// Encode a branch of the sealed `headers` on top of `parent`
+fn encode_sealed_branch(parent: &Header, headers: &[Header]) -> Vec<u8> {
+    let mut items = Vec::new();
+    rlp::encode_bytes(&mut items, &parent.encode());
+    for header in headers {
+        rlp::encode_bytes(&mut items, &header.encode());
+    }
+    let mut branch = Vec::new();
+    rlp::encode_list(&mut branch, &items);
+    branch
+}
+
+/// A full branch of clique headers fits in a transaction, every header costs a secp256k1
+/// recovery. Checked against the release build as `test_submit_full_branch`.
+#[test]
+fn test_submit_full_clique_branch() {
+    let mut context = Context::default();
+    let contract_bin: Bytes = Loader::default().load_binary("eth-light-client-typescript");
+
+    // prepare the branch from a checkpoint of the largest set of signers
+    let signers = new_signers(MAX_SIGNERS as u8);
+    let mut checkpoint = full_header(0);
+    checkpoint.coinbase = [0u8; 20];
+    checkpoint.difficulty = 2;
+    checkpoint.extra_data = vec![0u8; EXTRA_VANITY];
+    for signer in &signers {
+        checkpoint.extra_data.extend_from_slice(&signer.address);
+    }
+    checkpoint.extra_data.extend_from_slice(&[0u8; EXTRA_SEAL]);
+    checkpoint.hash = keccak256(&checkpoint.encode());
+    let mut headers: Vec<Header> = Vec::new();
+    for _ in 0..MAX_SEALED_HEADERS {
+        let parent = headers.last().unwrap_or(&checkpoint);
+        let mut header = unsealed_child(parent, CLIQUE.period);
+        header.coinbase = [0u8; 20];
+        header.difficulty = 2;
+        header.base_fee_per_gas =
+            Some(consensus::base_fee(CLIQUE.london, parent).expect("base fee"));
+        let signer = &signers[(header.number % signers.len() as u64) as usize];
+        let hash = clique::seal_hash(&header);
+        seal(&mut header, &hash, signer);
+        headers.push(header);
+    }
+    let input_state = CliqueClientState {
+        headers: HeaderChain::new(HeaderRecord::new(&checkpoint), checkpoint.difficulty),
+        snapshot: clique::Snapshot::new(signers.iter().map(|signer| signer.address).collect())
+            .expect("snapshot"),
+        seals: vec![Seal::default()],
+    };
+    let output_state =
+        clique::apply_branch(&CLIQUE, &input_state, &checkpoint, &headers).expect("apply");
+    let tx = update_tx(
+        &mut context,
+        contract_bin,
+        5,
+        Consensus::Clique(CLIQUE),
+        input_state.encode(),
+        output_state.encode(),
+        encode_sealed_branch(&checkpoint, &headers),
+    );
+
+    // run
+    let max_cycles = match TestEnv::from_env() {
+        TestEnv::Release => MAX_TX_CYCLES,
+        TestEnv::Debug => u64::MAX,
+    };
+    let cycles = context
+        .verify_tx(&tx, max_cycles)
+        .expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+/// A full branch of parlia headers fits in a transaction, every header costs a secp256k1
+/// recovery. Checked against the release build as `test_submit_full_branch`.
+#[test]
+fn test_submit_full_parlia_branch() {
+    let mut context = Context::default();
+    let contract_bin: Bytes = Loader::default().load_binary("eth-light-client-typescript");
+
+    // prepare the branch from an epoch header of the largest set of validators
+    let validators = new_signers(MAX_VALIDATORS as u8);
+    let set = ValidatorSet::new(validators.iter().map(|signer| signer.address).collect(), 1)
+        .expect("validators");
+    let mut epoch_header = full_header(0);
+    epoch_header.coinbase = validators[0].address;
+    epoch_header.difficulty = 2;
+    epoch_header.base_fee_per_gas = Some(0);
+    let mut extra_data = vec![0u8; EXTRA_VANITY];
+    extra_data.push(validators.len() as u8);
+    for validator in &validators {
+        // the address and the BLS public key
+        extra_data.extend_from_slice(&validator.address);
+        extra_data.extend_from_slice(&[0u8; 48]);
+    }
+    extra_data.push(set.turn_length);
+    extra_data.extend_from_slice(&[0u8; EXTRA_SEAL]);
+    epoch_header.extra_data = extra_data;
+    let hash = parlia::seal_hash(PARLIA.chain_id, &epoch_header).expect("seal hash");
+    seal(&mut epoch_header, &hash, &validators[0]);
+    let mut headers: Vec<Header> = Vec::new();
+    for _ in 0..MAX_SEALED_HEADERS {
+        let parent = headers.last().unwrap_or(&epoch_header);
+        let mut header = unsealed_child(parent, 3);
+        let validator = &validators[(header.number % validators.len() as u64) as usize];
+        header.coinbase = validator.address;
+        header.difficulty = 2;
+        header.base_fee_per_gas = Some(0);
+        let hash = parlia::seal_hash(PARLIA.chain_id, &header).expect("seal hash");
+        seal(&mut header, &hash, validator);
+        headers.push(header);
+    }
+    let mut snapshot = parlia::Snapshot::new(set.clone());
+    snapshot.apply(&PARLIA, 0, epoch_header.coinbase, Some(set));
+    let input_state = ParliaClientState {
+        headers: HeaderChain::new(HeaderRecord::new(&epoch_header), epoch_header.difficulty),
+        snapshot,
+        signers: vec![epoch_header.coinbase],
+        listed: None,
+    };
+    let output_state =
+        parlia::apply_branch(&PARLIA, &input_state, &epoch_header, &headers).expect("apply");
+    let tx = update_tx(
+        &mut context,
+        contract_bin,
+        PARLIA.chain_id,
+        Consensus::Parlia(PARLIA),
+        input_state.encode(),
+        output_state.encode(),
+        encode_sealed_branch(&epoch_header, &headers),
+    );
+
     // run
+    let max_cycles = match TestEnv::from_env() {
+        TestEnv::Release => MAX_TX_CYCLES,
+        TestEnv::Debug => u64::MAX,
+    };
     let cycles = context
-        .verify_tx(&tx, MAX_CYCLES)
+        .verify_tx(&tx, max_cycles)
         .expect("pass verification");
     println!("consume cycles: {}", cycles);
 }